@@ -38,6 +38,9 @@
 //! clang-tidy -checks=cert-* -warnings-as-errors=* main.cpp -- | clang-tidy-sarif
 //! ```
 //!
+//! Pass `--fingerprint` to add the `partialFingerprints` Github code scanning
+//! tracks alerts by, reading the source files `clang-tidy` reports.
+//!
 //! If you are using Github Actions, SARIF is useful for integrating with
 //! Github Advanced Security (GHAS), which can show code alerts in the
 //! "Security" tab of your repository.
@@ -81,6 +84,8 @@
 
 use anyhow::Result;
 use clap::Parser;
+use serde_sarif::converters::clang_tidy::ClangTidyConverter;
+use serde_sarif::converters::{convert, ConvertOptions};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 
@@ -96,6 +101,10 @@ struct Args {
   input: Option<std::path::PathBuf>,
   /// output file; writes to stdout if none is given
   output: Option<std::path::PathBuf>,
+  /// add partialFingerprints to each result, reading the source files the
+  /// results refer to
+  #[arg(long)]
+  fingerprint: bool,
 }
 
 fn main() -> Result<()> {
@@ -113,5 +122,12 @@ fn main() -> Result<()> {
   };
   let writer = BufWriter::new(write);
 
-  serde_sarif::converters::clang_tidy::parse_to_writer(reader, writer)
+  let options = if args.fingerprint {
+    ConvertOptions::new().fingerprints(".")
+  } else {
+    ConvertOptions::new()
+  };
+
+  convert(&ClangTidyConverter, reader, writer, &options)?;
+  Ok(())
 }
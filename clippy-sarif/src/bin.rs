@@ -38,6 +38,14 @@
 //! cargo clippy --message-format=json | clippy-sarif
 //! ```
 //!
+//! Pass `--fingerprint` to add the `partialFingerprints` Github code scanning
+//! tracks alerts by. Run it from the directory `cargo clippy` was run in, as clippy
+//! reports paths relative to the workspace.
+//!
+//! Each rule is tagged with its lint group, ex. `pedantic`, when clippy names
+//! the group which enabled the lint, and carries a `problem.severity` property
+//! which Github code scanning shows as the severity of its alerts.
+//!
 //! If you are using Github Actions, SARIF is useful for integrating with
 //! Github Advanced Security (GHAS), which can show code alerts in the
 //! "Security" tab of your repository.
@@ -82,6 +90,8 @@
 
 use anyhow::Result;
 use clap::Parser;
+use serde_sarif::converters::clippy::ClippyConverter;
+use serde_sarif::converters::{convert, ConvertOptions};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 
@@ -99,6 +109,10 @@ struct Args {
   /// output file; writes to stdout if none is given
   #[arg(short, long)]
   output: Option<std::path::PathBuf>,
+  /// add partialFingerprints to each result, reading the source files the
+  /// results refer to
+  #[arg(long)]
+  fingerprint: bool,
 }
 
 fn main() -> Result<()> {
@@ -116,5 +130,12 @@ fn main() -> Result<()> {
   };
   let writer = BufWriter::new(write);
 
-  serde_sarif::converters::clippy::parse_to_writer(reader, writer)
+  let options = if args.fingerprint {
+    ConvertOptions::new().fingerprints(".")
+  } else {
+    ConvertOptions::new()
+  };
+
+  convert(&ClippyConverter, reader, writer, &options)?;
+  Ok(())
 }
@@ -0,0 +1,290 @@
+#![doc(html_root_url = "https://docs.rs/sarif-cli/0.3.4")]
+
+//! # WARNING: VERY UNSTABLE (EARLY IMPLEMENTATION)
+//!
+//! This crate provides a command line tool, `sarif`, to validate and
+//! manipulate SARIF files.
+//!
+//! The latest [documentation can be found here](https://docs.rs/sarif_cli).
+//!
+//! SARIF or the Static Analysis Results Interchange Format is an industry
+//! standard format for the output of static analysis tools. More information
+//! can be found on the official website: [https://sarifweb.azurewebsites.net/](https://sarifweb.azurewebsites.net/).
+//!
+//! ## Installation
+//!
+//! `sarif` may be installed via `cargo`
+//!
+//! ```shell
+//! cargo install sarif-cli
+//! ```
+//!
+//! or downloaded directly from Github Releases
+//!
+//!```shell
+//! # make sure to adjust the target and version (you may also want to pin to a specific version)
+//! curl -sSL https://github.com/psastras/sarif-rs/releases/download/sarif-latest/sarif-x86_64-unknown-linux-gnu -o sarif
+//! ```
+//!
+//! ## Usage
+//!
+//! ### validate
+//!
+//! Checks a SARIF file against the SARIF 2.1.0 JSON schema as well as the
+//! semantic rules of the specification the schema cannot express (ex. a
+//! `ruleIndex` referring to a rule which does not exist). Each violation is
+//! printed to stderr and the command exits with a non-zero status if there
+//! are schema violations or error level rule violations.
+//!
+//!```shell
+//! $ sarif validate foo.sarif
+//! error[rule-index-out-of-range]: /runs/0/results/3/ruleIndex: rule index 7 is out of range, /tool/driver/rules has 5 rules
+//! warning[undefined-uri-base-id]: /runs/0/results/3/locations/0/physicalLocation/artifactLocation/uriBaseId: uriBaseId "SRCROOT" is not defined in originalUriBaseIds
+//! ```
+//!
+//! ### merge
+//!
+//! Merges several SARIF files into one, ex. to upload the output of several
+//! tools to Github code scanning at once. By default the runs of each file are
+//! kept side by side; with `--coalesce` the runs of the same tool are combined
+//! into one run, with their rules unioned and every index re-numbered. Runs
+//! of the same tool which count columns in different units (`columnKind`)
+//! cannot be coalesced.
+//!
+//!```shell
+//! $ sarif merge clippy.sarif shellcheck.sarif hadolint.sarif -o merged.sarif
+//! ```
+//!
+//! ### upgrade
+//!
+//! Upgrades a SARIF 1.0.0 file, or one of a pre-release 2.0.0 draft, to
+//! SARIF 2.1.0. `merge` upgrades its inputs automatically.
+//!
+//!```shell
+//! $ sarif upgrade legacy.sarif -o upgraded.sarif
+//! ```
+//!
+//! ### fix
+//!
+//! Applies the fixes of results to the files they change. Relative artifact
+//! locations are resolved through the `originalUriBaseIds` of the run, or
+//! `--uri-base`, and against the current directory otherwise. A fix which
+//! overlaps a fix applied before is skipped with a warning. `--dry-run`
+//! prints the changes as a unified diff instead of writing them, and
+//! `--rule` only applies the fixes of matching rules.
+//!
+//!```shell
+//! $ shellcheck -f json script.sh | shellcheck-sarif | sarif fix --dry-run
+//! --- a/script.sh
+//! +++ b/script.sh
+//! @@ -1,2 +1,2 @@
+//!  #!/bin/sh
+//! -echo $1
+//! +echo "$1"
+//! $ sarif fix --rule '2086' shellcheck.sarif
+//! applied 1 fixes to 1 files
+//! ```
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use serde_sarif::fix::Fixer;
+use serde_sarif::merge::{merge, MergeStrategy};
+use serde_sarif::query::Query;
+use serde_sarif::sarif;
+use serde_sarif::upgrade::upgrade;
+use serde_sarif::uri::{parse_uri_base, UriResolver, Url};
+use serde_sarif::validate::report;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+  version,
+  about = "Validate and manipulate SARIF files",
+  long_about = None
+)]
+struct Args {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Validate a SARIF file against the schema and semantic rules; exits with
+  /// a non-zero status if the input is invalid
+  Validate {
+    /// input file; reads from stdin if none is given
+    input: Option<PathBuf>,
+  },
+  /// Merge several SARIF files into one
+  Merge {
+    /// input files
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// output file; writes to stdout if none is given
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// combine the runs of the same tool (tool.driver.name) into one run
+    #[arg(long)]
+    coalesce: bool,
+  },
+  /// Upgrade a SARIF 1.0.0 or 2.0.0 draft file to SARIF 2.1.0
+  Upgrade {
+    /// input file; reads from stdin if none is given
+    input: Option<PathBuf>,
+    /// output file; writes to stdout if none is given
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+  },
+  /// Apply the fixes of results to the files they change
+  Fix {
+    /// input file; reads from stdin if none is given
+    input: Option<PathBuf>,
+    /// print the changes as a unified diff instead of writing them
+    #[arg(long)]
+    dry_run: bool,
+    /// only apply the fixes of results whose rule id matches this glob, ex.
+    /// '2086' for shellcheck-sarif, may be given several times
+    #[arg(long, value_name = "GLOB")]
+    rule: Vec<String>,
+    /// defines a uriBaseId which relative artifact locations refer to, ex.
+    /// SRCROOT=/src; takes precedence over the originalUriBaseIds of the
+    /// input, may be given several times
+    #[arg(long, value_name = "ID=URI", value_parser = parse_uri_base)]
+    uri_base: Vec<(String, Url)>,
+  },
+}
+
+fn writer(output: Option<PathBuf>) -> Result<BufWriter<Box<dyn Write>>> {
+  let write = match output {
+    Some(path) => Box::new(File::create(path)?) as Box<dyn Write>,
+    None => Box::new(std::io::stdout()) as Box<dyn Write>,
+  };
+  Ok(BufWriter::new(write))
+}
+
+fn reader(input: Option<PathBuf>) -> Result<BufReader<Box<dyn Read>>> {
+  let read = match input {
+    Some(path) => Box::new(File::open(path)?) as Box<dyn Read>,
+    None => Box::new(std::io::stdin()) as Box<dyn Read>,
+  };
+  Ok(BufReader::new(read))
+}
+
+// Returns `uri:line` of the first location of a result, for warnings
+fn describe_location(result: &sarif::Result) -> String {
+  let physical_location = result
+    .locations
+    .iter()
+    .flatten()
+    .find_map(|location| location.physical_location.as_ref());
+  let uri = physical_location
+    .and_then(|location| location.artifact_location.as_ref()?.uri.as_deref())
+    .unwrap_or("<unknown>");
+  match physical_location
+    .and_then(|location| location.region.as_ref()?.start_line)
+  {
+    Some(line) => format!("{}:{}", uri, line),
+    None => uri.to_string(),
+  }
+}
+
+// Applies the fixes of the results matching the query, or prints them if
+// `dry_run`
+fn fix(
+  sarif: &sarif::Sarif,
+  query: &Query,
+  resolver: &UriResolver,
+  dry_run: bool,
+) -> Result<()> {
+  let mut fixer = Fixer::new(resolver);
+  for run in &sarif.runs {
+    for result in run.results.iter().flatten() {
+      if !query.matches(run, result) {
+        continue;
+      }
+      if let Err(err) = fixer.add(run, result) {
+        eprintln!(
+          "warning: skipped the fix of {} at {}: {}",
+          result.rule_id.as_deref().unwrap_or("<unknown>"),
+          describe_location(result),
+          err
+        );
+      }
+    }
+  }
+  if dry_run {
+    let mut writer = writer(None)?;
+    for (_, diff) in fixer.diffs(3)? {
+      write!(writer, "{}", diff)?;
+    }
+    writer.flush()?;
+  } else {
+    fixer.write()?;
+    eprintln!(
+      "applied {} fixes to {} files",
+      fixer.fixes(),
+      fixer.paths().count()
+    );
+  }
+  Ok(())
+}
+
+fn main() -> Result<()> {
+  let args = Args::parse();
+
+  match args.command {
+    Command::Validate { input } => {
+      if !report(reader(input)?, std::io::stderr())? {
+        std::process::exit(1);
+      }
+    }
+    Command::Merge {
+      inputs,
+      output,
+      coalesce,
+    } => {
+      let logs = inputs
+        .into_iter()
+        .map(|input| -> Result<sarif::Sarif> {
+          Ok(upgrade(serde_json::from_reader(reader(Some(input))?)?)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+      let strategy = if coalesce {
+        MergeStrategy::CoalesceByTool
+      } else {
+        MergeStrategy::SideBySide
+      };
+      let mut writer = writer(output)?;
+      serde_json::to_writer_pretty(&mut writer, &merge(logs, strategy)?)?;
+      writer.flush()?;
+    }
+    Command::Upgrade { input, output } => {
+      let sarif = upgrade(serde_json::from_reader(reader(input)?)?)?;
+      let mut writer = writer(output)?;
+      serde_json::to_writer_pretty(&mut writer, &sarif)?;
+      writer.flush()?;
+    }
+    Command::Fix {
+      input,
+      dry_run,
+      rule,
+      uri_base,
+    } => {
+      let sarif = upgrade(serde_json::from_reader(reader(input)?)?)?;
+      let resolver = uri_base.into_iter().fold(
+        UriResolver::new().default_base(
+          Url::from_directory_path(std::env::current_dir()?)
+            .map_err(|_| anyhow::anyhow!("Invalid current directory"))?,
+        ),
+        |resolver, (id, base)| resolver.uri_base(id, base),
+      );
+      let query = rule
+        .iter()
+        .fold(Query::new(), |query, rule| query.rule(rule));
+      fix(&sarif, &query, &resolver, dry_run)?;
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use std::fs;
+
+// Runs `sarif` with the arguments, writing each of `inputs` to a file in a
+// temporary directory whose path is passed in place of `{}`, and returns its
+// exit code, stdout and stderr
+fn sarif(
+  args: &[&str],
+  inputs: &[serde_json::Value],
+) -> Result<(Option<i32>, String, String)> {
+  let dir = tempfile::tempdir()?;
+  let mut paths = vec![];
+  for (i, input) in inputs.iter().enumerate() {
+    let path = dir.path().join(format!("{}.sarif", i));
+    fs::write(&path, serde_json::to_string(input)?)?;
+    paths.push(path.to_string_lossy().into_owned());
+  }
+  let mut paths = paths.into_iter();
+  let args: Vec<String> = args
+    .iter()
+    .map(|arg| match *arg {
+      "{}" => paths.next().unwrap(),
+      arg => arg.to_string(),
+    })
+    .collect();
+  let output = duct::cmd(env!("CARGO_BIN_EXE_sarif"), args)
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+  Ok((
+    output.status.code(),
+    String::from_utf8(output.stdout)?,
+    String::from_utf8(output.stderr)?,
+  ))
+}
+
+fn log(tool: &str, rule: &str) -> serde_json::Value {
+  serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": tool, "rules": [{ "id": rule }] } },
+      "results": [{ "ruleIndex": 0, "message": { "text": rule } }]
+    }]
+  })
+}
+
+#[test]
+// Test that validate reports violations and exits with a non-zero status
+fn test_validate() -> Result<()> {
+  let (code, _, stderr) = sarif(&["validate", "{}"], &[log("a", "A")])?;
+  assert_eq!((code, stderr.as_str()), (Some(0), ""));
+  let mut invalid = log("a", "A");
+  invalid["runs"][0]["results"][0]["ruleIndex"] = 1.into();
+  let (code, _, stderr) = sarif(&["validate", "{}"], &[invalid])?;
+  assert_eq!(code, Some(1));
+  assert!(stderr.contains("error[rule-index-out-of-range]"));
+  Ok(())
+}
+
+#[test]
+// Test that merge keeps the runs side by side, or coalesces those of a tool
+fn test_merge() -> Result<()> {
+  let inputs = [log("a", "A"), log("a", "B"), log("b", "A")];
+  let (code, stdout, _) = sarif(&["merge", "{}", "{}", "{}"], &inputs)?;
+  assert_eq!(code, Some(0));
+  let merged: serde_json::Value = serde_json::from_str(&stdout)?;
+  assert_eq!(merged["runs"].as_array().map(Vec::len), Some(3));
+  let (code, stdout, _) =
+    sarif(&["merge", "--coalesce", "{}", "{}", "{}"], &inputs)?;
+  assert_eq!(code, Some(0));
+  let merged: serde_json::Value = serde_json::from_str(&stdout)?;
+  assert_eq!(merged["runs"].as_array().map(Vec::len), Some(2));
+  assert_eq!(merged["runs"][0]["results"][1]["ruleIndex"], 1);
+  Ok(())
+}
+
+#[test]
+// Test that upgrade converts a SARIF 1.0.0 log to 2.1.0
+fn test_upgrade() -> Result<()> {
+  let legacy = serde_json::json!({
+    "version": "1.0.0",
+    "runs": [{
+      "tool": { "name": "legacy" },
+      "results": [{ "ruleId": "C2001", "message": "wrong" }]
+    }]
+  });
+  let (code, stdout, _) = sarif(&["upgrade", "{}"], &[legacy])?;
+  assert_eq!(code, Some(0));
+  let upgraded: serde_json::Value = serde_json::from_str(&stdout)?;
+  assert_eq!(upgraded["version"], "2.1.0");
+  assert_eq!(upgraded["runs"][0]["tool"]["driver"]["name"], "legacy");
+  assert_eq!(
+    upgraded["runs"][0]["results"][0]["message"]["text"],
+    "wrong"
+  );
+  Ok(())
+}
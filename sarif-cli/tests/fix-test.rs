@@ -0,0 +1,140 @@
+use anyhow::Result;
+use serde_sarif::uri::Url;
+use std::fs;
+
+// A result of `rule` which replaces the columns `start..end` of line 1 of
+// main.sh with `text`
+fn fixed_result(
+  rule: &str,
+  start: i64,
+  end: i64,
+  text: &str,
+) -> serde_json::Value {
+  serde_json::json!({
+    "ruleId": rule,
+    "message": { "text": "fix me" },
+    "locations": [{
+      "physicalLocation": {
+        "artifactLocation": { "uri": "main.sh", "uriBaseId": "SRCROOT" },
+        "region": { "startLine": 1, "startColumn": start }
+      }
+    }],
+    "fixes": [{ "artifactChanges": [{
+      "artifactLocation": { "uri": "main.sh", "uriBaseId": "SRCROOT" },
+      "replacements": [{
+        "deletedRegion": {
+          "startLine": 1, "startColumn": start, "endColumn": end
+        },
+        "insertedContent": { "text": text }
+      }]
+    }]}]
+  })
+}
+
+// Runs `sarif fix` on a log of `results` with main.sh in a temporary
+// directory, returning the exit code, stdout, stderr and the contents of
+// main.sh afterwards
+fn fix(
+  results: Vec<serde_json::Value>,
+  args: &[&str],
+) -> Result<(Option<i32>, String, String, String)> {
+  let dir = tempfile::tempdir()?;
+  let script = dir.path().join("main.sh");
+  fs::write(&script, "echo $1 $2\n")?;
+  let input = dir.path().join("input.sarif");
+  fs::write(
+    &input,
+    serde_json::to_string(&serde_json::json!({
+      "version": "2.1.0",
+      "runs": [{
+        "tool": { "driver": { "name": "shellcheck" } },
+        "results": results
+      }]
+    }))?,
+  )?;
+  let base = Url::from_directory_path(dir.path()).unwrap();
+  let uri_base = format!("SRCROOT={}", base);
+  let mut command = vec!["fix", "--uri-base", &uri_base];
+  command.extend(args);
+  command.push(input.to_str().unwrap());
+  let output = duct::cmd(env!("CARGO_BIN_EXE_sarif"), command)
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+  Ok((
+    output.status.code(),
+    String::from_utf8(output.stdout)?,
+    String::from_utf8(output.stderr)?,
+    fs::read_to_string(&script)?,
+  ))
+}
+
+#[test]
+// Test that the fixes are written to the files they change
+fn test_fix() -> Result<()> {
+  let (code, stdout, stderr, contents) = fix(
+    vec![
+      fixed_result("2086", 6, 8, "\"$1\""),
+      fixed_result("2086", 9, 11, "\"$2\""),
+    ],
+    &[],
+  )?;
+  assert_eq!(code, Some(0));
+  assert_eq!(stdout, "");
+  assert_eq!(stderr, "applied 2 fixes to 1 files\n");
+  assert_eq!(contents, "echo \"$1\" \"$2\"\n");
+  Ok(())
+}
+
+#[test]
+// Test that a dry run prints a unified diff without changing the files
+fn test_fix_dry_run() -> Result<()> {
+  let (code, stdout, stderr, contents) =
+    fix(vec![fixed_result("2086", 6, 8, "\"$1\"")], &["--dry-run"])?;
+  assert_eq!(code, Some(0));
+  assert_eq!(
+    stdout,
+    "--- a/main.sh\n+++ b/main.sh\n@@ -1,1 +1,1 @@\n-echo $1 $2\n\
+     +echo \"$1\" $2\n"
+  );
+  assert_eq!(stderr, "");
+  assert_eq!(contents, "echo $1 $2\n");
+  Ok(())
+}
+
+#[test]
+// Test that a fix which overlaps one applied before is skipped with a warning
+fn test_fix_overlap() -> Result<()> {
+  let (code, _, stderr, contents) = fix(
+    vec![
+      fixed_result("2086", 6, 8, "\"$1\""),
+      fixed_result("2116", 1, 8, "printf"),
+    ],
+    &[],
+  )?;
+  assert_eq!(code, Some(0));
+  assert_eq!(
+    stderr,
+    "warning: skipped the fix of 2116 at main.sh:1: replacements overlap at \
+     byte 0\napplied 1 fixes to 1 files\n"
+  );
+  assert_eq!(contents, "echo \"$1\" $2\n");
+  Ok(())
+}
+
+#[test]
+// Test that only the fixes of the rules given are applied
+fn test_fix_rule() -> Result<()> {
+  let (code, _, stderr, contents) = fix(
+    vec![
+      fixed_result("2086", 6, 8, "\"$1\""),
+      fixed_result("2116", 9, 11, "x"),
+    ],
+    &["--rule", "2086"],
+  )?;
+  assert_eq!(code, Some(0));
+  assert_eq!(stderr, "applied 1 fixes to 1 files\n");
+  assert_eq!(contents, "echo \"$1\" $2\n");
+  Ok(())
+}
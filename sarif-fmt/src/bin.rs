@@ -30,6 +30,45 @@
 //!
 //! For most cases, simply pipe a SARIF file into `sarif-fmt` (`cat ./foo.sarif | sarif-fmt`)
 //!
+//! To only show the results which are new relative to a baseline, ex. the
+//! SARIF file of the target branch of a pull request, pass the baseline with
+//! `--baseline` (`cat ./new.sarif | sarif-fmt --baseline ./old.sarif`)
+//!
+//! Runs which keep their results or rules in external property files
+//! (`externalPropertyFileReferences`) are resolved transparently; relative
+//! locations are resolved against the directory of the input file. Logs of
+//! SARIF 1.0.0 and of the 2.0.0 drafts are upgraded to 2.1.0.
+//!
+//! Relative artifact locations are resolved against the `originalUriBaseIds`
+//! of the input. Base ids it leaves undefined, or which point to another
+//! machine, can be given with `--uri-base`
+//! (`cat ./foo.sarif | sarif-fmt --uri-base SRCROOT=/src`).
+//!
+//! Results can be filtered by their level, rule, file and tool with
+//! `--level`, `--rule`, `--path` and `--tool`, each of which may be given
+//! several times. Rules and paths are globs
+//! (`cat ./foo.sarif | sarif-fmt --level error --path 'src/**/*.rs'`).
+//!
+//! Messages and rule descriptions are shown in another language with
+//! `--lang` (`cat ./foo.sarif | sarif-fmt --lang de-DE`), if the input
+//! provides translations into it, either inline or in translation files it
+//! refers to.
+//!
+//! The taxa a result is categorized by, ex. CWE weaknesses, are shown next
+//! to it.
+//!
+//! The code flows of a result, ex. the path tainted data takes from a source
+//! to a sink, are shown after it as numbered steps, indented by their nesting
+//! level and labelled with their kinds, ex. `call` or `return`. Steps which
+//! are less important than `--importance` are left out
+//! (`cat ./foo.sarif | sarif-fmt --importance important`).
+//!
+//! With `--show-fixes`, the fixes a result proposes, ex. those of shellcheck,
+//! are shown after it as unified diffs of the files on disk, each headed by the
+//! description of the fix (`cat ./foo.sarif | sarif-fmt --show-fixes`). In
+//! `--plain` mode the fixes of a run are shown after its diagnostics, each
+//! headed by the location of its result.
+//!
 //! ## Example
 //!
 //!```shell
@@ -134,613 +173,600 @@ use codespan_reporting::term::termcolor::ColorChoice;
 use codespan_reporting::term::termcolor::ColorSpec;
 use codespan_reporting::term::termcolor::StandardStream;
 use codespan_reporting::term::termcolor::WriteColor;
+use serde_sarif::baseline::compare;
+use serde_sarif::external::resolve;
+use serde_sarif::fix::{edits, unified_diff};
+use serde_sarif::flow::steps;
+use serde_sarif::localize::{load_translations, Localizer, DEFAULT_LANGUAGE};
+use serde_sarif::message::LinkTarget;
+use serde_sarif::query::{resolve_level, Query};
+use serde_sarif::region::{ColumnUnit, SourceText};
 use serde_sarif::sarif;
-use serde_sarif::sarif::ResultLevel;
+use serde_sarif::sarif::{ResultLevel, ThreadFlowLocationImportance};
+use serde_sarif::stream::{ResultReader, RunResult};
+use serde_sarif::taxonomy::taxa;
+use serde_sarif::upgrade::{detect_reader, upgrade, SourceVersion};
+use serde_sarif::uri::{parse_uri_base, UriError, UriResolver, Url};
+use serde_sarif::validate::report;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::Write;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufReader, Read};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
-use std::str::FromStr;
-use std::usize;
-
-fn process<R: BufRead>(mut reader: R) -> Result<sarif::Sarif> {
-  let mut data = String::new();
-  reader.read_to_string(&mut data)?;
-  let s: sarif::Sarif = serde_json::from_str(&data)?;
-  Ok(s)
-}
-
-fn try_find_file(
-  physical_location: &sarif::PhysicalLocation,
-  run: &sarif::Run,
-) -> Result<PathBuf> {
-  let artifact_location = physical_location
-    .artifact_location
-    .as_ref()
-    .map_or_else(|| Err(anyhow::anyhow!("No artifact location.")), Ok)?;
-  let uri = artifact_location
-    .uri
-    .as_ref()
-    .map_or_else(|| Err(anyhow::anyhow!("No uri.")), Ok)?;
-  let path = Path::new(uri);
-
-  // if the path is absolute and exists, return it, else
-  if path.is_absolute() {
-    if path.exists() {
-      return Ok(path.to_path_buf());
+use std::sync::Arc;
+
+type ResultItem = serde_json::Result<RunResult>;
+
+// Results are streamed rather than deserialized all at once to keep memory
+// bounded on large logs. The reader makes two passes over the input, so
+// stdin is first copied into a temporary file. Logs of older SARIF versions,
+// and logs whose runs refer to external property files, are read whole, as
+// they need to be upgraded or their results may live in other files.
+fn process(
+  input: Option<PathBuf>,
+) -> Result<Box<dyn Iterator<Item = ResultItem>>> {
+  let base = base_dir(input.as_deref());
+  let mut file = match input {
+    Some(path) => File::open(path)?,
+    None => {
+      let mut file = tempfile::tempfile()?;
+      std::io::copy(&mut std::io::stdin(), &mut file)?;
+      file.seek(SeekFrom::Start(0))?;
+      file
+    }
+  };
+  let version = detect_reader(BufReader::new(&mut file))?;
+  file.seek(SeekFrom::Start(0))?;
+  if version == SourceVersion::V2_1_0 {
+    let reader = ResultReader::new(file.try_clone()?)?;
+    if reader
+      .runs()
+      .iter()
+      .all(|run| run.external_property_file_references.is_none())
+    {
+      return Ok(Box::new(reader));
     }
-    return Err(anyhow::anyhow!("Path does not exist"));
+    file.seek(SeekFrom::Start(0))?;
   }
+  let sarif = read_log(file, &base)?;
+  Ok(Box::new(run_results(sarif, |_| true).into_iter().map(Ok)))
+}
+
+// Reads a whole log, upgrading it to SARIF 2.1.0 and inlining its external
+// property files
+fn read_log<R: Read>(read: R, base: &Path) -> Result<sarif::Sarif> {
+  let value: serde_json::Value = serde_json::from_reader(BufReader::new(read))?;
+  let mut sarif = upgrade(value)?;
+  resolve(&mut sarif, base)?;
+  Ok(sarif)
+}
+
+// Relative external property file locations are resolved against the
+// directory of the log
+fn base_dir(input: Option<&Path>) -> PathBuf {
+  input
+    .and_then(Path::parent)
+    .map(Path::to_path_buf)
+    .unwrap_or_default()
+}
 
-  // if it's a relative path, it's more complicated and dictated by the SARIF spec
-
-  // 1. check if uri base id exists -- it SHOULD exist, but may not
-  if let Some(uri_base_id) = artifact_location.uri_base_id.as_ref() {
-    // check if this is defined in originalUriBaseIds
-    if let Some(original_uri_base_ids) = run.original_uri_base_ids.as_ref() {
-      let mut path = PathBuf::new();
-      if let Some(uri_base) = original_uri_base_ids.get(uri_base_id) {
-        // todo: this doesn't handle recursive uri_base_id...
-        if let Some(uri) = uri_base.uri.as_ref() {
-          path.push(uri);
+fn run_results(
+  sarif: sarif::Sarif,
+  filter: impl Fn(&sarif::Result) -> bool,
+) -> Vec<RunResult> {
+  let mut run_results = vec![];
+  sarif
+    .runs
+    .into_iter()
+    .enumerate()
+    .for_each(|(run_index, mut run)| {
+      let results = run.results.take().unwrap_or_default();
+      let run = Arc::new(run);
+      run_results.extend(results.into_iter().filter(&filter).map(|result| {
+        RunResult {
+          run_index,
+          run: run.clone(),
+          result,
         }
-      }
-      path.push(uri);
-      if path.exists() {
-        return Ok(path);
-      }
-    }
+      }));
+    });
+  run_results
+}
+
+// Comparing with a baseline requires the whole log, so it is not streamed;
+// returns the results which are new relative to the baseline
+fn process_with_baseline(
+  input: Option<PathBuf>,
+  baseline: PathBuf,
+) -> Result<Vec<RunResult>> {
+  let base = base_dir(input.as_deref());
+  let read = match input {
+    Some(path) => Box::new(File::open(path)?) as Box<dyn Read>,
+    None => Box::new(std::io::stdin()) as Box<dyn Read>,
+  };
+  let mut sarif = read_log(read, &base)?;
+  let baseline = read_log(File::open(&baseline)?, &base_dir(Some(&baseline)))?;
+  compare(&mut sarif, &baseline);
+
+  Ok(run_results(sarif, |result| {
+    result.baseline_state == Some(sarif::ResultBaselineState::New)
+  }))
+}
 
-    // just check if the relative path exists by chance
-    if path.exists() {
-      return Ok(path.to_path_buf());
+fn try_find_file(
+  artifact_location: &sarif::ArtifactLocation,
+  run: &sarif::Run,
+  resolver: &UriResolver,
+) -> Result<PathBuf> {
+  let path = match resolver.resolve_path(run, artifact_location) {
+    Ok(path) => path,
+    // the base id may only have been known on the machine which produced
+    // the log, so check if the uri exists relative to the current directory
+    Err(UriError::UndefinedUriBaseId(_)) => {
+      let mut artifact_location = artifact_location.clone();
+      artifact_location.uri_base_id = None;
+      resolver.resolve_path(run, &artifact_location)?
     }
-  }
+    Err(err) => return Err(err.into()),
+  };
   if path.exists() {
-    Ok(path.to_path_buf())
+    Ok(path)
   } else {
     Err(anyhow::anyhow!("Path not found: {:#?}", path))
   }
 }
 
-fn get_physical_location_contents(
-  physical_location: &sarif::PhysicalLocation,
-  run: &sarif::Run,
-) -> Result<String> {
-  let path = try_find_file(physical_location, run)?;
-  let mut file = File::open(path)?;
-  let mut contents = String::new();
-  file.read_to_string(&mut contents)?;
-  Ok(contents)
+// The files which locations are rendered from, each of which is read and
+// added once however many locations refer to it
+struct SourceFiles {
+  files: SimpleFiles<String, String>,
+  ids: HashMap<PathBuf, usize>,
 }
 
-fn try_get_byte_offset(
-  file_id: usize,
-  files: &SimpleFiles<&String, String>,
-  row: i64,
-  column: i64,
-) -> Result<usize> {
-  files
-    .line_range(file_id, row as usize - 1)?
-    .find(|byte| {
-      if let Ok(location) = files.location(file_id, *byte) {
-        location.column_number == column as usize
-      } else {
-        false
-      }
-    })
-    .ok_or_else(|| anyhow::anyhow!("Byte offset not found"))
-}
-
-fn get_byte_range(
-  file_id: usize,
-  files: &SimpleFiles<&String, String>,
-  region: &sarif::Region,
-) -> (Option<usize>, Option<usize>) {
-  // todo: support character regions
-  let byte_offset = if let Some(byte_offset) = region.byte_offset {
-    Some(byte_offset as usize)
-  } else if let (Some(start_line), Some(start_column)) =
-    (region.start_line, region.start_column.or(Some(1)))
-  {
-    if let Ok(byte_offset) =
-      try_get_byte_offset(file_id, files, start_line, start_column)
-    {
-      Some(byte_offset)
-    } else {
-      None
+impl SourceFiles {
+  fn new() -> Self {
+    SourceFiles {
+      files: SimpleFiles::new(),
+      ids: HashMap::new(),
     }
-  } else {
-    None
-  };
+  }
 
-  let byte_end = if let Some(byte_offset) = byte_offset {
-    if let Some(byte_length) = region.byte_length {
-      Some(byte_offset + byte_length as usize)
-    } else if let (Some(end_line), Some(end_column)) = (
-      region.end_line.map_or_else(|| region.start_line, Some), // if no end_line, default to start_line
-      region.end_column.map_or_else(
-        // if no end column use the line's last column
-        || {
-          region
-            .end_line
-            .map_or_else(|| region.start_line, Some)
-            .and_then(|start_line| {
-              files
-                .line_range(file_id, start_line as usize - 1)
-                .map_or(None, Option::from)
-                .and_then(|byte_range| {
-                  byte_range.last().and_then(|last_byte| {
-                    files
-                      .column_number(
-                        file_id,
-                        start_line as usize - 1,
-                        last_byte,
-                      )
-                      .map_or(None, |v| Option::from(v as i64))
-                  })
-                })
-            })
-        },
-        Some,
-      ),
-    ) {
-      if let Ok(byte_offset) =
-        try_get_byte_offset(file_id, files, end_line, end_column)
-      {
-        Some(byte_offset)
-      } else {
-        Some(byte_offset)
-      }
-    } else {
-      Some(byte_offset)
+  // Returns the id of the file of an artifact location, reading the file
+  // the first time it is referred to
+  fn id(
+    &mut self,
+    artifact_location: &sarif::ArtifactLocation,
+    run: &sarif::Run,
+    resolver: &UriResolver,
+  ) -> Result<usize> {
+    let uri = artifact_location
+      .uri
+      .as_ref()
+      .ok_or_else(|| anyhow::anyhow!("No artifact uri."))?;
+    let path = try_find_file(artifact_location, run, resolver)?;
+    if let Some(file_id) = self.ids.get(&path) {
+      return Ok(*file_id);
     }
-  } else {
-    None
-  };
-
-  (byte_offset, byte_end)
+    let contents = std::fs::read_to_string(&path)?;
+    let file_id = self.files.add(uri.clone(), contents);
+    self.ids.insert(path, file_id);
+    Ok(file_id)
+  }
 }
 
-// If kind (§3.27.9) has any value other than "fail", then if level is absent, it SHALL default to "none", and if it is present, it SHALL have the value "none".
-// If kind has the value "fail" and level is absent, then level SHALL be determined by the following procedure:
-// IF rule (§3.27.7) is present THEN
-//     LET theDescriptor be the reportingDescriptor object (§3.49) that it specifies.
-//     # Is there a configuration override for the level property?
-//     IF result.provenance.invocationIndex (§3.27.29, §3.48.6) is >= 0 THEN
-//         LET theInvocation be the invocation object (§3.20) that it specifies.
-//         IF theInvocation.ruleConfigurationOverrides (§3.20.5) is present
-//               AND it contains a configurationOverride object (§3.51) whose
-//               descriptor property (§3.51.2) specifies theDescriptor THEN
-//             LET theOverride be that configurationOverride object.
-//             IF theOverride.configuration.level (§3.51.3, §3.50.3) is present THEN
-//               Set level to theConfiguration.level.
-//     ELSE
-//         # There is no configuration override for level. Is there a default configuration for it?
-//         IF theDescriptor.defaultConfiguration.level (§3.49.14, §, §3.50.3) is present THEN
-//           SET level to theDescriptor.defaultConfiguration.level.
-// IF level has not yet been set THEN
-//     SET level to "warning".
-fn resolve_level(
-  rules: &[sarif::ReportingDescriptor],
+fn get_byte_range(
+  file_id: usize,
+  files: &SimpleFiles<String, String>,
+  region: &sarif::Region,
   run: &sarif::Run,
-  result: &sarif::Result,
-) -> sarif::ResultLevel {
-  result
-    .kind
-    .as_ref()
-    .and_then(|value| {
-      value.as_str().and_then(|kind| match kind {
-        // If kind has the value "fail" and level is absent, then level SHALL be determined by the following procedure:
-        "fail" => match result.level.as_ref() {
-          Some(level) => level.as_str().and_then(|level| {
-            sarif::ResultLevel::from_str(level).map_or(None, Option::from)
-          }),
-          None => result.rule.as_ref().and_then(|rule| {
-            // IF rule (§3.27.7) is present THEN
-            rule.index.and_then(|rule_index| {
-              rules
-                .get(rule_index as usize)
-                //     LET theDescriptor be the reportingDescriptor object (§3.49) that it specifies.
-                //     # Is there a configuration override for the level property?
-                .and_then(|the_descriptor| {
-                  //     IF result.provenance.invocationIndex (§3.27.29, §3.48.6) is >= 0 THEN
-                  result
-                    .provenance
-                    .as_ref()
-                    .and_then(|provenance| {
-                      provenance.invocation_index.and_then(|invocation_index| {
-                        run
-                          .invocations
-                          .iter()
-                          .flatten()
-                          .collect::<Vec<_>>()
-                          .get(invocation_index as usize)
-                          // LET theInvocation be the invocation object (§3.20) that it specifies.
-                          // IF theInvocation.ruleConfigurationOverrides (§3.20.5) is present
-                          //       AND it contains a configurationOverride object (§3.51) whose
-                          //       descriptor property (§3.51.2) specifies theDescriptor THEN
-                          .and_then(|the_invocation| {
-                            the_invocation
-                              .rule_configuration_overrides
-                              .as_ref()
-                              .and_then(|rule_configuration_overrides| {
-                                rule_configuration_overrides
-                                  .iter()
-                                  .find(|v| {
-                                    v.descriptor.id.as_ref()
-                                      == Some(&the_descriptor.id)
-                                  })
-                                  .and_then(|the_override| {
-                                    the_override
-                                      .configuration
-                                      .level
-                                      .as_ref()
-                                      .and_then(|value| {
-                                        value.as_str().and_then(|level| {
-                                          sarif::ResultLevel::from_str(level)
-                                            .map_or(None, Option::from)
-                                        })
-                                      })
-                                  })
-                              })
-                          })
-                      })
-                    })
-                    .or_else(|| {
-                      //         # There is no configuration override for level. Is there a default configuration for it?
-                      //         IF theDescriptor.defaultConfiguration.level (§3.49.14, §, §3.50.3) is present THEN
-                      //           SET level to theDescriptor.defaultConfiguration.level.
-                      the_descriptor.default_configuration.as_ref().and_then(
-                        |default_configuration| {
-                          default_configuration.level.as_ref().and_then(
-                            |value| {
-                              value.as_str().and_then(|level| {
-                                sarif::ResultLevel::from_str(level)
-                                  .map_or(None, Option::from)
-                              })
-                            },
-                          )
-                        },
-                      )
-                    })
-                })
-            })
-          }),
-        },
-        // If kind (§3.27.9) has any value other than "fail", then if level is absent, it SHALL default to "none", and if it is present, it SHALL have the value "none".
-        _ => Some(sarif::ResultLevel::None),
-      })
+) -> (Option<usize>, Option<usize>) {
+  files
+    .get(file_id)
+    .ok()
+    .and_then(|file| {
+      SourceText::new(file.source()).byte_range(region, ColumnUnit::of(run))
     })
-    // IF level has not yet been set THEN
-    //     SET level to "warning".
-    .unwrap_or(sarif::ResultLevel::Warning)
+    .map_or((None, None), |range| (Some(range.start), Some(range.end)))
 }
 
-// IF theMessage.text is present and the desired language is theRun.language THEN
-//     Use the text or markdown property of theMessage as appropriate.
-// IF the string has not yet been found THEN
-//     IF theMessage occurs as the value of result.message (§3.27.11) THEN
-//         LET theRule be the reportingDescriptor object (§3.49), an element of theComponent.rules (§3.19.23), which defines the rule that was violated by this result.
-//         IF theRule exists AND theRule.messageStrings (§3.49.11) is present AND contains a property whose name equals theMessage.id THEN
-//             LET theMFMS be the multiformatMessageString object (§3.12) that is the value of that property.
-//             Use the text or markdown property of theMFMS as appropriate.
-//     ELSE IF theMessage occurs as the value of notification.message (§3.58.5) THEN
-//         LET theDescriptor be the reportingDescriptor object (§3.49), an element of theComponent.notifications (§3.19.23), which describes this notification.
-//         IF theDescriptor exists AND theDescriptor.messageStrings is present AND contains a property whose name equals theMessage.id THEN
-//             LET theMFMS be the multiformatMessageString object that is the value of that property.
-//             Use the text or markdown property of theMFMS as appropriate.
-// IF the string has not yet been found THEN
-//     IF theComponent.globalMessageStrings (§3.19.22) is present AND contains a property whose name equals theMessage.id THEN
-//             LET theMFMS be the multiformatMessageString object that is the value of that property.
-//             Use the text or markdown property of theMFMS as appropriate.
-// IF the string has not yet been found THEN
-//     The lookup procedure fails (which means the SARIF log file is invalid).
-fn resolve_message_text_from_result(
-  result: &sarif::Result,
+// Returns the file and the byte range of the region of a location, adding the
+// file to the files if its contents can be read
+fn location_range(
+  location: &sarif::Location,
   run: &sarif::Run,
-) -> Option<String> {
-  result
-    .message
-    .text
-    .as_ref()
-    .cloned()
-    // IF the string has not yet been found THEN
-    .or_else(|| {
-      //     IF theMessage occurs as the value of result.message (§3.27.11) THEN
-      result.rule.as_ref().and_then(|the_rule| {
-        the_rule.index.and_then(|rule_index| {
-          run.tool.driver.rules.as_ref().and_then(|rules| {
-            //         LET theRule be the reportingDescriptor object (§3.49), an element of theComponent.rules (§3.19.23), which defines the rule that was violated by this result.
-            //         IF theRule exists AND theRule.messageStrings (§3.49.11) is present AND contains a property whose name equals theMessage.id THEN
-            rules.get(rule_index as usize).and_then(|the_rule| {
-              the_rule
-                .message_strings
-                .as_ref()
-                .and_then(|message_strings| {
-                  result.message.id.as_ref().and_then(|message_id| {
-                    //             LET theMFMS be the multiformatMessageString object (§3.12) that is the value of that property.
-                    //             Use the text or markdown property of theMFMS as appropriate.
-                    message_strings
-                      .get(message_id)
-                      .map(|the_mfms| the_mfms.text.clone())
-                  })
-                })
-            })
-          })
-        })
-      })
-    })
-    // IF the string has not yet been found THEN
-    //     IF theComponent.globalMessageStrings (§3.19.22) is present AND contains a property whose name equals theMessage.id THEN
-    //             LET theMFMS be the multiformatMessageString object that is the value of that property.
-    //             Use the text or markdown property of theMFMS as appropriate.
-    .or_else(|| {
-      run.tool.driver.global_message_strings.as_ref().and_then(
-        |global_message_strings| {
-          result.message.id.as_ref().and_then(|message_id| {
-            global_message_strings
-              .get(message_id)
-              .map(|the_mfms| the_mfms.text.clone())
-          })
-        },
-      )
-    })
-  // IF the string has not yet been found THEN
-  //     The lookup procedure fails (which means the SARIF log file is invalid).
-  // .or_else(|| None) # uncesscary but written for illustration
+  resolver: &UriResolver,
+  files: &mut SourceFiles,
+) -> Option<(usize, Range<usize>)> {
+  let physical_location = location.physical_location.as_ref()?;
+  let artifact_location = physical_location.artifact_location.as_ref()?;
+  let region = physical_location.region.as_ref()?;
+  let file_id = files.id(artifact_location, run, resolver).ok()?;
+  match get_byte_range(file_id, &files.files, region, run) {
+    (Some(range_start), Some(range_end)) => {
+      Some((file_id, range_start..range_end))
+    }
+    _ => None,
+  }
 }
 
-fn resolve_full_description_from_result(
-  rules: &[sarif::ReportingDescriptor],
-  result: &sarif::Result,
-) -> Option<String> {
-  result
-    .rule_index
-    .and_then(|rule_index| {
-      rules.get(rule_index as usize).and_then(|the_descriptor| {
-        the_descriptor
-          .full_description
-          .as_ref()
-          .map(|mfms| mfms.text.clone())
-      })
-    })
-    .or_else(|| {
-      result.rule.as_ref().and_then(|rule| {
-        rule.index.and_then(|rule_index| {
-          rules.get(rule_index as usize).and_then(|the_descriptor| {
-            the_descriptor
-              .full_description
-              .as_ref()
-              .map(|mfms| mfms.text.clone())
+// Returns the fixes of a result, each as its description and a unified diff
+// of the changes it makes to the files on disk
+fn fix_diffs<'a>(
+  run: &'a sarif::Run,
+  result: &'a sarif::Result,
+  localizer: &Localizer<'a>,
+  resolver: &UriResolver,
+) -> Vec<(String, String)> {
+  let fixes = result.fixes.as_deref().unwrap_or_default();
+  fixes
+    .iter()
+    .enumerate()
+    .map(|(i, fix)| {
+      let description = fix
+        .description
+        .as_ref()
+        .and_then(|description| localizer.message(description, result))
+        .map_or_else(|| format!("fix {}", i + 1), |d| d.to_string());
+      let diff = fix
+        .artifact_changes
+        .iter()
+        .map(|change| {
+          let artifact_location = &change.artifact_location;
+          let name = artifact_location.uri.clone().unwrap_or_default();
+          let diff = try_find_file(artifact_location, run, resolver)
+            .and_then(|path| Ok(std::fs::read_to_string(path)?))
+            .and_then(|contents| {
+              let edits = edits(run, change, &contents)?;
+              Ok(unified_diff(&name, &contents, &edits, 3)?)
+            });
+          diff.unwrap_or_else(|err| {
+            format!("cannot show the changes to {}: {}\n", name, err)
           })
         })
-      })
+        .collect();
+      (description, diff)
     })
+    .collect()
 }
 
-fn resolve_short_description_from_result(
-  rules: &[sarif::ReportingDescriptor],
-  result: &sarif::Result,
-) -> Option<String> {
-  result
-    .rule_index
-    .and_then(|rule_index| {
-      rules.get(rule_index as usize).and_then(|the_descriptor| {
-        the_descriptor
-          .short_description
-          .as_ref()
-          .map(|mfms| mfms.text.clone())
-      })
-    })
-    .or_else(|| {
-      result.rule.as_ref().and_then(|rule| {
-        rule.index.and_then(|rule_index| {
-          rules.get(rule_index as usize).and_then(|the_descriptor| {
-            the_descriptor
-              .short_description
-              .as_ref()
-              .map(|mfms| mfms.text.clone())
-          })
-        })
-      })
-    })
+// Prints a unified diff, coloring removed lines red, added lines green and
+// the ranges of hunks cyan
+fn write_diff(writer: &mut StandardStream, diff: &str) -> Result<()> {
+  for line in diff.split_inclusive('\n') {
+    let mut color = ColorSpec::new();
+    if line.starts_with("---") || line.starts_with("+++") {
+      color.set_bold(true);
+    } else if line.starts_with("@@") {
+      color.set_fg(Some(Color::Cyan));
+    } else if line.starts_with('-') {
+      color.set_fg(Some(Color::Red));
+    } else if line.starts_with('+') {
+      color.set_fg(Some(Color::Green));
+    }
+    writer.set_color(&color)?;
+    writer.write_all(line.as_bytes())?;
+    writer.reset()?;
+  }
+  Ok(())
 }
 
-fn to_writer_plain(sarif: &sarif::Sarif) -> Result<()> {
-  let mut files = SimpleFiles::new();
-  sarif.runs.iter().try_for_each(|run| -> Result<()> {
-    let mut diagnostics = vec![];
-    if let Some(results) = run.results.as_ref() {
-      results.iter().try_for_each(|result| -> Result<()> {
-        let rules = vec![];
-        let rules = run.tool.driver.rules.as_ref().unwrap_or(&rules);
-        let level = resolve_level(rules, run, result);
-
-        if let (Some(text), Some(locations)) = (
-          resolve_message_text_from_result(result, run),
-          result.locations.as_ref(),
-        ) {
-          locations.iter().for_each(|location| {
-            if let Some((file_id, range)) = location
-              .physical_location
-              .as_ref()
-              .and_then(|physical_location| {
-                physical_location.artifact_location.as_ref().and_then(
-                  |artifact_location| {
-                    artifact_location.uri.as_ref().and_then(|uri| {
-                      physical_location.region.as_ref().and_then(|region| {
-                        get_physical_location_contents(physical_location, run)
-                          .ok()
-                          .and_then(|contents| {
-                            let file_id = files.add(uri, contents);
-                            if let (Some(range_start), Some(range_end)) =
-                              get_byte_range(file_id, &files, region)
-                            {
-                              Some((file_id, range_start..range_end))
-                            } else {
-                              None
-                            }
-                          })
-                      })
-                    })
-                  },
-                )
-              })
-            {
-              if let (Ok(name), Ok(location)) =
-                (files.name(file_id), files.location(file_id, range.start))
-              {
-                let diagnostic = (
-                  name.clone(),
-                  level,
-                  location.line_number,
-                  location.column_number,
-                  text.clone(),
-                );
-                diagnostics.push(diagnostic);
-              } else {
-                // todo: no location found
-              }
-            }
-          });
-          // todo: no location found
-        }
+// Translations which live in separate files, loaded once per run
+#[derive(Default)]
+struct RunTranslations {
+  run_index: Option<usize>,
+  translations: Vec<sarif::ToolComponent>,
+}
 
-        Ok(())
-      })?;
+impl RunTranslations {
+  // Returns the localizer of the run of a result; without a language, the
+  // messages are shown in the language of the run
+  fn localizer<'a>(
+    &'a mut self,
+    run_result: &'a RunResult,
+    language: Option<&str>,
+    resolver: &UriResolver,
+  ) -> Result<Localizer<'a>> {
+    let run = &*run_result.run;
+    let localizer = match language {
+      Some(language) => Localizer::new(run, language),
+      None => {
+        let language = run.language.as_deref().unwrap_or(DEFAULT_LANGUAGE);
+        return Ok(Localizer::new(run, language));
+      }
     };
+    if localizer.is_native() {
+      return Ok(localizer);
+    }
+    if self.run_index != Some(run_result.run_index) {
+      self.translations = load_translations(run, resolver)?;
+      self.run_index = Some(run_result.run_index);
+    }
+    Ok(localizer.translations(&self.translations))
+  }
+}
 
-    diagnostics.sort_by(|a, b| {
-      a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal)
-    });
+// Prints the plain diagnostics of a run sorted by file name, followed by the
+// fixes of its results, so that each diagnostic stays on one line
+fn print_plain(
+  diagnostics: &mut Vec<(String, ResultLevel, usize, usize, String)>,
+  fixes: &mut Vec<String>,
+) {
+  diagnostics
+    .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+  diagnostics.drain(..).for_each(|diagnostic| {
+    println!(
+      "{}:{}:{}: {}: {}",
+      diagnostic.0, diagnostic.2, diagnostic.3, diagnostic.1, diagnostic.4
+    )
+  });
+  fixes.drain(..).for_each(|fix| print!("{}", fix));
+}
 
-    diagnostics.iter().for_each(|diagnostic| {
-      println!(
-        "{}:{}:{}: {}: {}",
-        diagnostic.0, diagnostic.2, diagnostic.3, diagnostic.1, diagnostic.4
-      )
-    });
-    Ok(())
-  })?;
+fn to_writer_plain(
+  results: impl Iterator<Item = ResultItem>,
+  resolver: &UriResolver,
+  language: Option<&str>,
+  show_fixes: bool,
+) -> Result<()> {
+  let mut translations = RunTranslations::default();
+  let mut files = SourceFiles::new();
+  let mut diagnostics = vec![];
+  let mut fixes = vec![];
+  let mut current_run = None;
+  for run_result in results {
+    let run_result = run_result?;
+    let (run, result) = (&*run_result.run, &run_result.result);
+    // diagnostics are sorted per run, so print them once the next run starts
+    if current_run != Some(run_result.run_index) {
+      print_plain(&mut diagnostics, &mut fixes);
+      current_run = Some(run_result.run_index);
+    }
+    let level = resolve_level(run, result);
+    let localizer = translations.localizer(&run_result, language, resolver)?;
+
+    if let (Some(message), Some(locations)) = (
+      localizer.message(&result.message, result),
+      result.locations.as_ref(),
+    ) {
+      let ids: Vec<&str> = taxa(run, result)
+        .into_iter()
+        .map(|(_, taxon)| taxon.id.as_str())
+        .collect();
+      let text = if ids.is_empty() {
+        message.to_string()
+      } else {
+        format!("{} [{}]", message, ids.join(", "))
+      };
+      // the fixes are headed by the first location of the result
+      let mut first = None;
+      locations.iter().for_each(|location| {
+        if let Some((file_id, range)) =
+          location_range(location, run, resolver, &mut files)
+        {
+          if let (Ok(name), Ok(location)) = (
+            files.files.name(file_id),
+            files.files.location(file_id, range.start),
+          ) {
+            let diagnostic = (
+              name.clone(),
+              level.clone(),
+              location.line_number,
+              location.column_number,
+              text.clone(),
+            );
+            first.get_or_insert_with(|| {
+              format!(
+                "{}:{}:{}",
+                name, location.line_number, location.column_number
+              )
+            });
+            diagnostics.push(diagnostic);
+          } else {
+            // todo: no location found
+          }
+        }
+      });
+      // todo: no location found
+      if show_fixes {
+        let first = first.map_or_else(String::new, |first| first + ": ");
+        fixes.extend(
+          fix_diffs(run, result, &localizer, resolver)
+            .into_iter()
+            .map(|(description, diff)| {
+              format!("fix: {}{}\n{}", first, description, diff)
+            }),
+        );
+      }
+    }
+  }
+  print_plain(&mut diagnostics, &mut fixes);
 
   Ok(())
 }
 
-fn to_writer_pretty(sarif: &sarif::Sarif) -> Result<()> {
-  let mut writer = StandardStream::stdout(ColorChoice::Auto);
-  let mut files = SimpleFiles::new();
+// Prints the thread flows of a result as numbered steps, one note per step
+// indented by its nesting level, leaving out steps less important than
+// `importance`
+fn emit_code_flows<'a>(
+  writer: &mut StandardStream,
+  files: &mut SourceFiles,
+  run: &'a sarif::Run,
+  result: &'a sarif::Result,
+  localizer: &Localizer<'a>,
+  resolver: &UriResolver,
+  importance: &ThreadFlowLocationImportance,
+) -> Result<()> {
   let config = codespan_reporting::term::Config::default();
-  let mut message_counter = (0, 0, 0);
-  sarif.runs.iter().try_for_each(|run| -> Result<()> {
-    if let Some(results) = run.results.as_ref() {
-      results.iter().try_for_each(|result| -> Result<()> {
-        let rules = vec![];
-        let rules = run.tool.driver.rules.as_ref().unwrap_or(&rules);
-        let level = resolve_level(rules, run, result);
-        let mut diagnostic: Diagnostic<usize> = Diagnostic::new(match level {
-          ResultLevel::Note => diagnostic::Severity::Note,
-          ResultLevel::Warning => diagnostic::Severity::Warning,
-          ResultLevel::Error => diagnostic::Severity::Error,
-          _ => diagnostic::Severity::Warning,
-        });
-        if let Some(message) = resolve_message_text_from_result(result, run) {
-          diagnostic.message = message;
+  let code_flows = result.code_flows.as_deref().unwrap_or_default();
+  for (i, code_flow) in code_flows.iter().enumerate() {
+    for (j, thread_flow) in code_flow.thread_flows.iter().enumerate() {
+      let mut title = format!("code flow {}", i + 1);
+      if code_flow.thread_flows.len() > 1 {
+        title.push_str(&format!(", thread {}", j + 1));
+      }
+      if let Some(message) = thread_flow
+        .message
+        .as_ref()
+        .or(code_flow.message.as_ref())
+        .and_then(|message| localizer.message(message, result))
+      {
+        title.push_str(&format!(": {}", message));
+      }
+      term::emit(
+        &mut writer.lock(),
+        &config,
+        &files.files,
+        &Diagnostic::note().with_message(title),
+      )?;
+
+      for step in steps(run, thread_flow)
+        .into_iter()
+        .filter(|step| step.importance.is_at_least(importance))
+      {
+        let indent = "  ".repeat(step.nesting_level.max(0) as usize);
+        let mut text = format!("{}{}.", indent, step.number);
+        if !step.kinds.is_empty() {
+          text.push_str(&format!(" [{}]", step.kinds.join(", ")));
         }
-        if let Some(text) = resolve_short_description_from_result(rules, result)
+        if let Some(message) = step
+          .location
+          .and_then(|location| location.message.as_ref())
+          .and_then(|message| localizer.message(message, result))
         {
-          diagnostic.notes.push(text);
+          text.push_str(&format!(" {}", message));
         }
-        if let Some(text) = resolve_full_description_from_result(rules, result)
+        let mut diagnostic = Diagnostic::note().with_message(text);
+        if let Some((file_id, range)) = step
+          .location
+          .and_then(|location| location_range(location, run, resolver, files))
         {
-          diagnostic.notes.push(text);
+          diagnostic.labels.push(Label::primary(file_id, range));
         }
+        term::emit(&mut writer.lock(), &config, &files.files, &diagnostic)?;
+      }
+    }
+  }
+  Ok(())
+}
 
-        if let Some(locations) = result.locations.as_ref() {
-          locations.iter().for_each(|location| {
-            if let Some((file_id, range)) = location
-              .physical_location
-              .as_ref()
-              .and_then(|physical_location| {
-                physical_location.artifact_location.as_ref().and_then(
-                  |artifact_location| {
-                    artifact_location.uri.as_ref().and_then(|uri| {
-                      physical_location.region.as_ref().and_then(|region| {
-                        get_physical_location_contents(physical_location, run)
-                          .ok()
-                          .and_then(|contents| {
-                            let file_id = files.add(uri, contents);
-                            if let (Some(range_start), Some(range_end)) =
-                              get_byte_range(file_id, &files, region)
-                            {
-                              Some((file_id, range_start..range_end))
-                            } else {
-                              None
-                            }
-                          })
-                      })
-                    })
-                  },
-                )
-              })
-            {
-              diagnostic.labels.push(Label::primary(file_id, range));
-            }
-          });
-        }
+fn to_writer_pretty(
+  results: impl Iterator<Item = ResultItem>,
+  resolver: &UriResolver,
+  language: Option<&str>,
+  importance: &ThreadFlowLocationImportance,
+  show_fixes: bool,
+) -> Result<()> {
+  let mut translations = RunTranslations::default();
+  let mut writer = StandardStream::stdout(ColorChoice::Auto);
+  let mut files = SourceFiles::new();
+  let config = codespan_reporting::term::Config::default();
+  let mut message_counter = (0, 0, 0);
+  for run_result in results {
+    let run_result = run_result?;
+    let (run, result) = (&*run_result.run, &run_result.result);
+    let level = resolve_level(run, result);
+    let mut diagnostic: Diagnostic<usize> = Diagnostic::new(match level {
+      ResultLevel::Note => diagnostic::Severity::Note,
+      ResultLevel::Warning => diagnostic::Severity::Warning,
+      ResultLevel::Error => diagnostic::Severity::Error,
+      _ => diagnostic::Severity::Warning,
+    });
+    let localizer = translations.localizer(&run_result, language, resolver)?;
+    let message = localizer.message(&result.message, result);
+    if let Some(message) = message.as_ref() {
+      diagnostic.message = message.to_string();
+    }
+    if let Some(text) = localizer.short_description(result) {
+      diagnostic.notes.push(text.to_string());
+    }
+    if let Some(text) = localizer.full_description(result) {
+      diagnostic.notes.push(text.to_string());
+    }
+    taxa(run, result).into_iter().for_each(|(_, taxon)| {
+      diagnostic.notes.push(match taxon.name.as_ref() {
+        Some(name) => format!("{}: {}", taxon.id, name),
+        None => taxon.id.clone(),
+      })
+    });
 
-        if let Some(locations) = result.related_locations.as_ref() {
-          locations.iter().for_each(|location| {
-            if let Some((file_id, range)) = location
-              .physical_location
-              .as_ref()
-              .and_then(|physical_location| {
-                physical_location.artifact_location.as_ref().and_then(
-                  |artifact_location| {
-                    artifact_location.uri.as_ref().and_then(|uri| {
-                      physical_location.region.as_ref().and_then(|region| {
-                        get_physical_location_contents(physical_location, run)
-                          .ok()
-                          .and_then(|contents| {
-                            let file_id = files.add(uri, contents);
-                            if let (Some(range_start), Some(range_end)) =
-                              get_byte_range(file_id, &files, region)
-                            {
-                              Some((file_id, range_start..range_end))
-                            } else {
-                              None
-                            }
-                          })
-                      })
-                    })
-                  },
-                )
-              })
-            {
-              diagnostic.labels.push(Label::secondary(file_id, range));
-            }
-          });
+    if let Some(locations) = result.locations.as_ref() {
+      locations.iter().for_each(|location| {
+        if let Some((file_id, range)) =
+          location_range(location, run, resolver, &mut files)
+        {
+          diagnostic.labels.push(Label::primary(file_id, range));
         }
+      });
+    }
 
-        term::emit(&mut writer.lock(), &config, &files, &diagnostic)?;
-        match diagnostic.severity {
-          codespan_reporting::diagnostic::Severity::Note => {
-            message_counter.0 += 1
-          }
-          codespan_reporting::diagnostic::Severity::Warning => {
-            message_counter.1 += 1
-          }
-          codespan_reporting::diagnostic::Severity::Error => {
-            message_counter.2 += 1
-          }
-          _ => {}
+    if let Some(locations) = result.related_locations.as_ref() {
+      locations.iter().for_each(|location| {
+        if let Some((file_id, range)) =
+          location_range(location, run, resolver, &mut files)
+        {
+          // locations which the message links to are labelled with the
+          // text of the link
+          let label = message
+            .iter()
+            .flat_map(|message| message.links())
+            .find_map(|(text, target)| match target {
+              LinkTarget::Location(linked)
+                if std::ptr::eq(*linked, location) =>
+              {
+                Some(text.to_string())
+              }
+              _ => None,
+            })
+            .or_else(|| {
+              location
+                .message
+                .as_ref()
+                .and_then(|message| localizer.message(message, result))
+                .map(|message| message.to_string())
+            });
+          diagnostic.labels.push(
+            Label::secondary(file_id, range)
+              .with_message(label.unwrap_or_default()),
+          );
         }
-        Ok(())
-      })?;
-    };
-    Ok(())
-  })?;
+      });
+    }
+
+    term::emit(&mut writer.lock(), &config, &files.files, &diagnostic)?;
+    emit_code_flows(
+      &mut writer,
+      &mut files,
+      run,
+      result,
+      &localizer,
+      resolver,
+      importance,
+    )?;
+    if show_fixes {
+      for (description, diff) in fix_diffs(run, result, &localizer, resolver) {
+        writer.set_color(
+          ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true),
+        )?;
+        writer.write_all("fix".as_bytes())?;
+        writer.set_color(ColorSpec::new().set_bold(true))?;
+        writer.write_all(format!(": {}\n", description).as_bytes())?;
+        writer.reset()?;
+        write_diff(&mut writer, &diff)?;
+        writer.write_all("\n".as_bytes())?;
+      }
+    }
+    match diagnostic.severity {
+      codespan_reporting::diagnostic::Severity::Note => message_counter.0 += 1,
+      codespan_reporting::diagnostic::Severity::Warning => {
+        message_counter.1 += 1
+      }
+      codespan_reporting::diagnostic::Severity::Error => message_counter.2 += 1,
+      _ => {}
+    }
+  }
 
   if message_counter.1 > 0 {
     writer
@@ -789,19 +815,124 @@ struct Args {
   /// input file; reads from stdin if none is given
   #[arg(short, long)]
   input: Option<std::path::PathBuf>,
+  /// validate the input against the SARIF schema and semantic rules instead
+  /// of printing it; exits with a non-zero status if the input is invalid
+  #[arg(long)]
+  validate: bool,
+  /// baseline SARIF file; only results which are new relative to it are
+  /// shown
+  #[arg(long)]
+  baseline: Option<std::path::PathBuf>,
+  /// defines a uriBaseId which relative artifact locations refer to, ex.
+  /// SRCROOT=/src; takes precedence over the originalUriBaseIds of the
+  /// input, may be given several times
+  #[arg(long, value_name = "ID=URI", value_parser = parse_uri_base)]
+  uri_base: Vec<(String, Url)>,
+  /// only show results of this level (error, warning, note or none), may be
+  /// given several times
+  #[arg(long, value_name = "LEVEL", value_parser = parse_level)]
+  level: Vec<ResultLevel>,
+  /// only show results whose rule id matches this glob, ex. 'clippy::*', may
+  /// be given several times
+  #[arg(long, value_name = "GLOB")]
+  rule: Vec<String>,
+  /// only show results in files whose uri matches this glob, ex.
+  /// 'src/**/*.rs', may be given several times
+  #[arg(long, value_name = "GLOB")]
+  path: Vec<String>,
+  /// only show results of this tool, ex. clippy, may be given several times
+  #[arg(long, value_name = "NAME")]
+  tool: Vec<String>,
+  /// show messages and rule descriptions in this language, ex. de-DE, if the
+  /// input provides translations into it
+  #[arg(long, value_name = "LANGUAGE")]
+  lang: Option<String>,
+  /// only show the steps of code flows which are at least this important
+  /// (essential, important or unimportant)
+  #[arg(
+    long,
+    value_name = "IMPORTANCE",
+    default_value = "unimportant",
+    value_parser = parse_importance
+  )]
+  importance: ThreadFlowLocationImportance,
+  /// show the fixes proposed by each result as a diff of the files they
+  /// change
+  #[arg(long)]
+  show_fixes: bool,
+}
+
+// ResultLevel parses levels outside of the schema into its Unknown variant,
+// which no result resolves to
+fn parse_level(level: &str) -> Result<ResultLevel, String> {
+  match level.parse() {
+    Ok(ResultLevel::Unknown(_)) | Err(_) => Err(format!(
+      "unknown level {}, expected one of error, warning, note or none",
+      level
+    )),
+    Ok(level) => Ok(level),
+  }
+}
+
+fn parse_importance(
+  importance: &str,
+) -> Result<ThreadFlowLocationImportance, String> {
+  match importance.parse() {
+    Ok(ThreadFlowLocationImportance::Unknown(_)) | Err(_) => Err(format!(
+      "unknown importance {}, expected one of essential, important or \
+       unimportant",
+      importance
+    )),
+    Ok(importance) => Ok(importance),
+  }
 }
 
 fn main() -> Result<()> {
   let args = Args::parse();
 
-  let read = match args.input {
-    Some(path) => Box::new(File::open(path)?) as Box<dyn Read>,
-    None => Box::new(std::io::stdin()) as Box<dyn Read>,
+  if args.validate {
+    let read = match args.input {
+      Some(path) => Box::new(File::open(path)?) as Box<dyn Read>,
+      None => Box::new(std::io::stdin()) as Box<dyn Read>,
+    };
+    if !report(BufReader::new(read), std::io::stderr())? {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+  let resolver = args.uri_base.into_iter().fold(
+    UriResolver::new().default_base(
+      Url::from_directory_path(std::env::current_dir()?)
+        .map_err(|_| anyhow::anyhow!("Invalid current directory"))?,
+    ),
+    |resolver, (id, base)| resolver.uri_base(id, base),
+  );
+  let query = args.level.into_iter().fold(Query::new(), Query::level);
+  let query = args.rule.iter().fold(query, |query, rule| query.rule(rule));
+  let query = args.path.iter().fold(query, |query, path| query.path(path));
+  let query = args.tool.into_iter().fold(query, Query::tool);
+  let results: Box<dyn Iterator<Item = ResultItem>> = match args.baseline {
+    Some(baseline) => Box::new(
+      process_with_baseline(args.input, baseline)?
+        .into_iter()
+        .map(Ok),
+    ),
+    None => process(args.input)?,
   };
-  let reader = BufReader::new(read);
-  let sarif = process(reader)?;
+  let results = results.filter(|item| match item {
+    Ok(run_result) => query.matches(&run_result.run, &run_result.result),
+    Err(_) => true,
+  });
   match args.message_format {
-    MessageFormat::Plain => to_writer_plain(&sarif),
-    MessageFormat::Pretty => to_writer_pretty(&sarif),
+    MessageFormat::Plain => {
+      to_writer_plain(results, &resolver, args.lang.as_deref(), args.show_fixes)
+    }
+    MessageFormat::Pretty => to_writer_pretty(
+      results,
+      &resolver,
+      args.lang.as_deref(),
+      &args.importance,
+      args.show_fixes,
+    ),
   }
 }
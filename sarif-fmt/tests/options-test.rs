@@ -0,0 +1,203 @@
+use anyhow::Result;
+use serde_sarif::uri::Url;
+use std::fs;
+
+// Returns a shellcheck log of a warning with a code flow, a translation and a
+// fix in main.sh, and an error in lib.sh
+fn log() -> serde_json::Value {
+  let location = |uri: &str, start: i64, end: i64| {
+    serde_json::json!({ "physicalLocation": {
+      "artifactLocation": { "uri": uri, "uriBaseId": "SRCROOT" },
+      "region": { "startLine": 1, "startColumn": start, "endColumn": end }
+    } })
+  };
+  let step = |importance: &str, text: &str| {
+    serde_json::json!({
+      "importance": importance,
+      "location": { "message": { "text": text } }
+    })
+  };
+  serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": {
+        "name": "shellcheck",
+        "rules": [{
+          "id": "2086",
+          "messageStrings": { "default": { "text": "Double quote {0}" } }
+        }, { "id": "2034" }]
+      } },
+      "translations": [{
+        "name": "shellcheck",
+        "language": "de-DE",
+        "associatedComponent": { "name": "shellcheck" },
+        "rules": [{
+          "id": "2086",
+          "messageStrings": {
+            "default": { "text": "Setze {0} in Anführungszeichen" }
+          }
+        }]
+      }],
+      "results": [{
+        "ruleId": "2086",
+        "level": "warning",
+        "message": { "id": "default", "arguments": ["$1"] },
+        "locations": [location("main.sh", 6, 8)],
+        "codeFlows": [{ "threadFlows": [{ "locations": [
+          step("essential", "read here"),
+          step("unimportant", "passed here")
+        ] }] }],
+        "fixes": [{
+          "description": { "text": "quote it" },
+          "artifactChanges": [{
+            "artifactLocation": { "uri": "main.sh", "uriBaseId": "SRCROOT" },
+            "replacements": [{
+              "deletedRegion": {
+                "startLine": 1, "startColumn": 6, "endColumn": 8
+              },
+              "insertedContent": { "text": "\"$1\"" }
+            }]
+          }]
+        }]
+      }, {
+        "ruleId": "2034",
+        "level": "error",
+        "message": { "text": "x appears unused" },
+        "locations": [location("lib.sh", 1, 2)]
+      }]
+    }]
+  })
+}
+
+// Runs sarif-fmt on `log` with the arguments, with the files of the log in a
+// temporary directory, and returns its stdout; `{baseline}` in the arguments
+// is replaced by the path of `baseline`
+fn sarif_fmt(
+  log: &serde_json::Value,
+  baseline: Option<&serde_json::Value>,
+  args: &[&str],
+) -> Result<String> {
+  let dir = tempfile::tempdir()?;
+  fs::write(dir.path().join("main.sh"), "echo $1\n")?;
+  fs::write(dir.path().join("lib.sh"), "x=1\necho hi\n")?;
+  let write = |name: &str, log: &serde_json::Value| -> Result<String> {
+    let path = dir.path().join(name);
+    fs::write(&path, serde_json::to_string(log)?)?;
+    Ok(path.to_string_lossy().into_owned())
+  };
+  let input = write("input.sarif", log)?;
+  let baseline = match baseline {
+    Some(baseline) => write("baseline.sarif", baseline)?,
+    None => String::new(),
+  };
+  let mut command = vec![
+    "-i".to_string(),
+    input,
+    "--uri-base".to_string(),
+    format!("SRCROOT={}", Url::from_directory_path(dir.path()).unwrap()),
+  ];
+  command.extend(args.iter().map(|arg| arg.replace("{baseline}", &baseline)));
+  let output = duct::cmd(env!("CARGO_BIN_EXE_sarif-fmt"), command)
+    .env("TERM", "dumb")
+    .stdout_capture()
+    .run()?;
+  Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+// Test that in plain mode the fixes follow the diagnostics of the run, which
+// stay on one line each
+fn test_show_fixes_plain() -> Result<()> {
+  let output = sarif_fmt(&log(), None, &["-m", "plain", "--show-fixes"])?;
+  assert_eq!(
+    output,
+    "lib.sh:1:1: error: x appears unused\n\
+     main.sh:1:6: warning: Double quote $1\n\
+     fix: main.sh:1:6: quote it\n\
+     --- a/main.sh\n+++ b/main.sh\n@@ -1,1 +1,1 @@\n-echo $1\n+echo \"$1\"\n"
+  );
+  Ok(())
+}
+
+#[test]
+// Test that the fixes are shown after their result in pretty mode
+fn test_show_fixes_pretty() -> Result<()> {
+  let output = sarif_fmt(&log(), None, &["--show-fixes"])?;
+  assert!(output.contains(
+    "fix: quote it\n--- a/main.sh\n+++ b/main.sh\n@@ -1,1 +1,1 @@\n\
+     -echo $1\n+echo \"$1\"\n"
+  ));
+  assert!(!sarif_fmt(&log(), None, &[])?.contains("fix:"));
+  Ok(())
+}
+
+#[test]
+// Test that the steps of code flows less important than --importance are left
+// out
+fn test_importance() -> Result<()> {
+  let output = sarif_fmt(&log(), None, &[])?;
+  assert!(output.contains("note: 1. read here"));
+  assert!(output.contains("note: 2. passed here"));
+  let output = sarif_fmt(&log(), None, &["--importance", "important"])?;
+  assert!(output.contains("note: 1. read here"));
+  assert!(!output.contains("passed here"));
+  Ok(())
+}
+
+#[test]
+// Test that messages are shown in the language given by --lang
+fn test_lang() -> Result<()> {
+  let output = sarif_fmt(&log(), None, &["-m", "plain", "--lang", "de-DE"])?;
+  assert_eq!(
+    output,
+    "lib.sh:1:1: error: x appears unused\n\
+     main.sh:1:6: warning: Setze $1 in Anführungszeichen\n"
+  );
+  // without a translation, the messages of the run are shown
+  let output = sarif_fmt(&log(), None, &["-m", "plain", "--lang", "fr"])?;
+  assert!(output.contains("main.sh:1:6: warning: Double quote $1\n"));
+  Ok(())
+}
+
+#[test]
+// Test that results are filtered by --level, --rule, --path and --tool
+fn test_filters() -> Result<()> {
+  let plain = |args: &[&str]| -> Result<String> {
+    let mut command = vec!["-m", "plain"];
+    command.extend(args);
+    sarif_fmt(&log(), None, &command)
+  };
+  let error = "lib.sh:1:1: error: x appears unused\n";
+  let warning = "main.sh:1:6: warning: Double quote $1\n";
+  assert_eq!(plain(&["--level", "error"])?, error);
+  assert_eq!(plain(&["--rule", "20*6"])?, warning);
+  assert_eq!(plain(&["--path", "lib.*"])?, error);
+  assert_eq!(
+    plain(&["--tool", "shellcheck", "--level", "warning"])?,
+    warning
+  );
+  assert_eq!(plain(&["--tool", "clippy"])?, "");
+  // the values of an option are alternatives
+  assert_eq!(
+    plain(&["--level", "error", "--level", "warning"])?,
+    format!("{}{}", error, warning)
+  );
+  Ok(())
+}
+
+#[test]
+// Test that only the results which are new relative to --baseline are shown
+fn test_baseline() -> Result<()> {
+  let mut baseline = log();
+  baseline["runs"][0]["results"]
+    .as_array_mut()
+    .unwrap()
+    .remove(0);
+  let output = sarif_fmt(
+    &log(),
+    Some(&baseline),
+    &["-m", "plain", "--baseline", "{baseline}"],
+  )?;
+  assert_eq!(output, "main.sh:1:6: warning: Double quote $1\n");
+  Ok(())
+}
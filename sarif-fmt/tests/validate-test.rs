@@ -0,0 +1,41 @@
+use anyhow::Result;
+use std::fs;
+
+// Runs `sarif-fmt --validate` on a log, returning its exit code and stderr
+fn validate(log: &serde_json::Value) -> Result<(Option<i32>, String)> {
+  let dir = tempfile::tempdir()?;
+  let input = dir.path().join("input.sarif");
+  fs::write(&input, serde_json::to_string(log)?)?;
+  let output =
+    duct::cmd!(env!("CARGO_BIN_EXE_sarif-fmt"), "--validate", "-i", &input)
+      .stderr_capture()
+      .unchecked()
+      .run()?;
+  Ok((output.status.code(), String::from_utf8(output.stderr)?))
+}
+
+#[test]
+// Test that an invalid log is reported and exits with a non-zero status
+fn test_validate_invalid() -> Result<()> {
+  let (code, stderr) = validate(&serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{ "tool": { "driver": {} } }]
+  }))?;
+  assert_eq!(code, Some(1));
+  assert!(stderr.contains(
+    "error[schema]: /runs/0/tool/driver: missing required property \"name\""
+  ));
+  Ok(())
+}
+
+#[test]
+// Test that a valid log exits successfully without output
+fn test_validate_valid() -> Result<()> {
+  let (code, stderr) = validate(&serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{ "tool": { "driver": { "name": "tool" } } }]
+  }))?;
+  assert_eq!(code, Some(0));
+  assert_eq!(stderr, "");
+  Ok(())
+}
@@ -5,11 +5,206 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use anyhow::Result;
+use proc_macro2::Span;
+use quote::{format_ident, ToTokens};
 use schemafy_lib::Expander;
 use schemafy_lib::Schema;
 
+// Schema enums which schemafy emits as untyped serde_json::Value fields,
+// keyed by (struct, field) and mapped to the handwritten enum in sarif.rs
+static ENUM_FIELDS: &[(&str, &str, &str)] = &[
+  ("Artifact", "roles", "ArtifactRoles"),
+  ("ExternalProperties", "version", "ExternalPropertiesVersion"),
+  ("Notification", "level", "NotificationLevel"),
+  (
+    "ReportingConfiguration",
+    "level",
+    "ReportingConfigurationLevel",
+  ),
+  ("Result", "baseline_state", "ResultBaselineState"),
+  ("Result", "kind", "ResultKind"),
+  ("Result", "level", "ResultLevel"),
+  ("Run", "column_kind", "ResultColumnKind"),
+  ("Sarif", "version", "Version"),
+  ("Suppression", "kind", "SupressionKind"),
+  ("Suppression", "status", "SupressionStatus"),
+  (
+    "ThreadFlowLocation",
+    "importance",
+    "ThreadFlowLocationImportance",
+  ),
+  ("ToolComponent", "contents", "ToolComponentContents"),
+];
+
+// Checks if the type is serde_json::Value
+fn type_is_value(ty: &syn::Type) -> bool {
+  if let syn::Type::Path(typepath) = ty {
+    let idents_of_path =
+      typepath
+        .path
+        .segments
+        .iter()
+        .fold(String::new(), |mut acc, v| {
+          acc.push_str(&v.ident.to_string());
+          acc.push('|');
+          acc
+        });
+    idents_of_path == "serde_json|Value|"
+  } else {
+    false
+  }
+}
+
+// Replaces serde_json::Value with the replacement type, looking through
+// generic arguments so that Option<Value> and Option<Vec<Value>> are handled
+fn replace_value_type(ty: &mut syn::Type, replacement: &syn::Type) -> bool {
+  if type_is_value(ty) {
+    *ty = replacement.clone();
+    return true;
+  }
+  if let syn::Type::Path(typepath) = ty {
+    if let Some(segment) = typepath.path.segments.last_mut() {
+      if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+        return args.args.iter_mut().any(|arg| match arg {
+          syn::GenericArgument::Type(ty) => replace_value_type(ty, replacement),
+          _ => false,
+        });
+      }
+    }
+  }
+  false
+}
+
+// Types which cannot derive Eq and Hash, nor can the structs containing them.
+// f64 fields are hashed by hand instead, see hash_impl.
+static UNHASHABLE_TYPES: &[&str] = &["f32", "Value", "HashMap"];
+
+// Structs which are hashed by hand although their fields are unhashable, see
+// property_bag_hash_impl
+static HASHED_BY_HAND: &[&str] = &["PropertyBag"];
+
+// Returns the identifiers in some tokens, ex. Option, Vec and Region for the
+// type Option<Vec<Region>>
+fn idents<T: ToTokens>(tokens: &T) -> Vec<String> {
+  tokens
+    .to_token_stream()
+    .to_string()
+    .split(|c: char| !c.is_alphanumeric() && c != '_')
+    .filter(|ident| !ident.is_empty())
+    .map(String::from)
+    .collect()
+}
+
+// Returns the names of the structs which can derive Eq and Hash, which are
+// those which do not (transitively) contain an unhashable type
+fn hashable_structs(ast: &syn::File) -> Vec<String> {
+  let structs: Vec<(String, Vec<String>)> = ast
+    .items
+    .iter()
+    .filter_map(|item| match item {
+      syn::Item::Struct(s) => Some((
+        s.ident.to_string(),
+        s.fields
+          .iter()
+          .flat_map(|field| idents(&field.ty))
+          .collect(),
+      )),
+      _ => None,
+    })
+    .collect();
+  let mut unhashable: Vec<String> =
+    UNHASHABLE_TYPES.iter().map(|ty| ty.to_string()).collect();
+  loop {
+    let before = unhashable.len();
+    structs.iter().for_each(|(name, idents)| {
+      if !unhashable.contains(name)
+        && !HASHED_BY_HAND.contains(&name.as_str())
+        && idents.iter().any(|ident| unhashable.contains(ident))
+      {
+        unhashable.push(name.clone());
+      }
+    });
+    if unhashable.len() == before {
+      break;
+    }
+  }
+  structs
+    .into_iter()
+    .map(|(name, _)| name)
+    .filter(|name| !unhashable.contains(name))
+    .collect()
+}
+
+// Implements Eq and Hash for a struct with Option<f64> fields, which are
+// hashed by their bits; JSON numbers are never NaN, and 0.0 and -0.0 (which
+// are equal) are hashed alike
+fn hash_impl(s: &syn::ItemStruct) -> Vec<syn::Item> {
+  let name = &s.ident;
+  let fields = s.fields.iter().map(|field| {
+    let ident = &field.ident;
+    if idents(&field.ty).iter().any(|ident| ident == "f64") {
+      let expected: syn::Type = syn::parse_quote! { Option<f64> };
+      if field.ty != expected {
+        panic!("{}.{:?} is not an Option<f64>", name, ident);
+      }
+      quote::quote! {
+        self
+          .#ident
+          .map(|value| if value == 0.0 { 0 } else { value.to_bits() })
+          .hash(state);
+      }
+    } else {
+      quote::quote! { self.#ident.hash(state); }
+    }
+  });
+  vec![
+    syn::parse_quote! {
+      impl Eq for #name {}
+    },
+    syn::parse_quote! {
+      impl std::hash::Hash for #name {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+          #(#fields)*
+        }
+      }
+    },
+  ]
+}
+
+// Implements Eq and Hash for PropertyBag, whose additional properties are
+// hashed by their keys only; serde_json::Value is Eq but not Hash
+fn property_bag_hash_impl() -> Vec<syn::Item> {
+  vec![
+    syn::parse_quote! {
+      impl Eq for PropertyBag {}
+    },
+    syn::parse_quote! {
+      impl std::hash::Hash for PropertyBag {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+          self.tags.hash(state);
+          self.additional_properties.keys().for_each(|key| key.hash(state));
+        }
+      }
+    },
+  ]
+}
+
+// Checks if the struct already derives the trait
+fn derives(s: &syn::ItemStruct, name: &str) -> bool {
+  s.attrs.iter().any(|attr| {
+    attr.path.is_ident("derive")
+      && idents(&attr.tokens).iter().any(|ident| ident == name)
+  })
+}
+
 // Add additional items to the generated sarif.rs file
-// Currently adds: derive(Builder) to each struct
+// Currently adds: derive(Builder) to each struct,
+// derive(Eq, Hash) to each struct whose fields allow it,
+// derive(Default) to each struct whose fields are all optional,
+// typed enums for fields which the schema restricts to an enum,
+// the properties of a PropertyBag other than tags, which the schema allows
+// but schemafy drops,
+// a BuilderError wrapping the error of every builder,
 // and appropriate use statements at the top of the file
 // todo: this (and other parts) need a refactor and tests
 fn process_token_stream(input: proc_macro2::TokenStream) -> syn::File {
@@ -32,15 +227,11 @@ fn process_token_stream(input: proc_macro2::TokenStream) -> syn::File {
   // Checks if the type is an Option type (returns true if yes, false otherwise)
   fn path_is_option(path: &syn::Path) -> bool {
     let idents_of_path =
-      path
-        .segments
-        .iter()
-        .into_iter()
-        .fold(String::new(), |mut acc, v| {
-          acc.push_str(&v.ident.to_string());
-          acc.push('|');
-          acc
-        });
+      path.segments.iter().fold(String::new(), |mut acc, v| {
+        acc.push_str(&v.ident.to_string());
+        acc.push('|');
+        acc
+      });
 
     vec!["Option|", "std|option|Option|", "core|option|Option|"]
       .into_iter()
@@ -49,6 +240,7 @@ fn process_token_stream(input: proc_macro2::TokenStream) -> syn::File {
 
   ast.items.iter_mut().for_each(|ref mut item| {
     if let syn::Item::Struct(s) = item {
+      let struct_name = s.ident.to_string();
       // add builder attributes to each struct
       s.attrs.extend(vec![
         syn::parse_quote! {
@@ -62,6 +254,20 @@ fn process_token_stream(input: proc_macro2::TokenStream) -> syn::File {
       // for each struct field, if that field is Optional, set None
       // as the default value when using the builder
       (&mut s.fields).into_iter().for_each(|ref mut field| {
+        // swap untyped enum fields for the matching handwritten enum
+        let field_name = field.ident.as_ref().map(|ident| ident.to_string());
+        if let Some((_, field_name, enum_name)) =
+          ENUM_FIELDS.iter().find(|(st, f, _)| {
+            *st == struct_name && Some(f.to_string()) == field_name
+          })
+        {
+          let enum_ident = syn::Ident::new(enum_name, Span::call_site());
+          let replacement: syn::Type = syn::parse_quote! { #enum_ident };
+          if !replace_value_type(&mut field.ty, &replacement) {
+            panic!("{}.{} is not a serde_json::Value", struct_name, field_name);
+          }
+        }
+
         if let syn::Type::Path(typepath) = &field.ty {
           if path_is_option(&typepath.path) {
             field.attrs.push(syn::parse_quote! {
@@ -69,16 +275,274 @@ fn process_token_stream(input: proc_macro2::TokenStream) -> syn::File {
             })
           }
         }
-      })
+      });
+
+      if struct_name == "PropertyBag" {
+        if let syn::Fields::Named(fields) = &mut s.fields {
+          let additional: syn::FieldsNamed = syn::parse_quote! {{
+            #[doc = "The properties other than `tags`, see [PropertyBag::get]."]
+            #[serde(flatten)]
+            #[builder(setter(into), default)]
+            pub additional_properties:
+              std::collections::BTreeMap<String, serde_json::Value>,
+          }};
+          fields.named.extend(additional.named);
+        }
+      }
+    }
+  });
+
+  // derive Eq and Hash where the fields allow it, and Default where every
+  // field is optional (schemafy only does so for some of them)
+  let hashable = hashable_structs(&ast);
+  let mut hash_impls = vec![];
+  ast.items.iter_mut().for_each(|item| {
+    if let syn::Item::Struct(s) = item {
+      let has_float = s
+        .fields
+        .iter()
+        .any(|field| idents(&field.ty).iter().any(|ident| ident == "f64"));
+      if HASHED_BY_HAND.contains(&s.ident.to_string().as_str()) {
+        hash_impls.extend(property_bag_hash_impl());
+      } else if hashable.contains(&s.ident.to_string()) {
+        if has_float {
+          hash_impls.extend(hash_impl(s));
+        } else {
+          s.attrs.push(syn::parse_quote! {
+            #[derive(Eq, Hash)]
+          });
+        }
+      }
+      let all_optional = s.fields.iter().all(|field| match &field.ty {
+        syn::Type::Path(typepath) => path_is_option(&typepath.path),
+        _ => false,
+      });
+      if all_optional && !derives(s, "Default") {
+        s.attrs.push(syn::parse_quote! {
+          #[derive(Default)]
+        });
+      }
     }
   });
+  ast.items.extend(hash_impls);
+
+  // derive_builder emits one error type per builder, collect them all into
+  // a single error so that code using several builders can use `?`
+  let builder_errors: Vec<syn::Ident> = ast
+    .items
+    .iter()
+    .filter_map(|item| match item {
+      syn::Item::Struct(s) => Some(format_ident!("{}BuilderError", s.ident)),
+      _ => None,
+    })
+    .collect();
+  ast.items.push(syn::parse_quote! {
+    #[doc = "An error returned by any of the builders."]
+    #[derive(Debug, thiserror::Error)]
+    pub enum BuilderError {
+      #(
+        #[error(transparent)]
+        #builder_errors {
+          #[from]
+          source: #builder_errors,
+        },
+      )*
+    }
+  });
+  ast.items.push(syn::parse_quote! {
+    #[doc = "Implemented by the error of every builder, for error types which"]
+    #[doc = "wrap [BuilderError] to convert from each of them."]
+    pub trait IntoBuilderError: Into<BuilderError> {}
+  });
+  builder_errors.iter().for_each(|builder_error| {
+    ast.items.push(syn::parse_quote! {
+      impl IntoBuilderError for #builder_error {}
+    })
+  });
 
   ast
 }
 
+// Converts a type name to snake case, ex. ArtifactLocation to
+// artifact_location
+fn snake_case(name: &str) -> String {
+  name
+    .chars()
+    .enumerate()
+    .fold(String::new(), |mut acc, (i, c)| {
+      if c.is_uppercase() && i > 0 {
+        acc.push('_');
+      }
+      acc.push(c.to_ascii_lowercase());
+      acc
+    })
+}
+
+// Returns the statements visiting the SARIF structs within a value of type
+// `ty`, where `value` is a (mutable) reference to it, or None if it contains
+// none. Structs may be nested within Option, Vec and BTreeMap values.
+fn walk_type(
+  ty: &syn::Type,
+  value: proc_macro2::TokenStream,
+  structs: &[String],
+  mutable: bool,
+) -> Option<proc_macro2::TokenStream> {
+  let segment = match ty {
+    syn::Type::Path(typepath) => typepath.path.segments.last()?,
+    _ => return None,
+  };
+  let name = segment.ident.to_string();
+  let args: Vec<&syn::Type> = match &segment.arguments {
+    syn::PathArguments::AngleBracketed(args) => args
+      .args
+      .iter()
+      .filter_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+      })
+      .collect(),
+    _ => vec![],
+  };
+  let suffix = if mutable { "_mut" } else { "" };
+  match (name.as_str(), args.as_slice()) {
+    (_, []) if structs.contains(&name) => {
+      let visit = format_ident!("visit_{}{}", snake_case(&name), suffix);
+      Some(quote::quote! { visitor.#visit(#value); })
+    }
+    ("Option", [inner]) => {
+      let walk = walk_type(inner, quote::quote! { value }, structs, mutable)?;
+      Some(quote::quote! {
+        if let Some(value) = #value {
+          #walk
+        }
+      })
+    }
+    ("Vec", [inner]) => {
+      let walk = walk_type(inner, quote::quote! { value }, structs, mutable)?;
+      Some(quote::quote! {
+        for value in #value {
+          #walk
+        }
+      })
+    }
+    ("BTreeMap", [_, inner]) => {
+      let walk = walk_type(inner, quote::quote! { value }, structs, mutable)?;
+      let values = format_ident!("values{}", suffix);
+      Some(quote::quote! {
+        for value in (#value).#values() {
+          #walk
+        }
+      })
+    }
+    _ => None,
+  }
+}
+
+// Generates the Visit and VisitMut traits, with a method per SARIF struct
+// which by default walks the structs within it
+fn generate_visitors(ast: &syn::File) -> syn::File {
+  let structs: Vec<&syn::ItemStruct> = ast
+    .items
+    .iter()
+    .filter_map(|item| match item {
+      syn::Item::Struct(s) => Some(s),
+      _ => None,
+    })
+    .collect();
+  let names: Vec<String> =
+    structs.iter().map(|s| s.ident.to_string()).collect();
+  let mut visit_methods = vec![];
+  let mut visit_mut_methods = vec![];
+  let mut items: Vec<syn::Item> = vec![];
+  structs.iter().for_each(|s| {
+    let name = &s.ident;
+    let doc =
+      format!("Visits a [sarif::{}] and, by default, its children", name);
+    let snake = snake_case(&name.to_string());
+    let node = format_ident!("{}", snake);
+    [false, true].iter().for_each(|mutable| {
+      let suffix = if *mutable { "_mut" } else { "" };
+      let visit = format_ident!("visit_{}{}", snake, suffix);
+      let walk = format_ident!("walk_{}{}", snake, suffix);
+      let fields: Vec<_> = s
+        .fields
+        .iter()
+        .filter_map(|field| {
+          let ident = &field.ident;
+          let value = if *mutable {
+            quote::quote! { &mut #node.#ident }
+          } else {
+            quote::quote! { &#node.#ident }
+          };
+          walk_type(&field.ty, value, &names, *mutable)
+        })
+        .collect();
+      let (trait_name, reference) = if *mutable {
+        (
+          quote::quote! { VisitMut },
+          quote::quote! { &mut sarif::#name },
+        )
+      } else {
+        (quote::quote! { Visit }, quote::quote! { &sarif::#name })
+      };
+      let method = quote::quote! {
+        #[doc = #doc]
+        fn #visit(&mut self, #node: #reference) {
+          #walk(self, #node)
+        }
+      };
+      let walk_doc = format!("Visits the children of a [sarif::{}]", name);
+      let (visitor_param, node_param) = if fields.is_empty() {
+        (format_ident!("_visitor"), format_ident!("_{}", snake))
+      } else {
+        (format_ident!("visitor"), node.clone())
+      };
+      items.push(syn::parse_quote! {
+        #[doc = #walk_doc]
+        pub fn #walk<V: #trait_name + ?Sized>(
+          #visitor_param: &mut V,
+          #node_param: #reference,
+        ) {
+          #(#fields)*
+        }
+      });
+      if *mutable {
+        visit_mut_methods.push(method);
+      } else {
+        visit_methods.push(method);
+      }
+    })
+  });
+  items.insert(
+    0,
+    syn::parse_quote! {
+      #[doc = "A visitor over the SARIF object graph, with a method per type."]
+      pub trait Visit {
+        #(#visit_methods)*
+      }
+    },
+  );
+  items.insert(
+    1,
+    syn::parse_quote! {
+      #[doc = "A visitor over the SARIF object graph which may modify it, with"]
+      #[doc = "a method per type."]
+      pub trait VisitMut {
+        #(#visit_mut_methods)*
+      }
+    },
+  );
+  syn::File {
+    shebang: None,
+    attrs: vec![],
+    items,
+  }
+}
+
 fn main() -> Result<()> {
   // Rerun if the schema changes
   println!("cargo:rerun-if-changed=src/schema.json");
+  println!("cargo:rustc-check-cfg=cfg(doc_cfg)");
   let path = Path::new("src/schema.json");
 
   // Generate the Rust schema struct
@@ -93,5 +557,11 @@ fn main() -> Result<()> {
   let mut file = File::create(out_path.join("sarif.rs"))?;
   file.write_all(prettyplease::unparse(&generated).as_bytes())?;
 
+  // Write the visitors to the $OUT_DIR/visit.rs file.
+  let mut file = File::create(out_path.join("visit.rs"))?;
+  file.write_all(
+    prettyplease::unparse(&generate_visitors(&generated)).as_bytes(),
+  )?;
+
   Ok(())
 }
@@ -0,0 +1,661 @@
+//! Comparison of SARIF logs against a baseline.
+//!
+//! [compare] matches the results of a log with those of a baseline log, for
+//! example the log of the target branch of a pull request, and sets the
+//! `baselineState` of every result:
+//!
+//! - `new` - the result has no counterpart in the baseline
+//! - `unchanged` - the result matches a baseline result with the same
+//!   message and artifact
+//! - `updated` - the result matches a baseline result, but its message or
+//!   artifact changed
+//! - `absent` - the baseline result has no counterpart in the log; these are
+//!   copied into the log, with the objects of the baseline run they refer to
+//!   by index, ex. artifacts, inlined
+//!
+//! Runs are paired by `tool.driver.name`. Within a pair of runs, results of
+//! the same rule are matched in passes of decreasing strictness: equal
+//! `fingerprints` or `partialFingerprints`, then equal artifact URI and
+//! message, then equal artifact URI and similar message. Line numbers are
+//! only used to pick the nearest candidate, so results whose lines shifted
+//! still match.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::baseline::compare;
+//! use serde_sarif::sarif::{ResultBaselineState, Sarif};
+//!
+//! let log = |line: i64, message: &str| -> Sarif {
+//!   serde_json::from_value(serde_json::json!({
+//!     "version": "2.1.0",
+//!     "runs": [{
+//!       "tool": { "driver": { "name": "clippy" } },
+//!       "results": [{
+//!         "ruleId": "clippy::needless_return",
+//!         "message": { "text": message },
+//!         "locations": [{
+//!           "physicalLocation": {
+//!             "artifactLocation": { "uri": "src/lib.rs" },
+//!             "region": { "startLine": line }
+//!           }
+//!         }]
+//!       }]
+//!     }]
+//!   }))
+//!   .unwrap()
+//! };
+//!
+//! let mut current = log(12, "unneeded `return` statement");
+//! compare(&mut current, &log(10, "unneeded `return` statement"));
+//!
+//! let results = current.runs[0].results.as_ref().unwrap();
+//! assert_eq!(results[0].baseline_state, Some(ResultBaselineState::Unchanged));
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::sarif;
+use crate::taxonomy::resolve_taxon;
+use crate::visit::{
+  walk_graph_traversal_mut, walk_result_mut, walk_thread_flow_location_mut,
+  VisitMut,
+};
+
+// The minimum similarity of two messages for their results to match
+const MESSAGE_SIMILARITY: f64 = 0.5;
+
+/// Sets the `baselineState` of every result in `current` by comparing it
+/// with `baseline`, and appends the baseline results which are absent from
+/// `current` to it.
+///
+/// # Arguments
+///
+/// * `current` - The log to annotate
+/// * `baseline` - The log to compare with
+pub fn compare(current: &mut sarif::Sarif, baseline: &sarif::Sarif) {
+  let mut paired = vec![false; current.runs.len()];
+  let mut absent_runs = vec![];
+  baseline.runs.iter().for_each(|baseline_run| {
+    let run =
+      current
+        .runs
+        .iter_mut()
+        .zip(paired.iter_mut())
+        .find(|(run, paired)| {
+          !**paired && run.tool.driver.name == baseline_run.tool.driver.name
+        });
+    match run {
+      Some((run, paired)) => {
+        *paired = true;
+        compare_runs(run, baseline_run);
+      }
+      None => {
+        let mut run = baseline_run.clone();
+        run.results.iter_mut().flatten().for_each(|result| {
+          result.baseline_state = Some(sarif::ResultBaselineState::Absent)
+        });
+        absent_runs.push(run);
+      }
+    }
+  });
+  current
+    .runs
+    .iter_mut()
+    .zip(paired)
+    .filter(|(_, paired)| !paired)
+    .for_each(|(run, _)| {
+      run.results.iter_mut().flatten().for_each(|result| {
+        result.baseline_state = Some(sarif::ResultBaselineState::New)
+      })
+    });
+  current.runs.extend(absent_runs);
+}
+
+// The properties of a result used to match it
+struct Key {
+  rule_id: Option<String>,
+  artifact: Option<(Option<String>, String)>,
+  line: Option<i64>,
+  message: String,
+  words: HashSet<String>,
+}
+
+impl Key {
+  fn new(run: &sarif::Run, result: &sarif::Result) -> Self {
+    let message = result
+      .message
+      .text
+      .clone()
+      .or_else(|| {
+        result.message.id.as_ref().map(|id| {
+          std::iter::once(id.clone())
+            .chain(result.message.arguments.iter().flatten().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+        })
+      })
+      .unwrap_or_default();
+    let physical_location = result
+      .locations
+      .iter()
+      .flatten()
+      .find_map(|location| location.physical_location.as_ref());
+    Key {
+      rule_id: rule_id(run, result),
+      artifact: physical_location
+        .and_then(|p| p.artifact_location.as_ref())
+        .and_then(|location| {
+          resolve_uri(run, location)
+            .map(|uri| (location.uri_base_id.clone(), uri))
+        }),
+      line: physical_location
+        .and_then(|p| p.region.as_ref())
+        .and_then(|region| region.start_line),
+      words: message
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect(),
+      message,
+    }
+  }
+
+  // Returns the Jaccard similarity of the words of the two messages
+  fn similarity(&self, other: &Key) -> f64 {
+    let union = self.words.union(&other.words).count();
+    if union == 0 {
+      return 1.0;
+    }
+    self.words.intersection(&other.words).count() as f64 / union as f64
+  }
+
+  fn distance(&self, other: &Key) -> i64 {
+    match (self.line, other.line) {
+      (Some(a), Some(b)) => (a - b).abs(),
+      _ => i64::MAX,
+    }
+  }
+}
+
+fn get<T>(items: Option<&Vec<T>>, index: Option<i64>) -> Option<&T> {
+  index
+    .and_then(|index| usize::try_from(index).ok())
+    .and_then(|index| items.and_then(|items| items.get(index)))
+}
+
+fn rule_id(run: &sarif::Run, result: &sarif::Result) -> Option<String> {
+  result
+    .rule_id
+    .clone()
+    .or_else(|| result.rule.as_ref().and_then(|rule| rule.id.clone()))
+    .or_else(|| run.resolve_rule(result).map(|rule| rule.id.clone()))
+}
+
+fn resolve_uri(
+  run: &sarif::Run,
+  location: &sarif::ArtifactLocation,
+) -> Option<String> {
+  location.uri.clone().or_else(|| {
+    get(run.artifacts.as_ref(), location.index)
+      .and_then(|artifact| artifact.location.as_ref())
+      .and_then(|location| location.uri.clone())
+  })
+}
+
+// Checks whether two sets of fingerprints share at least one fingerprint
+// and agree on every shared one
+fn fingerprints_agree(
+  a: Option<&BTreeMap<String, String>>,
+  b: Option<&BTreeMap<String, String>>,
+) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => {
+      let mut shared = a.iter().filter_map(|(k, v)| b.get(k).map(|w| v == w));
+      shared.next().is_some_and(|equal| equal) && shared.all(|equal| equal)
+    }
+    _ => false,
+  }
+}
+
+fn fingerprints_match(
+  current: &sarif::Result,
+  baseline: &sarif::Result,
+) -> bool {
+  fingerprints_agree(
+    current.fingerprints.as_ref(),
+    baseline.fingerprints.as_ref(),
+  ) || fingerprints_agree(
+    current.partial_fingerprints.as_ref(),
+    baseline.partial_fingerprints.as_ref(),
+  )
+}
+
+fn compare_runs(run: &mut sarif::Run, baseline_run: &sarif::Run) {
+  let baseline_results = baseline_run.results.as_deref().unwrap_or_default();
+  let baseline_keys: Vec<Key> = baseline_results
+    .iter()
+    .map(|result| Key::new(baseline_run, result))
+    .collect();
+  let results = run.results.as_deref().unwrap_or_default();
+  let keys: Vec<Key> =
+    results.iter().map(|result| Key::new(run, result)).collect();
+
+  // only results of the same rule are ever matched
+  let mut candidates: HashMap<&Option<String>, Vec<usize>> = HashMap::new();
+  baseline_keys
+    .iter()
+    .enumerate()
+    .for_each(|(j, key)| candidates.entry(&key.rule_id).or_default().push(j));
+
+  let mut matches: Vec<Option<usize>> = vec![None; results.len()];
+  let mut matched = vec![false; baseline_results.len()];
+  // each pass returns a score for a candidate pair, higher is better
+  let passes: [&dyn Fn(usize, usize) -> Option<f64>; 3] = [
+    &|i, j| {
+      if fingerprints_match(&results[i], &baseline_results[j]) {
+        Some(0.0)
+      } else {
+        None
+      }
+    },
+    &|i, j| {
+      if keys[i].artifact == baseline_keys[j].artifact
+        && keys[i].message == baseline_keys[j].message
+      {
+        Some(0.0)
+      } else {
+        None
+      }
+    },
+    &|i, j| {
+      let similarity = keys[i].similarity(&baseline_keys[j]);
+      if keys[i].artifact == baseline_keys[j].artifact
+        && similarity >= MESSAGE_SIMILARITY
+      {
+        Some(similarity)
+      } else {
+        None
+      }
+    },
+  ];
+  passes.iter().for_each(|pass| {
+    (0..results.len()).for_each(|i| {
+      if matches[i].is_some() {
+        return;
+      }
+      let best = candidates
+        .get(&keys[i].rule_id)
+        .into_iter()
+        .flatten()
+        .copied()
+        .filter(|j| !matched[*j])
+        .filter_map(|j| pass(i, j).map(|score| (j, score)))
+        .max_by(|(a, score_a), (b, score_b)| {
+          score_a
+            .partial_cmp(score_b)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| {
+              // prefer the nearest line
+              keys[i]
+                .distance(&baseline_keys[*b])
+                .cmp(&keys[i].distance(&baseline_keys[*a]))
+            })
+        });
+      if let Some((j, _)) = best {
+        matches[i] = Some(j);
+        matched[j] = true;
+      }
+    })
+  });
+
+  let states: Vec<sarif::ResultBaselineState> = matches
+    .iter()
+    .enumerate()
+    .map(|(i, j)| match j {
+      None => sarif::ResultBaselineState::New,
+      Some(j) => {
+        if keys[i].message == baseline_keys[*j].message
+          && keys[i].artifact == baseline_keys[*j].artifact
+        {
+          sarif::ResultBaselineState::Unchanged
+        } else {
+          sarif::ResultBaselineState::Updated
+        }
+      }
+    })
+    .collect();
+  run
+    .results
+    .iter_mut()
+    .flatten()
+    .zip(states)
+    .for_each(|(result, state)| result.baseline_state = Some(state));
+
+  let absent: Vec<sarif::Result> = baseline_results
+    .iter()
+    .zip(matched)
+    .filter(|(_, matched)| !matched)
+    .map(|(result, _)| absent_result(run, baseline_run, result))
+    .collect();
+  if !absent.is_empty() {
+    run.results.get_or_insert_with(Vec::new).extend(absent);
+  }
+}
+
+// Copies a baseline result into `run`, rewriting the indices which refer to
+// the baseline run
+fn absent_result(
+  run: &mut sarif::Run,
+  baseline_run: &sarif::Run,
+  result: &sarif::Result,
+) -> sarif::Result {
+  let mut absent = result.clone();
+  absent.baseline_state = Some(sarif::ResultBaselineState::Absent);
+
+  let uses_driver = result
+    .rule
+    .as_ref()
+    .and_then(|rule| rule.tool_component.as_ref())
+    .is_none();
+  if uses_driver && (absent.rule_index.is_some() || absent.rule.is_some()) {
+    let descriptor = get(
+      baseline_run.tool.driver.rules.as_ref(),
+      result
+        .rule_index
+        .or_else(|| result.rule.as_ref().and_then(|rule| rule.index)),
+    );
+    let rules = run.tool.driver.rules.get_or_insert_with(Vec::new);
+    let index = rule_id(baseline_run, result)
+      .and_then(|id| rules.iter().position(|rule| rule.id == id))
+      .or_else(|| {
+        // the rule is only known to the baseline, so it is copied over
+        descriptor.map(|descriptor| {
+          rules.push(descriptor.clone());
+          rules.len() - 1
+        })
+      })
+      .map(|index| index as i64);
+    if absent.rule_index.is_some() {
+      absent.rule_index = index;
+    }
+    if let Some(rule) = absent.rule.as_mut() {
+      rule.index = index;
+    }
+  }
+
+  let mut detach = Detach::new(baseline_run, run);
+  detach.visit_result_mut(&mut absent);
+  detach.copy_into(run);
+  absent
+}
+
+// Detaches a result copied from the baseline run from it: the objects of the
+// baseline run it refers to by index are inlined, ex. artifact locations and
+// thread flow locations, and the indices cleared. The graphs, uriBaseIds and
+// descriptors of tool components it still needs are collected, to be copied
+// into the current run by `copy_into`.
+struct Detach<'a> {
+  baseline_run: &'a sarif::Run,
+  // the number of graphs of the current run, which copied graphs follow
+  graph_offset: usize,
+  graphs: Vec<&'a sarif::Graph>,
+  uri_base_ids: Vec<String>,
+  // the taxa, and the rules of extensions, with their tool components
+  taxa: Vec<(&'a sarif::ToolComponent, &'a sarif::ReportingDescriptor)>,
+  rules: Vec<(&'a sarif::ToolComponent, &'a sarif::ReportingDescriptor)>,
+}
+
+impl<'a> Detach<'a> {
+  fn new(baseline_run: &'a sarif::Run, run: &sarif::Run) -> Self {
+    Detach {
+      baseline_run,
+      graph_offset: run.graphs.as_ref().map_or(0, |graphs| graphs.len()),
+      graphs: vec![],
+      uri_base_ids: vec![],
+      taxa: vec![],
+      rules: vec![],
+    }
+  }
+
+  // Copies what the detached results need into the current run, unless it
+  // has it already
+  fn copy_into(mut self, run: &mut sarif::Run) {
+    let mut graphs: Vec<sarif::Graph> =
+      self.graphs.iter().map(|&graph| graph.clone()).collect();
+    graphs
+      .iter_mut()
+      .for_each(|graph| self.visit_graph_mut(graph));
+    if !graphs.is_empty() {
+      run.graphs.get_or_insert_with(Vec::new).extend(graphs);
+    }
+
+    // base ids may themselves be relative to other base ids
+    let baseline_ids = self.baseline_run.original_uri_base_ids.as_ref();
+    while let Some(id) = self.uri_base_ids.pop() {
+      let ids = &mut run.original_uri_base_ids;
+      if ids.as_ref().is_some_and(|ids| ids.contains_key(&id)) {
+        continue;
+      }
+      if let Some(location) = baseline_ids.and_then(|ids| ids.get(&id)) {
+        self.uri_base_ids.extend(location.uri_base_id.clone());
+        ids
+          .get_or_insert_with(BTreeMap::new)
+          .insert(id, location.clone());
+      }
+    }
+
+    for (taxonomy, taxon) in self.taxa {
+      let taxonomies = run.taxonomies.get_or_insert_with(Vec::new);
+      copy_descriptor(taxonomies, taxonomy, taxon, |t| &mut t.taxa);
+    }
+    for (extension, rule) in self.rules {
+      let extensions = run.tool.extensions.get_or_insert_with(Vec::new);
+      copy_descriptor(extensions, extension, rule, |e| &mut e.rules);
+    }
+  }
+}
+
+// Adds a descriptor to the tool component of the same name in `components`,
+// unless it has one of the same id, adding the tool component if there is
+// none
+fn copy_descriptor(
+  components: &mut Vec<sarif::ToolComponent>,
+  component: &sarif::ToolComponent,
+  descriptor: &sarif::ReportingDescriptor,
+  descriptors: impl Fn(
+    &mut sarif::ToolComponent,
+  ) -> &mut Option<Vec<sarif::ReportingDescriptor>>,
+) {
+  match components.iter_mut().find(|c| c.name == component.name) {
+    Some(existing) => {
+      let existing = descriptors(existing).get_or_insert_with(Vec::new);
+      if !existing.iter().any(|d| d.id == descriptor.id) {
+        existing.push(descriptor.clone());
+      }
+    }
+    None => components.push(component.clone()),
+  }
+}
+
+// Fills the properties an object leaves unset with those of the object of
+// the baseline run it refers to by index
+fn inline<T: Serialize + DeserializeOwned>(object: &mut T, shared: Option<&T>) {
+  let shared = match shared {
+    Some(shared) => shared,
+    None => return,
+  };
+  if let (Ok(Value::Object(mut inlined)), Ok(Value::Object(local))) =
+    (serde_json::to_value(shared), serde_json::to_value(&*object))
+  {
+    inlined.extend(local);
+    if let Ok(inlined) = serde_json::from_value(Value::Object(inlined)) {
+      *object = inlined;
+    }
+  }
+}
+
+// Refers to a tool component by name and guid rather than by index
+fn refer_by_name(
+  reference: &mut sarif::ToolComponentReference,
+  component: &sarif::ToolComponent,
+) {
+  reference.name = Some(component.name.clone());
+  reference.guid = component.guid.clone();
+  reference.index = None;
+}
+
+// Refers to a descriptor by id and guid rather than by index
+fn refer_by_id(
+  reference: &mut sarif::ReportingDescriptorReference,
+  descriptor: &sarif::ReportingDescriptor,
+) {
+  reference.id = Some(descriptor.id.clone());
+  reference.guid = descriptor.guid.clone();
+  reference.index = None;
+}
+
+impl<'a> VisitMut for Detach<'a> {
+  fn visit_result_mut(&mut self, result: &mut sarif::Result) {
+    // a rule of an extension is referred to by name, and the driver's rules
+    // were mapped already
+    let baseline_run = self.baseline_run;
+    let component = result
+      .rule
+      .as_ref()
+      .and_then(|rule| rule.tool_component.as_ref())
+      .and_then(|c| baseline_run.resolve_tool_component(Some(c)));
+    if let (Some(component), Some(descriptor)) =
+      (component, baseline_run.resolve_rule(result))
+    {
+      if let Some(rule) = result.rule.as_mut() {
+        refer_by_id(rule, descriptor);
+        if let Some(reference) = rule.tool_component.as_mut() {
+          refer_by_name(reference, component);
+        }
+      }
+      self.rules.push((component, descriptor));
+    }
+    if let Some(provenance) = result.provenance.as_mut() {
+      provenance.invocation_index = None;
+    }
+    walk_result_mut(self, result)
+  }
+
+  // taxa, as references with a tool component other than the rule of a
+  // result, which is referred to by name already
+  fn visit_reporting_descriptor_reference_mut(
+    &mut self,
+    reference: &mut sarif::ReportingDescriptorReference,
+  ) {
+    let is_indexed = |reference: &sarif::ReportingDescriptorReference| {
+      reference.index.is_some()
+        || reference
+          .tool_component
+          .as_ref()
+          .is_some_and(|c| c.index.is_some())
+    };
+    if reference.tool_component.is_none() || !is_indexed(reference) {
+      return;
+    }
+    if let Some((taxonomy, taxon)) = resolve_taxon(self.baseline_run, reference)
+    {
+      refer_by_id(reference, taxon);
+      if let Some(component) = reference.tool_component.as_mut() {
+        refer_by_name(component, taxonomy);
+      }
+      self.taxa.push((taxonomy, taxon));
+    }
+  }
+
+  fn visit_artifact_location_mut(
+    &mut self,
+    location: &mut sarif::ArtifactLocation,
+  ) {
+    if location.uri.is_none() {
+      if let Some(artifact) =
+        get(self.baseline_run.artifacts.as_ref(), location.index)
+          .and_then(|artifact| artifact.location.as_ref())
+      {
+        location.uri = artifact.uri.clone();
+        location.uri_base_id = artifact.uri_base_id.clone();
+      }
+    }
+    location.index = None;
+    self.uri_base_ids.extend(location.uri_base_id.clone());
+  }
+
+  fn visit_logical_location_mut(
+    &mut self,
+    location: &mut sarif::LogicalLocation,
+  ) {
+    let shared =
+      get(self.baseline_run.logical_locations.as_ref(), location.index);
+    inline(location, shared);
+    location.index = None;
+    location.parent_index = None;
+  }
+
+  fn visit_address_mut(&mut self, address: &mut sarif::Address) {
+    inline(
+      address,
+      get(self.baseline_run.addresses.as_ref(), address.index),
+    );
+    address.index = None;
+    address.parent_index = None;
+  }
+
+  fn visit_thread_flow_location_mut(
+    &mut self,
+    location: &mut sarif::ThreadFlowLocation,
+  ) {
+    let shared = get(
+      self.baseline_run.thread_flow_locations.as_ref(),
+      location.index,
+    );
+    inline(location, shared);
+    location.index = None;
+    walk_thread_flow_location_mut(self, location)
+  }
+
+  fn visit_web_request_mut(&mut self, request: &mut sarif::WebRequest) {
+    inline(
+      request,
+      get(self.baseline_run.web_requests.as_ref(), request.index),
+    );
+    request.index = None;
+  }
+
+  fn visit_web_response_mut(&mut self, response: &mut sarif::WebResponse) {
+    inline(
+      response,
+      get(self.baseline_run.web_responses.as_ref(), response.index),
+    );
+    response.index = None;
+  }
+
+  fn visit_graph_traversal_mut(
+    &mut self,
+    traversal: &mut sarif::GraphTraversal,
+  ) {
+    let graph =
+      get(self.baseline_run.graphs.as_ref(), traversal.run_graph_index);
+    traversal.run_graph_index = graph.map(|graph| {
+      let position = self
+        .graphs
+        .iter()
+        .position(|g| std::ptr::eq(*g, graph))
+        .unwrap_or_else(|| {
+          self.graphs.push(graph);
+          self.graphs.len() - 1
+        });
+      (self.graph_offset + position) as i64
+    });
+    walk_graph_traversal_mut(self, traversal)
+  }
+}
@@ -1,15 +1,19 @@
+use super::{convert, ConvertOptions, Converter, ConverterError, Items};
+use crate::region::{ColumnUnit, SourceText};
 use crate::sarif::{self};
-use anyhow::Result;
 use derive_builder::Builder;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::convert::TryInto;
+use std::fs;
 use std::io::{BufRead, Write};
 
+/// A warning reported by clang-tidy.
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize, Builder)]
 #[builder(setter(into, strip_option))]
-struct ClangTidyResult {
+pub struct ClangTidyResult {
   pub file: Option<String>,
   pub line: Option<i64>,
   pub column: Option<i64>,
@@ -68,79 +72,114 @@ impl TryFrom<&ClangTidyResult> for sarif::Location {
   }
 }
 
-fn process<R: BufRead>(reader: R) -> Result<sarif::Sarif> {
-  let mut results = vec![];
-  let re = Regex::new(
-    r#"^(?P<file>[\w/\.\- ]+):(?P<line>\d+):(?P<column>\d+):\s+(?P<level>error|warning|info):\s+(?P<message>.+)\s+(?P<rules>\[[\w\-,\.]+\])$"#,
-  )?;
-  reader
-    .lines()
-    .into_iter()
-    .try_for_each(|line| -> Result<()> {
-      let line = line.unwrap();
-      let caps = re.captures(&line);
-      if let Some(caps) = caps {
-        if let Some(message) = caps.name("message") {
-          let result = ClangTidyResult {
-            file: caps.name("file").map(|f| f.as_str().into()),
-            line: caps
-              .name("line")
-              .and_then(|f| f.as_str().parse::<i64>().ok()),
-            column: caps
-              .name("column")
-              .and_then(|f| f.as_str().parse::<i64>().ok()),
-            level: caps
-              .name("level")
-              .map_or_else(|| "info".into(), |f| f.as_str().into()),
-            message: message.as_str().into(),
-            rules: caps
-              .name("rules")
-              .map_or_else(|| "".into(), |f| f.as_str().into()),
-          };
-          let location: sarif::Location = (&result).try_into()?;
-          let message = format!("{} {}", result.message, result.rules);
-          results.push(
-            sarif::ResultBuilder::default()
-              .message::<sarif::Message>((&message).try_into()?)
-              .locations(vec![location])
-              .level(result.level)
-              .build()?,
-          );
-        }
-      }
-      Ok(())
-    })?;
-
-  let tool_component: sarif::ToolComponent =
-    sarif::ToolComponentBuilder::default()
-      .name("clang-tidy")
-      .build()?;
-  let run = sarif::RunBuilder::default()
-    .tool::<sarif::Tool>(tool_component.try_into()?)
-    .results(results)
-    .build()?;
-
-  let sarif = sarif::SarifBuilder::default()
-    .version(sarif::Version::V2_1_0.to_string())
-    .runs(vec![run])
-    .build()?;
-
-  Ok(sarif)
+// clang-tidy reports byte columns, which are converted to the UTF-16 columns
+// of the run if the file can be read; each file is read and indexed once
+fn utf16_column(
+  sources: &mut HashMap<String, Option<SourceText<'static>>>,
+  result: &ClangTidyResult,
+) -> Option<i64> {
+  let source = sources
+    .entry(result.file.clone()?)
+    .or_insert_with_key(|file| {
+      fs::read_to_string(file).ok().map(SourceText::owned)
+    })
+    .as_ref()?;
+  source.convert_column(
+    result.line?,
+    result.column?,
+    ColumnUnit::Bytes,
+    ColumnUnit::Utf16CodeUnits,
+  )
 }
 
-/// Returns [sarif::Sarif] serialized into a JSON stream
+/// Converts the output of clang-tidy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClangTidyConverter;
+
+impl Converter for ClangTidyConverter {
+  type Item = ClangTidyResult;
+
+  fn name(&self) -> &str {
+    "clang-tidy"
+  }
+
+  fn run(
+    &self,
+    tool: sarif::ToolComponent,
+  ) -> Result<sarif::Run, ConverterError> {
+    Ok(
+      sarif::RunBuilder::default()
+        .tool::<sarif::Tool>(tool.try_into()?)
+        .column_kind(sarif::ResultColumnKind::Utf16CodeUnits)
+        .build()?,
+    )
+  }
+
+  fn parse<'r, R: BufRead + 'r>(
+    &self,
+    reader: R,
+  ) -> Result<Items<'r, Self::Item>, ConverterError> {
+    let re = Regex::new(
+      r#"^(?P<file>[\w/\.\- ]+):(?P<line>\d+):(?P<column>\d+):\s+(?P<level>error|warning|info):\s+(?P<message>.+)\s+(?P<rules>\[[\w\-,\.]+\])$"#,
+    )?;
+    let mut sources = HashMap::new();
+    Ok(Box::new(reader.lines().filter_map(move |line| {
+      let line = match line {
+        Ok(line) => line,
+        Err(err) => return Some(Err(err.into())),
+      };
+      let caps = re.captures(&line)?;
+      let message = caps.name("message")?;
+      let mut result = ClangTidyResult {
+        file: caps.name("file").map(|f| f.as_str().into()),
+        line: caps
+          .name("line")
+          .and_then(|f| f.as_str().parse::<i64>().ok()),
+        column: caps
+          .name("column")
+          .and_then(|f| f.as_str().parse::<i64>().ok()),
+        level: caps
+          .name("level")
+          .map_or_else(|| "info".into(), |f| f.as_str().into()),
+        message: message.as_str().into(),
+        rules: caps
+          .name("rules")
+          .map_or_else(|| "".into(), |f| f.as_str().into()),
+      };
+      result.column = utf16_column(&mut sources, &result).or(result.column);
+      Some(Ok(result))
+    })))
+  }
+
+  fn results(
+    &self,
+    result: &Self::Item,
+  ) -> Result<Vec<sarif::Result>, ConverterError> {
+    let location: sarif::Location = result.try_into()?;
+    let message = format!("{} {}", result.message, result.rules);
+    Ok(vec![sarif::ResultBuilder::default()
+      .message::<sarif::Message>((&message).try_into()?)
+      .locations(vec![location])
+      .level(match result.level.as_str() {
+        "error" => sarif::ResultLevel::Error,
+        "warning" => sarif::ResultLevel::Warning,
+        _ => sarif::ResultLevel::Note,
+      })
+      .build()?])
+  }
+}
+
+/// Writes [sarif::Sarif] serialized into a JSON stream, one result at a time
 ///
 /// # Arguments
 ///
-/// * `reader` - A `BufRead` of cargo output
+/// * `reader` - A `BufRead` of clang-tidy output
 /// * `writer` - A `Writer` to write the results to
 pub fn parse_to_writer<R: BufRead, W: Write>(
   reader: R,
   writer: W,
-) -> Result<()> {
-  let sarif = process(reader)?;
-  serde_json::to_writer_pretty(writer, &sarif)?;
-  Ok(())
+) -> Result<(), ConverterError> {
+  convert(&ClangTidyConverter, reader, writer, &ConvertOptions::new())
 }
 
 /// Returns [sarif::Sarif] serialized into a JSON string
@@ -148,8 +187,10 @@ pub fn parse_to_writer<R: BufRead, W: Write>(
 /// # Arguments
 ///
 /// * `reader` - A `BufRead` of clang-tidy output
-pub fn parse_to_string<R: BufRead>(reader: R) -> Result<String> {
-  let sarif = process(reader)?;
-  let json = serde_json::to_string_pretty(&sarif)?;
-  Ok(json)
+pub fn parse_to_string<R: BufRead>(
+  reader: R,
+) -> Result<String, ConverterError> {
+  let mut buffer = vec![];
+  parse_to_writer(reader, &mut buffer)?;
+  Ok(String::from_utf8(buffer)?)
 }
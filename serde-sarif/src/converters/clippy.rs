@@ -1,12 +1,12 @@
 use std::{
-  collections::HashMap,
   convert::From,
   convert::TryFrom,
   io::{BufRead, BufWriter, Write},
 };
 
+use super::{convert, ConvertOptions, Converter, ConverterError, Items};
+use crate::properties::GithubRuleProperties;
 use crate::sarif::{self, BuilderError};
-use anyhow::Result;
 use cargo_metadata::{
   self,
   diagnostic::{Diagnostic, DiagnosticLevel, DiagnosticSpan},
@@ -86,7 +86,7 @@ impl TryFrom<&DiagnosticSpan> for sarif::Location {
 fn build_global_message<W: Write>(
   diagnostic: &Diagnostic,
   writer: &mut BufWriter<W>,
-) -> Result<()> {
+) -> Result<(), ConverterError> {
   // if span exists, this message is local to a span, so skip it
   if diagnostic.spans.is_empty() {
     writeln!(writer, "{}", diagnostic.message)?;
@@ -98,93 +98,143 @@ fn build_global_message<W: Write>(
     .try_for_each(|diagnostic| build_global_message(diagnostic, writer))
 }
 
-fn process<R: BufRead>(reader: R) -> Result<sarif::Sarif> {
-  let mut results = vec![];
-  let mut map = HashMap::new();
-  let mut rules = vec![];
-  Message::parse_stream(reader)
-    .into_iter()
-    .filter_map(|r| r.ok())
-    .filter_map(|m| match m {
-      Message::CompilerMessage(msg) => Some(msg.message),
-      _ => None,
-    })
-    .try_for_each(|diagnostic| -> Result<()> {
-      diagnostic.spans.iter().try_for_each(|span| -> Result<()> {
-        let diagnostic_code = match &diagnostic.code {
-          Some(diagnostic_code) => diagnostic_code.code.clone(),
-          _ => "".into(),
-        };
-        if !map.contains_key(&diagnostic_code) {
-          let mut writer = BufWriter::new(Vec::new());
-          build_global_message(&diagnostic, &mut writer)?;
-          map.insert(diagnostic_code.clone(), map.len() as i64);
-          let mut rule = sarif::ReportingDescriptorBuilder::default();
-          rule
-            .id(&diagnostic_code)
-            .full_description::<sarif::MultiformatMessageString>(
-              (&String::from_utf8(writer.into_inner()?)?).try_into()?,
-            );
-
-          // help_uri is contained in a child diagnostic with a diagnostic level == help
-          // search for the relevant child diagnostic, then extract the uri from the message
-          if let Some(help_uri) = diagnostic
-            .children
-            .iter()
-            .find(|child| matches!(child.level, DiagnosticLevel::Help))
-            .and_then(|help| {
-              let re = regex::Regex::new(
-                r"^for further information visit (?P<url>\S+)",
-              )
-              .unwrap();
-              re.captures(&help.message)
-                .and_then(|captures| captures.name("url"))
-                .map(|re_match| re_match.as_str())
-            })
-          {
-            rule.help_uri(help_uri);
+// Returns the lint group of a clippy diagnostic, ex. pedantic, from the note
+// saying which group enabled the lint. Lints which are enabled by default
+// only name their group if they are denied, which only correctness lints
+// are by default.
+fn lint_group(diagnostic: &Diagnostic) -> Option<String> {
+  let re = regex::Regex::new(
+    r"implied by `(?:#\[\w+\(|-[WDF] ?)clippy::(?P<group>[\w-]+)",
+  )
+  .unwrap();
+  diagnostic.children.iter().find_map(|child| {
+    if let Some(captures) = re.captures(&child.message) {
+      return Some(captures["group"].replace('-', "_"));
+    }
+    if child.message.starts_with("`#[deny(clippy::")
+      && child.message.ends_with("on by default")
+    {
+      return Some("correctness".into());
+    }
+    None
+  })
+}
+
+/// Converts the output of `cargo clippy --message-format=json`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClippyConverter;
+
+impl Converter for ClippyConverter {
+  type Item = Diagnostic;
+
+  fn name(&self) -> &str {
+    "clippy"
+  }
+
+  fn tool(&self) -> Result<sarif::ToolComponent, ConverterError> {
+    Ok(
+      sarif::ToolComponentBuilder::default()
+        .name("clippy")
+        .information_uri("https://rust-lang.github.io/rust-clippy/")
+        .build()?,
+    )
+  }
+
+  fn parse<'r, R: BufRead + 'r>(
+    &self,
+    reader: R,
+  ) -> Result<Items<'r, Self::Item>, ConverterError> {
+    Ok(Box::new(
+      Message::parse_stream(reader)
+        .filter_map(|r| r.ok())
+        .filter_map(|m| match m {
+          // diagnostics without a span have no result
+          Message::CompilerMessage(msg) if !msg.message.spans.is_empty() => {
+            Some(Ok(msg.message))
           }
-          rules.push(rule.build()?);
-        }
-        if let Some(value) = map.get(&diagnostic_code) {
-          let level: sarif::ResultLevel = (&diagnostic.level).into();
-          results.push(
-            sarif::ResultBuilder::default()
-              .rule_id(diagnostic_code)
-              .rule_index(*value)
-              .message::<sarif::Message>((&diagnostic).try_into()?)
-              .locations(vec![span.try_into()?])
-              .level(level.to_string())
-              .build()?,
-          );
-        }
-        Ok(())
-      })?;
-
-      Ok(())
+          _ => None,
+        }),
+    ))
+  }
+
+  fn rule_id(&self, diagnostic: &Self::Item) -> Option<String> {
+    Some(match &diagnostic.code {
+      Some(diagnostic_code) => diagnostic_code.code.clone(),
+      _ => "".into(),
+    })
+  }
+
+  fn rule(
+    &self,
+    diagnostic: &Self::Item,
+    id: &str,
+  ) -> Result<sarif::ReportingDescriptor, ConverterError> {
+    let mut writer = BufWriter::new(Vec::new());
+    build_global_message(diagnostic, &mut writer)?;
+    let mut rule = sarif::ReportingDescriptorBuilder::default();
+    rule
+      .id(id)
+      .full_description::<sarif::MultiformatMessageString>(
+        (&String::from_utf8(
+          writer.into_inner().map_err(|err| err.into_error())?,
+        )?)
+          .try_into()?,
+      );
+
+    // help_uri is contained in a child diagnostic with a diagnostic level == help
+    // search for the relevant child diagnostic, then extract the uri from the message
+    if let Some(help_uri) = diagnostic
+      .children
+      .iter()
+      .find(|child| matches!(child.level, DiagnosticLevel::Help))
+      .and_then(|help| {
+        let re =
+          regex::Regex::new(r"^for further information visit (?P<url>\S+)")
+            .unwrap();
+        re.captures(&help.message)
+          .and_then(|captures| captures.name("url"))
+          .map(|re_match| re_match.as_str())
+      })
+    {
+      rule.help_uri(help_uri);
+    }
+
+    // tag the rule with its lint group, ex. pedantic, for GitHub code scanning
+    let level: sarif::ResultLevel = (&diagnostic.level).into();
+    let mut properties = sarif::PropertyBag::default();
+    if let Some(group) = lint_group(diagnostic) {
+      properties.add_tag(group);
+    }
+    properties.set_extension(&GithubRuleProperties {
+      problem_severity: Some((&level).into()),
+      ..Default::default()
     })?;
+    rule.properties(properties);
+    Ok(rule.build()?)
+  }
 
-  let tool_component: sarif::ToolComponent =
-    sarif::ToolComponentBuilder::default()
-      .name("clippy")
-      .information_uri("https://rust-lang.github.io/rust-clippy/")
-      .rules(rules)
-      .build()?;
-  let run = sarif::RunBuilder::default()
-    .tool::<sarif::Tool>(tool_component.try_into()?)
-    .results(results)
-    .build()?;
-
-  let sarif = sarif::SarifBuilder::default()
-    .version(sarif::Version::V2_1_0.to_string())
-    .schema(sarif::SCHEMA_URL)
-    .runs(vec![run])
-    .build()?;
-
-  Ok(sarif)
+  fn results(
+    &self,
+    diagnostic: &Self::Item,
+  ) -> Result<Vec<sarif::Result>, ConverterError> {
+    let level: sarif::ResultLevel = (&diagnostic.level).into();
+    diagnostic
+      .spans
+      .iter()
+      .map(|span| {
+        Ok(
+          sarif::ResultBuilder::default()
+            .message::<sarif::Message>(diagnostic.try_into()?)
+            .locations(vec![span.try_into()?])
+            .level(level.clone())
+            .build()?,
+        )
+      })
+      .collect()
+  }
 }
 
-/// Returns [sarif::Sarif] serialized into a JSON stream
+/// Writes [sarif::Sarif] serialized into a JSON stream, one result at a time
 ///
 /// # Arguments
 ///
@@ -193,19 +243,18 @@ fn process<R: BufRead>(reader: R) -> Result<sarif::Sarif> {
 pub fn parse_to_writer<R: BufRead, W: Write>(
   reader: R,
   writer: W,
-) -> Result<()> {
-  let sarif = process(reader)?;
-  serde_json::to_writer_pretty(writer, &sarif)?;
-  Ok(())
+) -> Result<(), ConverterError> {
+  convert(&ClippyConverter, reader, writer, &ConvertOptions::new())
 }
-
 /// Returns [sarif::Sarif] serialized into a JSON string
 ///
 /// # Arguments
 ///
 /// * `reader` - A `BufRead` of cargo clippy output
-pub fn parse_to_string<R: BufRead>(reader: R) -> Result<String> {
-  let sarif = process(reader)?;
-  let json = serde_json::to_string_pretty(&sarif)?;
-  Ok(json)
+pub fn parse_to_string<R: BufRead>(
+  reader: R,
+) -> Result<String, ConverterError> {
+  let mut buffer = vec![];
+  parse_to_writer(reader, &mut buffer)?;
+  Ok(String::from_utf8(buffer)?)
 }
@@ -1,5 +1,4 @@
 use std::{
-  collections::HashMap,
   convert::TryFrom,
   io::{BufRead, Write},
   str::FromStr,
@@ -8,15 +7,16 @@ use std::{
 use strum_macros::Display;
 use strum_macros::EnumString;
 
+use super::{convert, ConvertOptions, Converter, ConverterError, Items};
 use crate::sarif::{self, BuilderError, ResultLevel};
-use anyhow::Result;
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 
+/// A warning reported by hadolint.
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize, Builder)]
 #[builder(setter(into, strip_option))]
-struct HadolintResult {
+pub struct HadolintResult {
   file: String,
   line: i64,
   column: i64,
@@ -90,82 +90,79 @@ impl TryFrom<&HadolintResult> for sarif::Region {
   }
 }
 
-fn process<R: BufRead>(mut reader: R) -> Result<sarif::Sarif> {
-  let mut data = String::new();
-  reader.read_to_string(&mut data)?;
-  let mut results = vec![];
-  let mut map = HashMap::new();
-  let mut rules = vec![];
-
-  let hadolint_results: Vec<HadolintResult> = serde_json::from_str(&data)?;
-  hadolint_results
-    .iter()
-    .try_for_each(|result| -> Result<()> {
-      if !map.contains_key(&result.code) {
-        map.insert(result.code.clone(), map.len() as i64);
-        rules.push(
-          sarif::ReportingDescriptorBuilder::default()
-            .id(result.code.clone())
-            .name(result.code.clone())
-            .short_description::<sarif::MultiformatMessageString>(
-              (&result.code as &String).try_into()?,
-            )
-            .full_description::<sarif::MultiformatMessageString>(
-              (&format!(
-                "For more information: https://github.com/hadolint/hadolint/wiki/{}",
-                result.code
-              ))
-                .try_into()?,
-            )
-            .build()?,
-        );
-      }
-      if let Some(value) = map.get(&result.code) {
-        let level: sarif::ResultLevel =
-          HadolintLevel::from_str(&result.level)?.into();
-        results.push(
-          sarif::ResultBuilder::default()
-            .rule_id(result.code.clone())
-            .rule_index(*value)
-            .message::<sarif::Message>((&result.message).try_into()?)
-            .locations(vec![result.try_into()?])
-            .level(level.to_string())
-            .build()?,
-        );
-      }
-      Ok(())
-    })?;
-  let tool_component: sarif::ToolComponent =
-    sarif::ToolComponentBuilder::default()
-      .name("hadolint")
-      .rules(rules)
-      .build()?;
-  let run = sarif::RunBuilder::default()
-    .tool::<sarif::Tool>(tool_component.try_into()?)
-    .results(results)
-    .build()?;
-
-  let sarif = sarif::SarifBuilder::default()
-    .version(sarif::Version::V2_1_0.to_string())
-    .runs(vec![run])
-    .build()?;
-
-  Ok(sarif)
+/// Converts the output of `hadolint -f json`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HadolintConverter;
+
+impl Converter for HadolintConverter {
+  type Item = HadolintResult;
+
+  fn name(&self) -> &str {
+    "hadolint"
+  }
+
+  fn parse<'r, R: BufRead + 'r>(
+    &self,
+    mut reader: R,
+  ) -> Result<Items<'r, Self::Item>, ConverterError> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+    let results: Vec<HadolintResult> = serde_json::from_str(&data)?;
+    Ok(Box::new(results.into_iter().map(Ok)))
+  }
+
+  fn rule_id(&self, result: &Self::Item) -> Option<String> {
+    Some(result.code.clone())
+  }
+
+  fn rule(
+    &self,
+    result: &Self::Item,
+    id: &str,
+  ) -> Result<sarif::ReportingDescriptor, ConverterError> {
+    Ok(
+      sarif::ReportingDescriptorBuilder::default()
+        .id(id)
+        .name(id)
+        .short_description::<sarif::MultiformatMessageString>(
+          (&result.code as &String).try_into()?,
+        )
+        .full_description::<sarif::MultiformatMessageString>(
+          (&format!(
+            "For more information: https://github.com/hadolint/hadolint/wiki/{}",
+            result.code
+          ))
+            .try_into()?,
+        )
+        .build()?,
+    )
+  }
+
+  fn results(
+    &self,
+    result: &Self::Item,
+  ) -> Result<Vec<sarif::Result>, ConverterError> {
+    let level: sarif::ResultLevel =
+      HadolintLevel::from_str(&result.level)?.into();
+    Ok(vec![sarif::ResultBuilder::default()
+      .message::<sarif::Message>((&result.message).try_into()?)
+      .locations(vec![result.try_into()?])
+      .level(level)
+      .build()?])
+  }
 }
 
-/// Returns [sarif::Sarif] serialized into a JSON stream
+/// Writes [sarif::Sarif] serialized into a JSON stream, one result at a time
 ///
 /// # Arguments
 ///
-/// * `reader` - A `BufRead` of cargo output
+/// * `reader` - A `BufRead` of hadolint output
 /// * `writer` - A `Writer` to write the results to
 pub fn parse_to_writer<R: BufRead, W: Write>(
   reader: R,
   writer: W,
-) -> Result<()> {
-  let sarif = process(reader)?;
-  serde_json::to_writer_pretty(writer, &sarif)?;
-  Ok(())
+) -> Result<(), ConverterError> {
+  convert(&HadolintConverter, reader, writer, &ConvertOptions::new())
 }
 
 /// Returns [sarif::Sarif] serialized into a JSON string
@@ -173,8 +170,10 @@ pub fn parse_to_writer<R: BufRead, W: Write>(
 /// # Arguments
 ///
 /// * `reader` - A `BufRead` of hadolint output
-pub fn parse_to_string<R: BufRead>(reader: R) -> Result<String> {
-  let sarif = process(reader)?;
-  let json = serde_json::to_string_pretty(&sarif)?;
-  Ok(json)
+pub fn parse_to_string<R: BufRead>(
+  reader: R,
+) -> Result<String, ConverterError> {
+  let mut buffer = vec![];
+  parse_to_writer(reader, &mut buffer)?;
+  Ok(String::from_utf8(buffer)?)
 }
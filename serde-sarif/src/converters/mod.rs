@@ -1,3 +1,511 @@
+//! Converters from the output of static analysis tools to SARIF, each behind
+//! a feature of the same name.
+//!
+//! A converter implements [Converter]: it names its tool, parses the output of
+//! the tool into items, ex. diagnostics, and turns each item into results and
+//! the rule they violate. [convert] does the rest, which is shared by every
+//! converter: it assembles the run, adds each rule once, sets the `ruleId` and
+//! `ruleIndex` of the results, and streams the log to a writer as configured
+//! by [ConvertOptions]. A [Registry] looks converters up by the name of their
+//! tool.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::converters::{
+//!   Converter, ConverterError, ConvertOptions, Items, Registry,
+//! };
+//! use serde_sarif::sarif;
+//! use std::convert::TryInto;
+//! use std::io::BufRead;
+//!
+//! // converts lines like `src/main.rs:3: TODO: remove this`
+//! struct Todo;
+//!
+//! impl Converter for Todo {
+//!   type Item = (String, i64, String);
+//!
+//!   fn name(&self) -> &str {
+//!     "todo"
+//!   }
+//!
+//!   fn parse<'r, R: BufRead + 'r>(
+//!     &self,
+//!     reader: R,
+//!   ) -> Result<Items<'r, Self::Item>, ConverterError> {
+//!     Ok(Box::new(reader.lines().filter_map(|line| {
+//!       let line = match line {
+//!         Ok(line) => line,
+//!         Err(err) => return Some(Err(err.into())),
+//!       };
+//!       let mut parts = line.splitn(3, ':');
+//!       let file = parts.next()?.to_string();
+//!       let line = parts.next()?.parse().ok()?;
+//!       Some(Ok((file, line, parts.next()?.trim().to_string())))
+//!     })))
+//!   }
+//!
+//!   fn rule_id(&self, _: &Self::Item) -> Option<String> {
+//!     Some("TODO".into())
+//!   }
+//!
+//!   fn results(
+//!     &self,
+//!     (file, line, text): &Self::Item,
+//!   ) -> Result<Vec<sarif::Result>, ConverterError> {
+//!     let location = sarif::LocationBuilder::default()
+//!       .physical_location(
+//!         sarif::PhysicalLocationBuilder::default()
+//!           .artifact_location(
+//!             sarif::ArtifactLocationBuilder::default().uri(file).build()?,
+//!           )
+//!           .region(sarif::RegionBuilder::default().start_line(*line).build()?)
+//!           .build()?,
+//!       )
+//!       .build()?;
+//!     Ok(vec![sarif::ResultBuilder::default()
+//!       .message::<sarif::Message>(text.as_str().try_into()?)
+//!       .locations(vec![location])
+//!       .build()?])
+//!   }
+//! }
+//!
+//! let registry = Registry::new().register(Todo);
+//! let mut output = vec![];
+//! registry
+//!   .convert(
+//!     "todo",
+//!     &mut "a.rs:1: TODO: one\nb.rs:2: TODO: two\n".as_bytes(),
+//!     &mut output,
+//!     &ConvertOptions::new(),
+//!   )
+//!   .unwrap();
+//!
+//! let sarif: sarif::Sarif = serde_json::from_slice(&output).unwrap();
+//! let run = &sarif.runs[0];
+//! assert_eq!(run.tool.driver.rules.as_ref().map(Vec::len), Some(1));
+//! assert_eq!(run.results.as_ref().unwrap()[1].rule_index, Some(0));
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use serde_json::ser::Formatter;
+use thiserror::Error;
+
+use crate::fingerprint::Fingerprinter;
+use crate::properties::PropertyError;
+use crate::sarif::{self, BuilderError, IntoBuilderError};
+use crate::stream::SarifStreamWriter;
+use crate::taxonomy::{TaxonomyError, TaxonomyMapping};
+
+/// An error converting the output of a tool to SARIF.
+///
+/// The error of every builder converts into it, ex.
+///
+/// ```rust
+/// use serde_sarif::converters::ConverterError;
+/// use serde_sarif::sarif::{BuilderError, ToolComponentBuilder};
+///
+/// let error: ConverterError =
+///   ToolComponentBuilder::default().build().unwrap_err().into();
+/// assert!(matches!(
+///   error,
+///   ConverterError::Builder(BuilderError::ToolComponentBuilderError { .. })
+/// ));
+/// ```
+#[derive(Error, Debug)]
+pub enum ConverterError {
+  #[error(transparent)]
+  Builder(#[from] BuilderError),
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+  #[error(transparent)]
+  Regex(#[from] regex::Error),
+  #[error("unknown level: {0}")]
+  Level(#[from] strum::ParseError),
+  #[error(transparent)]
+  Property(#[from] PropertyError),
+  #[error(transparent)]
+  Taxonomy(#[from] TaxonomyError),
+  #[error(transparent)]
+  Utf8(#[from] std::string::FromUtf8Error),
+  #[error("no converter for {0}")]
+  UnknownConverter(String),
+}
+
+// the error of each builder converts into a ConverterError via BuilderError
+impl<E: IntoBuilderError> From<E> for ConverterError {
+  fn from(error: E) -> Self {
+    ConverterError::Builder(error.into())
+  }
+}
+
+/// The items parsed from the output of a tool.
+pub type Items<'r, T> =
+  Box<dyn Iterator<Item = Result<T, ConverterError>> + 'r>;
+
+/// A converter from the output of a tool to SARIF, see [convert].
+pub trait Converter {
+  /// An item of the output of the tool, ex. a diagnostic
+  type Item;
+
+  /// Returns the name of the tool, which is the name of its driver
+  fn name(&self) -> &str;
+
+  /// Returns the driver of the run, without rules; the default only has a
+  /// name
+  fn tool(&self) -> Result<sarif::ToolComponent, ConverterError> {
+    Ok(
+      sarif::ToolComponentBuilder::default()
+        .name(self.name())
+        .build()?,
+    )
+  }
+
+  /// Returns the run the results are written to, ex. to set its
+  /// `columnKind`; the default only has the tool
+  ///
+  /// # Arguments
+  ///
+  /// * `tool` - The driver of the run
+  fn run(
+    &self,
+    tool: sarif::ToolComponent,
+  ) -> Result<sarif::Run, ConverterError> {
+    Ok(
+      sarif::RunBuilder::default()
+        .tool::<sarif::Tool>(tool.try_into()?)
+        .build()?,
+    )
+  }
+
+  /// Parses the output of the tool into items
+  ///
+  /// # Arguments
+  ///
+  /// * `reader` - A `BufRead` of the output of the tool
+  fn parse<'r, R: BufRead + 'r>(
+    &self,
+    reader: R,
+  ) -> Result<Items<'r, Self::Item>, ConverterError>;
+
+  /// Returns the id of the rule an item violates, if any; the default is
+  /// none
+  ///
+  /// # Arguments
+  ///
+  /// * `item` - An item of the output
+  fn rule_id(&self, _item: &Self::Item) -> Option<String> {
+    None
+  }
+
+  /// Returns the rule of the first item which violates it; the default only
+  /// has an id
+  ///
+  /// # Arguments
+  ///
+  /// * `item` - An item of the output
+  /// * `id` - The id of the rule, see [Converter::rule_id]
+  fn rule(
+    &self,
+    _item: &Self::Item,
+    id: &str,
+  ) -> Result<sarif::ReportingDescriptor, ConverterError> {
+    Ok(
+      sarif::ReportingDescriptorBuilder::default()
+        .id(id)
+        .build()?,
+    )
+  }
+
+  /// Returns the results of an item. Their `ruleId` and `ruleIndex` are set
+  /// to the rule of the item unless they are already set.
+  ///
+  /// # Arguments
+  ///
+  /// * `item` - An item of the output
+  fn results(
+    &self,
+    item: &Self::Item,
+  ) -> Result<Vec<sarif::Result>, ConverterError>;
+
+  /// Returns the invocation of the tool, which is added to the run once
+  /// every item is converted; the default is none
+  fn invocation(&self) -> Result<Option<sarif::Invocation>, ConverterError> {
+    Ok(None)
+  }
+}
+
+/// Options of the log written by [convert].
+#[derive(Clone, Debug)]
+pub struct ConvertOptions {
+  pretty: bool,
+  taxonomies: Option<TaxonomyMapping>,
+  fingerprint_root: Option<PathBuf>,
+}
+
+impl Default for ConvertOptions {
+  fn default() -> Self {
+    ConvertOptions {
+      pretty: true,
+      taxonomies: None,
+      fingerprint_root: None,
+    }
+  }
+}
+
+impl ConvertOptions {
+  /// Returns the default options, which pretty print the log
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets whether the log is pretty printed
+  ///
+  /// # Arguments
+  ///
+  /// * `pretty` - Whether to pretty print the log
+  pub fn pretty(mut self, pretty: bool) -> Self {
+    self.pretty = pretty;
+    self
+  }
+
+  /// Categorizes the rules by the taxa a mapping maps them to
+  ///
+  /// # Arguments
+  ///
+  /// * `mapping` - The mapping from rule ids to taxa
+  pub fn taxonomies(mut self, mapping: TaxonomyMapping) -> Self {
+    self.taxonomies = Some(mapping);
+    self
+  }
+
+  /// Adds `partialFingerprints` to each result as it is written, computed
+  /// from the source files the results refer to, see [Fingerprinter]. This
+  /// lets Github code scanning keep track of alerts when the code around
+  /// them moves.
+  ///
+  /// # Arguments
+  ///
+  /// * `root` - The directory relative URIs are resolved against, usually
+  ///   the one the tool was run in
+  pub fn fingerprints<P: Into<PathBuf>>(mut self, root: P) -> Self {
+    self.fingerprint_root = Some(root.into());
+    self
+  }
+}
+
+/// Converts the output of a tool, writing a SARIF log with a single run one
+/// result at a time
+///
+/// # Arguments
+///
+/// * `converter` - The converter of the tool's output
+/// * `reader` - A `BufRead` of the output of the tool
+/// * `writer` - A `Writer` to write the log to
+/// * `options` - The options of the log
+pub fn convert<C, R, W>(
+  converter: &C,
+  reader: R,
+  writer: W,
+  options: &ConvertOptions,
+) -> Result<(), ConverterError>
+where
+  C: Converter + ?Sized,
+  R: BufRead,
+  W: Write,
+{
+  if options.pretty {
+    let mut log = SarifStreamWriter::pretty(writer)?;
+    write_run(converter, reader, &mut log, options)?;
+    log.finish()?;
+  } else {
+    let mut log = SarifStreamWriter::new(writer)?;
+    write_run(converter, reader, &mut log, options)?;
+    log.finish()?;
+  }
+  Ok(())
+}
+
+fn write_run<C, R, W, F>(
+  converter: &C,
+  reader: R,
+  log: &mut SarifStreamWriter<W, F>,
+  options: &ConvertOptions,
+) -> Result<(), ConverterError>
+where
+  C: Converter + ?Sized,
+  R: BufRead,
+  W: Write,
+  F: Formatter + Clone,
+{
+  let mapping = options.taxonomies.as_ref();
+  let mut fingerprinter =
+    options.fingerprint_root.clone().map(Fingerprinter::new);
+  let mut run = converter.run(converter.tool()?)?;
+  if let Some(mapping) = mapping {
+    mapping.apply(&mut run);
+  }
+  let mut run = log.begin_run(run)?;
+  // the index of each rule added to the run
+  let mut rules = HashMap::new();
+  for item in converter.parse(reader)? {
+    let item = item?;
+    let rule = match converter.rule_id(&item) {
+      Some(id) => {
+        let index = match rules.get(&id) {
+          Some(index) => *index,
+          None => {
+            let mut rule = converter.rule(&item, &id)?;
+            if let Some(mapping) = mapping {
+              mapping.categorize(&mut rule);
+            }
+            let index = run.write_rule(rule);
+            rules.insert(id.clone(), index);
+            index
+          }
+        };
+        Some((id, index))
+      }
+      None => None,
+    };
+    for mut result in converter.results(&item)? {
+      if let Some((id, index)) = rule.as_ref() {
+        result.rule_id.get_or_insert_with(|| id.clone());
+        result.rule_index.get_or_insert(*index);
+      }
+      if let Some(fingerprinter) = fingerprinter.as_mut() {
+        fingerprinter.fingerprint_result(run.run(), &mut result);
+      }
+      run.write_result(&result)?;
+    }
+  }
+  if let Some(invocation) = converter.invocation()? {
+    run.write_invocation(invocation);
+  }
+  run.end()?;
+  Ok(())
+}
+
+/// A [Converter] whose items are hidden, so that converters of different
+/// tools can be kept together, ex. in a [Registry]. Every converter is one.
+pub trait DynConverter {
+  /// Returns the name of the tool
+  fn name(&self) -> &str;
+
+  /// Converts the output of the tool, see [convert]
+  ///
+  /// # Arguments
+  ///
+  /// * `reader` - A `BufRead` of the output of the tool
+  /// * `writer` - A `Writer` to write the log to
+  /// * `options` - The options of the log
+  fn convert(
+    &self,
+    reader: &mut dyn BufRead,
+    writer: &mut dyn Write,
+    options: &ConvertOptions,
+  ) -> Result<(), ConverterError>;
+}
+
+impl<C: Converter> DynConverter for C {
+  fn name(&self) -> &str {
+    Converter::name(self)
+  }
+
+  fn convert(
+    &self,
+    reader: &mut dyn BufRead,
+    writer: &mut dyn Write,
+    options: &ConvertOptions,
+  ) -> Result<(), ConverterError> {
+    convert(self, reader, writer, options)
+  }
+}
+
+/// Converters keyed by the name of their tool.
+#[derive(Default)]
+pub struct Registry {
+  converters: BTreeMap<String, Box<dyn DynConverter>>,
+}
+
+impl Registry {
+  /// Returns an empty registry
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns a registry of the converters of this crate whose features are
+  /// enabled
+  pub fn builtin() -> Self {
+    let registry = Self::new();
+    #[cfg(feature = "clang-tidy-converters")]
+    let registry = registry.register(clang_tidy::ClangTidyConverter);
+    #[cfg(feature = "clippy-converters")]
+    let registry = registry.register(clippy::ClippyConverter);
+    #[cfg(feature = "hadolint-converters")]
+    let registry = registry.register(hadolint::HadolintConverter);
+    #[cfg(feature = "shellcheck-converters")]
+    let registry = registry.register(shellcheck::ShellcheckConverter);
+    registry
+  }
+
+  /// Adds a converter, replacing the converter of the same tool if any
+  ///
+  /// # Arguments
+  ///
+  /// * `converter` - The converter to add
+  pub fn register<C: DynConverter + 'static>(mut self, converter: C) -> Self {
+    self
+      .converters
+      .insert(converter.name().to_string(), Box::new(converter));
+    self
+  }
+
+  /// Returns the converter of a tool
+  ///
+  /// # Arguments
+  ///
+  /// * `name` - The name of the tool
+  pub fn get(&self, name: &str) -> Option<&dyn DynConverter> {
+    self
+      .converters
+      .get(name)
+      .map(|converter| converter.as_ref())
+  }
+
+  /// Returns the names of the tools which have a converter, in order
+  pub fn names(&self) -> impl Iterator<Item = &str> {
+    self.converters.keys().map(String::as_str)
+  }
+
+  /// Converts the output of a tool with its converter, see [convert]
+  ///
+  /// # Arguments
+  ///
+  /// * `name` - The name of the tool
+  /// * `reader` - A `BufRead` of the output of the tool
+  /// * `writer` - A `Writer` to write the log to
+  /// * `options` - The options of the log
+  pub fn convert(
+    &self,
+    name: &str,
+    reader: &mut dyn BufRead,
+    writer: &mut dyn Write,
+    options: &ConvertOptions,
+  ) -> Result<(), ConverterError> {
+    self
+      .get(name)
+      .ok_or_else(|| ConverterError::UnknownConverter(name.to_string()))?
+      .convert(reader, writer, options)
+  }
+}
+
 #[cfg(feature = "clippy-converters")]
 #[cfg_attr(doc_cfg, doc(cfg(feature = "clippy-converters")))]
 pub mod clippy;
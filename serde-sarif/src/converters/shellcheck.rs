@@ -1,5 +1,4 @@
 use std::{
-  collections::HashMap,
   convert::TryFrom,
   io::{BufRead, Write},
   str::FromStr,
@@ -8,15 +7,16 @@ use std::{
 use strum_macros::Display;
 use strum_macros::EnumString;
 
+use super::{convert, ConvertOptions, Converter, ConverterError, Items};
 use crate::sarif::{self, BuilderError, ResultLevel};
-use anyhow::Result;
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
 
+/// A warning reported by shellcheck.
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize, Builder)]
 #[builder(setter(into, strip_option))]
-struct ShellcheckResult {
+pub struct ShellcheckResult {
   file: String,
   line: i64,
   #[serde(rename = "endLine")]
@@ -135,125 +135,146 @@ impl TryFrom<&ShellcheckResult> for sarif::Region {
   }
 }
 
-fn process<R: BufRead>(mut reader: R) -> Result<sarif::Sarif> {
-  let mut data = String::new();
-  reader.read_to_string(&mut data)?;
-  let mut results = vec![];
-  let mut map = HashMap::new();
-  let mut rules = vec![];
+/// Converts the output of `shellcheck -f json`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShellcheckConverter;
 
-  let shellcheck_results: Vec<ShellcheckResult> = serde_json::from_str(&data)?;
-  shellcheck_results
-    .iter()
-    .try_for_each(|result| -> Result<()> {
-      #[allow(clippy::map_entry)]
-      if !map.contains_key(&result.code.to_string()) {
-        map.insert(result.code.to_string(), map.len() as i64);
-        rules.push(
-          sarif::ReportingDescriptorBuilder::default()
-            .id(result.code.to_string())
-            .name(result.code.to_string())
-            .short_description::<sarif::MultiformatMessageString>(
-              (&format!("SC{}", result.code)).try_into()?,
-            )
-            .full_description::<sarif::MultiformatMessageString>(
-              (&format!(
-                "For more information: https://www.shellcheck.net/wiki/SC{}",
-                result.code
-              ))
-                .try_into()?,
+impl Converter for ShellcheckConverter {
+  type Item = ShellcheckResult;
+
+  fn name(&self) -> &str {
+    "shellcheck"
+  }
+
+  fn run(
+    &self,
+    tool: sarif::ToolComponent,
+  ) -> Result<sarif::Run, ConverterError> {
+    Ok(
+      sarif::RunBuilder::default()
+        .tool::<sarif::Tool>(tool.try_into()?)
+        // shellcheck counts columns in characters
+        .column_kind(sarif::ResultColumnKind::UnicodeCodePoints)
+        .build()?,
+    )
+  }
+
+  fn parse<'r, R: BufRead + 'r>(
+    &self,
+    mut reader: R,
+  ) -> Result<Items<'r, Self::Item>, ConverterError> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+    let results: Vec<ShellcheckResult> = serde_json::from_str(&data)?;
+    Ok(Box::new(results.into_iter().map(Ok)))
+  }
+
+  fn rule_id(&self, result: &Self::Item) -> Option<String> {
+    Some(result.code.to_string())
+  }
+
+  fn rule(
+    &self,
+    result: &Self::Item,
+    id: &str,
+  ) -> Result<sarif::ReportingDescriptor, ConverterError> {
+    Ok(
+      sarif::ReportingDescriptorBuilder::default()
+        .id(id)
+        .name(id)
+        .short_description::<sarif::MultiformatMessageString>(
+          (&format!("SC{}", result.code)).try_into()?,
+        )
+        .full_description::<sarif::MultiformatMessageString>(
+          (&format!(
+            "For more information: https://www.shellcheck.net/wiki/SC{}",
+            result.code
+          ))
+            .try_into()?,
+        )
+        .build()?,
+    )
+  }
+
+  fn results(
+    &self,
+    result: &Self::Item,
+  ) -> Result<Vec<sarif::Result>, ConverterError> {
+    let level: sarif::ResultLevel =
+      ShellcheckLevel::from_str(&result.level)?.into();
+    // the replacements of a shellcheck fix are made together, as one fix
+    let fixes = match result.fix.as_ref() {
+      Some(fix) => {
+        let replacements = fix
+          .replacements
+          .iter()
+          .map(|replacement| -> Result<sarif::Replacement, BuilderError> {
+            Ok(
+              sarif::ReplacementBuilder::default()
+                .deleted_region::<sarif::Region>(replacement.try_into()?)
+                .inserted_content(
+                  sarif::ArtifactContentBuilder::default()
+                    .text(&replacement.replacement)
+                    .build()?,
+                )
+                .build()?,
             )
-            .build()?,
-        );
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        vec![sarif::FixBuilder::default()
+          .description::<sarif::Message>((&result.message).try_into()?)
+          .artifact_changes(vec![sarif::ArtifactChangeBuilder::default()
+            .artifact_location::<sarif::ArtifactLocation>(result.try_into()?)
+            .replacements(replacements)
+            .build()?])
+          .build()?]
       }
-      if let Some(value) = map.get(&result.code.to_string()) {
-        let level: sarif::ResultLevel =
-          ShellcheckLevel::from_str(&result.level)?.into();
-        let fixes = if let Some(fix) = result.fix.as_ref() {
-          fix
-            .replacements
-            .iter()
-            .map(|fix| -> Result<sarif::Fix> {
-              Ok(
-                sarif::FixBuilder::default()
-                  .description::<sarif::Message>((&fix.replacement).try_into()?)
-                  .build()?,
-              )
-            })
-            .filter_map(|v| v.ok())
-            .collect()
-        } else {
-          vec![]
-        };
-        let related_locations = if let Some(fix) = result.fix.as_ref() {
-          fix
-            .replacements
-            .iter()
-            .map(|replacement| -> Result<sarif::Location> {
-              let region: sarif::Region = replacement.try_into()?;
-              let artifact_location: sarif::ArtifactLocation =
-                result.try_into()?;
-              Ok(
-                sarif::LocationBuilder::default()
-                  .physical_location(
-                    sarif::PhysicalLocationBuilder::default()
-                      .artifact_location(artifact_location)
-                      .region(region)
-                      .build()?,
-                  )
+      None => vec![],
+    };
+    let related_locations = if let Some(fix) = result.fix.as_ref() {
+      fix
+        .replacements
+        .iter()
+        .map(|replacement| -> Result<sarif::Location, BuilderError> {
+          let region: sarif::Region = replacement.try_into()?;
+          let artifact_location: sarif::ArtifactLocation = result.try_into()?;
+          Ok(
+            sarif::LocationBuilder::default()
+              .physical_location(
+                sarif::PhysicalLocationBuilder::default()
+                  .artifact_location(artifact_location)
+                  .region(region)
                   .build()?,
               )
-            })
-            .filter_map(|v| v.ok())
-            .collect()
-        } else {
-          vec![]
-        };
-        results.push(
-          sarif::ResultBuilder::default()
-            .rule_id(result.code.to_string())
-            .rule_index(*value)
-            .message::<sarif::Message>((&result.message).try_into()?)
-            .locations(vec![result.try_into()?])
-            .related_locations(related_locations)
-            .fixes(fixes)
-            .level(level.to_string())
-            .build()?,
-        );
-      }
-      Ok(())
-    })?;
-  let tool_component: sarif::ToolComponent =
-    sarif::ToolComponentBuilder::default()
-      .name("shellcheck")
-      .rules(rules)
-      .build()?;
-  let run = sarif::RunBuilder::default()
-    .tool::<sarif::Tool>(tool_component.try_into()?)
-    .results(results)
-    .build()?;
-
-  let sarif = sarif::SarifBuilder::default()
-    .version(sarif::Version::V2_1_0.to_string())
-    .runs(vec![run])
-    .build()?;
-
-  Ok(sarif)
+              .build()?,
+          )
+        })
+        .filter_map(|v| v.ok())
+        .collect()
+    } else {
+      vec![]
+    };
+    Ok(vec![sarif::ResultBuilder::default()
+      .message::<sarif::Message>((&result.message).try_into()?)
+      .locations(vec![result.try_into()?])
+      .related_locations(related_locations)
+      .fixes(fixes)
+      .level(level)
+      .build()?])
+  }
 }
 
-/// Returns [sarif::Sarif] serialized into a JSON stream
+/// Writes [sarif::Sarif] serialized into a JSON stream, one result at a time
 ///
 /// # Arguments
 ///
-/// * `reader` - A `BufRead` of cargo output
+/// * `reader` - A `BufRead` of shellcheck output
 /// * `writer` - A `Writer` to write the results to
 pub fn parse_to_writer<R: BufRead, W: Write>(
   reader: R,
   writer: W,
-) -> Result<()> {
-  let sarif = process(reader)?;
-  serde_json::to_writer_pretty(writer, &sarif)?;
-  Ok(())
+) -> Result<(), ConverterError> {
+  convert(&ShellcheckConverter, reader, writer, &ConvertOptions::new())
 }
 
 /// Returns [sarif::Sarif] serialized into a JSON string
@@ -261,8 +282,10 @@ pub fn parse_to_writer<R: BufRead, W: Write>(
 /// # Arguments
 ///
 /// * `reader` - A `BufRead` of shellcheck output
-pub fn parse_to_string<R: BufRead>(reader: R) -> Result<String> {
-  let sarif = process(reader)?;
-  let json = serde_json::to_string_pretty(&sarif)?;
-  Ok(json)
+pub fn parse_to_string<R: BufRead>(
+  reader: R,
+) -> Result<String, ConverterError> {
+  let mut buffer = vec![];
+  parse_to_writer(reader, &mut buffer)?;
+  Ok(String::from_utf8(buffer)?)
 }
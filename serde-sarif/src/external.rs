@@ -0,0 +1,567 @@
+//! Resolution of SARIF external property files.
+//!
+//! A run may keep some of its properties, ex. its results or artifacts, in
+//! separate `externalProperties` documents, which it refers to in
+//! `runs[].externalPropertyFileReferences`. [resolve] loads the referenced
+//! files (or the matching `inlineExternalProperties` of the log) and inlines
+//! them into their run, checking that their `guid`, `runGuid` and item count
+//! match the reference. [split] is the reverse operation, moving properties
+//! of a run into new `externalProperties` documents.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::external::{resolve, split, Category};
+//! use serde_sarif::sarif::Sarif;
+//!
+//! let mut sarif: Sarif = serde_json::from_value(serde_json::json!({
+//!   "version": "2.1.0",
+//!   "runs": [{
+//!     "tool": { "driver": { "name": "clippy" } },
+//!     "results": [{ "message": { "text": "message" } }]
+//!   }]
+//! }))
+//! .unwrap();
+//!
+//! // move the results out of the run, keeping them inline in the log
+//! let external = split(&mut sarif.runs[0], &[Category::Results], |_| None);
+//! assert_eq!(sarif.runs[0].results, None);
+//! sarif.inline_external_properties = Some(external);
+//!
+//! // and back
+//! resolve(&mut sarif, ".").unwrap();
+//! assert_eq!(sarif.runs[0].results.as_ref().map(Vec::len), Some(1));
+//! ```
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::sarif;
+use crate::uri::{UriError, UriResolver, Url};
+
+/// The property of a run an external property file contributes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+  Addresses,
+  Artifacts,
+  Conversion,
+  Driver,
+  Extensions,
+  ExternalizedProperties,
+  Graphs,
+  Invocations,
+  LogicalLocations,
+  Policies,
+  Results,
+  Taxonomies,
+  ThreadFlowLocations,
+  Translations,
+  WebRequests,
+  WebResponses,
+}
+
+/// An error resolving external property files.
+#[derive(Error, Debug)]
+pub enum ExternalPropertiesError {
+  #[error("failed to read external property file {path}: {source}")]
+  Io {
+    path: PathBuf,
+    source: std::io::Error,
+  },
+  #[error("failed to parse external property file {path}: {source}")]
+  Json {
+    path: PathBuf,
+    source: serde_json::Error,
+  },
+  #[error(transparent)]
+  Uri(#[from] UriError),
+  #[error(
+    "external property file reference has neither a location nor a guid"
+  )]
+  MissingLocation,
+  #[error("no inline external properties have guid {0}")]
+  MissingInline(String),
+  #[error("external property file has guid {found}, expected {expected}")]
+  GuidMismatch { expected: String, found: String },
+  #[error("external property file has run guid {found}, expected {expected}")]
+  RunGuidMismatch { expected: String, found: String },
+  #[error(
+    "external property file has {found} {category:?} items, expected {expected}"
+  )]
+  ItemCountMismatch {
+    category: Category,
+    expected: i64,
+    found: i64,
+  },
+}
+
+/// Loads an `externalProperties` document
+///
+/// # Arguments
+///
+/// * `path` - The path of the external property file
+pub fn load<P: AsRef<Path>>(
+  path: P,
+) -> Result<sarif::ExternalProperties, ExternalPropertiesError> {
+  let path = path.as_ref();
+  let file =
+    File::open(path).map_err(|source| ExternalPropertiesError::Io {
+      path: path.to_path_buf(),
+      source,
+    })?;
+  serde_json::from_reader(BufReader::new(file)).map_err(|source| {
+    ExternalPropertiesError::Json {
+      path: path.to_path_buf(),
+      source,
+    }
+  })
+}
+
+/// Resolves the external property file references of every run of `sarif`,
+/// inlining the referenced properties into the run. References without a
+/// location refer to the `inlineExternalProperties` of the log.
+///
+/// # Arguments
+///
+/// * `sarif` - The SARIF log to resolve
+/// * `base` - The directory relative locations are resolved against, usually
+///   the directory containing the log
+pub fn resolve<P: AsRef<Path>>(
+  sarif: &mut sarif::Sarif,
+  base: P,
+) -> Result<(), ExternalPropertiesError> {
+  let inline = sarif.inline_external_properties.take().unwrap_or_default();
+  let resolved = sarif
+    .runs
+    .iter_mut()
+    .try_for_each(|run| resolve_run(run, &inline, base.as_ref()));
+  if !inline.is_empty() {
+    sarif.inline_external_properties = Some(inline);
+  }
+  resolved
+}
+
+/// Resolves the external property file references of a single run
+///
+/// # Arguments
+///
+/// * `run` - The run to resolve
+/// * `inline` - The `inlineExternalProperties` of the log containing the run
+/// * `base` - The directory relative locations are resolved against
+pub fn resolve_run(
+  run: &mut sarif::Run,
+  inline: &[sarif::ExternalProperties],
+  base: &Path,
+) -> Result<(), ExternalPropertiesError> {
+  let references = match run.external_property_file_references.take() {
+    Some(references) => references,
+    None => return Ok(()),
+  };
+  let properties = references.properties.clone();
+  let references = into_references(references);
+  for (i, (category, reference)) in references.iter().enumerate() {
+    let inlined = find(run, reference, inline, base).and_then(|external| {
+      inline_properties(run, *category, reference, external)
+    });
+    if let Err(err) = inlined {
+      // leave the references which were not inlined in place
+      let mut remaining = sarif::ExternalPropertyFileReferences {
+        properties,
+        ..Default::default()
+      };
+      references[i..].iter().for_each(|(category, reference)| {
+        push_reference(&mut remaining, *category, reference.clone())
+      });
+      run.external_property_file_references = Some(remaining);
+      return Err(err);
+    }
+  }
+  Ok(())
+}
+
+// Loads the external property file a reference refers to
+fn find(
+  run: &sarif::Run,
+  reference: &sarif::ExternalPropertyFileReference,
+  inline: &[sarif::ExternalProperties],
+  base: &Path,
+) -> Result<sarif::ExternalProperties, ExternalPropertiesError> {
+  if let Some(location) = reference.location.as_ref() {
+    return load(path(run, location, base)?);
+  }
+  let guid = reference
+    .guid
+    .as_ref()
+    .ok_or(ExternalPropertiesError::MissingLocation)?;
+  inline
+    .iter()
+    .find(|external| {
+      external
+        .guid
+        .as_ref()
+        .is_some_and(|g| g.eq_ignore_ascii_case(guid))
+    })
+    .cloned()
+    .ok_or_else(|| ExternalPropertiesError::MissingInline(guid.clone()))
+}
+
+/// Inlines the `category` property of an external property file into `run`,
+/// after checking it against the reference to it
+///
+/// # Arguments
+///
+/// * `run` - The run to inline the property into
+/// * `category` - The property of the run the file contributes
+/// * `reference` - The reference to the file
+/// * `external` - The contents of the file
+pub fn inline_properties(
+  run: &mut sarif::Run,
+  category: Category,
+  reference: &sarif::ExternalPropertyFileReference,
+  mut external: sarif::ExternalProperties,
+) -> Result<(), ExternalPropertiesError> {
+  if let (Some(expected), Some(found)) = (&reference.guid, &external.guid) {
+    if !expected.eq_ignore_ascii_case(found) {
+      return Err(ExternalPropertiesError::GuidMismatch {
+        expected: expected.clone(),
+        found: found.clone(),
+      });
+    }
+  }
+  let run_guid = run
+    .automation_details
+    .as_ref()
+    .and_then(|details| details.guid.as_ref());
+  if let (Some(expected), Some(found)) = (run_guid, &external.run_guid) {
+    if !expected.eq_ignore_ascii_case(found) {
+      return Err(ExternalPropertiesError::RunGuidMismatch {
+        expected: expected.clone(),
+        found: found.clone(),
+      });
+    }
+  }
+  if let Some(expected) = reference.item_count {
+    let found = item_count(&external, category);
+    if found != expected {
+      return Err(ExternalPropertiesError::ItemCountMismatch {
+        category,
+        expected,
+        found,
+      });
+    }
+  }
+
+  match category {
+    Category::Addresses => extend(&mut run.addresses, external.addresses),
+    Category::Artifacts => extend(&mut run.artifacts, external.artifacts),
+    Category::Conversion => {
+      if external.conversion.is_some() {
+        run.conversion = external.conversion
+      }
+    }
+    Category::Driver => {
+      if let Some(driver) = external.driver {
+        run.tool.driver = driver
+      }
+    }
+    Category::Extensions => {
+      extend(&mut run.tool.extensions, external.extensions)
+    }
+    Category::ExternalizedProperties => {
+      if let Some(properties) = external.externalized_properties.take() {
+        run
+          .properties
+          .get_or_insert_with(Default::default)
+          .merge(properties)
+      }
+    }
+    Category::Graphs => extend(&mut run.graphs, external.graphs),
+    Category::Invocations => extend(&mut run.invocations, external.invocations),
+    Category::LogicalLocations => {
+      extend(&mut run.logical_locations, external.logical_locations)
+    }
+    Category::Policies => extend(&mut run.policies, external.policies),
+    Category::Results => extend(&mut run.results, external.results),
+    Category::Taxonomies => extend(&mut run.taxonomies, external.taxonomies),
+    Category::ThreadFlowLocations => extend(
+      &mut run.thread_flow_locations,
+      external.thread_flow_locations,
+    ),
+    Category::Translations => {
+      extend(&mut run.translations, external.translations)
+    }
+    Category::WebRequests => {
+      extend(&mut run.web_requests, external.web_requests)
+    }
+    Category::WebResponses => {
+      extend(&mut run.web_responses, external.web_responses)
+    }
+  }
+  Ok(())
+}
+
+/// Moves the given properties of `run` into new `externalProperties`
+/// documents, one per category, and adds references to them to the run.
+/// Properties the run does not have are skipped.
+///
+/// The guid of each document is derived from its contents, and its run guid
+/// is the guid of the run's `automationDetails`, if any.
+///
+/// # Arguments
+///
+/// * `run` - The run to split
+/// * `categories` - The properties to move out of the run
+/// * `location` - Returns the location each document will be written to;
+///   documents without a location are referred to by guid and are expected
+///   to be added to the `inlineExternalProperties` of the log
+pub fn split<F>(
+  run: &mut sarif::Run,
+  categories: &[Category],
+  mut location: F,
+) -> Vec<sarif::ExternalProperties>
+where
+  F: FnMut(Category) -> Option<sarif::ArtifactLocation>,
+{
+  let run_guid = run
+    .automation_details
+    .as_ref()
+    .and_then(|details| details.guid.clone());
+  categories
+    .iter()
+    .filter_map(|category| {
+      let mut external = take(run, *category)?;
+      external.version = Some(sarif::ExternalPropertiesVersion::V2_1_0);
+      external.run_guid = run_guid.clone();
+      let guid = guid(&external);
+      external.guid = Some(guid.clone());
+      let reference = sarif::ExternalPropertyFileReference {
+        guid: Some(guid),
+        item_count: match category {
+          Category::Conversion
+          | Category::Driver
+          | Category::ExternalizedProperties => None,
+          _ => Some(item_count(&external, *category)),
+        },
+        location: location(*category),
+        properties: None,
+      };
+      push_reference(
+        run
+          .external_property_file_references
+          .get_or_insert_with(Default::default),
+        *category,
+        reference,
+      );
+      Some(external)
+    })
+    .collect()
+}
+
+fn extend<T>(target: &mut Option<Vec<T>>, source: Option<Vec<T>>) {
+  if let Some(source) = source {
+    target.get_or_insert_with(Vec::new).extend(source)
+  }
+}
+
+fn len<T>(items: &Option<Vec<T>>) -> i64 {
+  items.as_ref().map_or(0, |items| items.len() as i64)
+}
+
+fn item_count(external: &sarif::ExternalProperties, category: Category) -> i64 {
+  match category {
+    Category::Addresses => len(&external.addresses),
+    Category::Artifacts => len(&external.artifacts),
+    Category::Conversion => external.conversion.is_some() as i64,
+    Category::Driver => external.driver.is_some() as i64,
+    Category::Extensions => len(&external.extensions),
+    Category::ExternalizedProperties => {
+      external.externalized_properties.is_some() as i64
+    }
+    Category::Graphs => len(&external.graphs),
+    Category::Invocations => len(&external.invocations),
+    Category::LogicalLocations => len(&external.logical_locations),
+    Category::Policies => len(&external.policies),
+    Category::Results => len(&external.results),
+    Category::Taxonomies => len(&external.taxonomies),
+    Category::ThreadFlowLocations => len(&external.thread_flow_locations),
+    Category::Translations => len(&external.translations),
+    Category::WebRequests => len(&external.web_requests),
+    Category::WebResponses => len(&external.web_responses),
+  }
+}
+
+// Moves a property out of the run into a new external properties document,
+// returning None if the run does not have it
+fn take(
+  run: &mut sarif::Run,
+  category: Category,
+) -> Option<sarif::ExternalProperties> {
+  let mut external = sarif::ExternalProperties::default();
+  match category {
+    Category::Addresses => external.addresses = Some(run.addresses.take()?),
+    Category::Artifacts => external.artifacts = Some(run.artifacts.take()?),
+    Category::Conversion => external.conversion = Some(run.conversion.take()?),
+    Category::Driver => {
+      // the driver is required, so only its name is left in the run
+      let driver = sarif::ToolComponentBuilder::default()
+        .name(run.tool.driver.name.clone())
+        .build()
+        .ok()?;
+      external.driver = Some(std::mem::replace(&mut run.tool.driver, driver))
+    }
+    Category::Extensions => {
+      external.extensions = Some(run.tool.extensions.take()?)
+    }
+    Category::ExternalizedProperties => {
+      external.externalized_properties = Some(run.properties.take()?)
+    }
+    Category::Graphs => external.graphs = Some(run.graphs.take()?),
+    Category::Invocations => {
+      external.invocations = Some(run.invocations.take()?)
+    }
+    Category::LogicalLocations => {
+      external.logical_locations = Some(run.logical_locations.take()?)
+    }
+    Category::Policies => external.policies = Some(run.policies.take()?),
+    Category::Results => external.results = Some(run.results.take()?),
+    Category::Taxonomies => external.taxonomies = Some(run.taxonomies.take()?),
+    Category::ThreadFlowLocations => {
+      external.thread_flow_locations = Some(run.thread_flow_locations.take()?)
+    }
+    Category::Translations => {
+      external.translations = Some(run.translations.take()?)
+    }
+    Category::WebRequests => {
+      external.web_requests = Some(run.web_requests.take()?)
+    }
+    Category::WebResponses => {
+      external.web_responses = Some(run.web_responses.take()?)
+    }
+  }
+  Some(external)
+}
+
+// Flattens the references of a run, in the order of the schema
+fn into_references(
+  references: sarif::ExternalPropertyFileReferences,
+) -> Vec<(Category, sarif::ExternalPropertyFileReference)> {
+  let one = |category, reference: Option<_>| {
+    reference
+      .into_iter()
+      .map(move |reference| (category, reference))
+  };
+  let many = |category, references: Option<Vec<_>>| {
+    references
+      .into_iter()
+      .flatten()
+      .map(move |reference| (category, reference))
+  };
+  std::iter::empty()
+    .chain(many(Category::Addresses, references.addresses))
+    .chain(many(Category::Artifacts, references.artifacts))
+    .chain(one(Category::Conversion, references.conversion))
+    .chain(one(Category::Driver, references.driver))
+    .chain(many(Category::Extensions, references.extensions))
+    .chain(one(
+      Category::ExternalizedProperties,
+      references.externalized_properties,
+    ))
+    .chain(many(Category::Graphs, references.graphs))
+    .chain(many(Category::Invocations, references.invocations))
+    .chain(many(
+      Category::LogicalLocations,
+      references.logical_locations,
+    ))
+    .chain(many(Category::Policies, references.policies))
+    .chain(many(Category::Results, references.results))
+    .chain(many(Category::Taxonomies, references.taxonomies))
+    .chain(many(
+      Category::ThreadFlowLocations,
+      references.thread_flow_locations,
+    ))
+    .chain(many(Category::Translations, references.translations))
+    .chain(many(Category::WebRequests, references.web_requests))
+    .chain(many(Category::WebResponses, references.web_responses))
+    .collect()
+}
+
+fn push_reference(
+  references: &mut sarif::ExternalPropertyFileReferences,
+  category: Category,
+  reference: sarif::ExternalPropertyFileReference,
+) {
+  let many = match category {
+    Category::Conversion => {
+      references.conversion = Some(reference);
+      return;
+    }
+    Category::Driver => {
+      references.driver = Some(reference);
+      return;
+    }
+    Category::ExternalizedProperties => {
+      references.externalized_properties = Some(reference);
+      return;
+    }
+    Category::Addresses => &mut references.addresses,
+    Category::Artifacts => &mut references.artifacts,
+    Category::Extensions => &mut references.extensions,
+    Category::Graphs => &mut references.graphs,
+    Category::Invocations => &mut references.invocations,
+    Category::LogicalLocations => &mut references.logical_locations,
+    Category::Policies => &mut references.policies,
+    Category::Results => &mut references.results,
+    Category::Taxonomies => &mut references.taxonomies,
+    Category::ThreadFlowLocations => &mut references.thread_flow_locations,
+    Category::Translations => &mut references.translations,
+    Category::WebRequests => &mut references.web_requests,
+    Category::WebResponses => &mut references.web_responses,
+  };
+  many.get_or_insert_with(Vec::new).push(reference)
+}
+
+// Resolves the path of an external property file; relative locations
+// without a base id are relative to `base`
+fn path(
+  run: &sarif::Run,
+  location: &sarif::ArtifactLocation,
+  base: &Path,
+) -> Result<PathBuf, ExternalPropertiesError> {
+  let base = std::env::current_dir()
+    .map(|dir| dir.join(base))
+    .unwrap_or_else(|_| base.to_path_buf());
+  let resolver = match Url::from_directory_path(base) {
+    Ok(base) => UriResolver::new().default_base(base),
+    Err(_) => UriResolver::new(),
+  };
+  Ok(resolver.resolve_path(run, location)?)
+}
+
+// Derives a guid from the contents of a document, so that splitting the same
+// run twice yields the same guids. Like a name-based (version 5) UUID, its
+// version is 5 and its variant that of RFC 4122, which the schema requires.
+fn guid(external: &sarif::ExternalProperties) -> String {
+  let bytes = serde_json::to_vec(external).unwrap_or_default();
+  let fnv1a = |seed: u64| {
+    bytes.iter().fold(seed, |hash, byte| {
+      (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+  };
+  let mut uuid = [0u8; 16];
+  uuid[..8].copy_from_slice(&fnv1a(0xcbf29ce484222325).to_be_bytes());
+  uuid[8..].copy_from_slice(&fnv1a(0x84222325cbf29ce4).to_be_bytes());
+  uuid[6] = (uuid[6] & 0x0f) | 0x50;
+  uuid[8] = (uuid[8] & 0x3f) | 0x80;
+  let hex: String = uuid.iter().map(|byte| format!("{:02x}", byte)).collect();
+  format!(
+    "{}-{}-{}-{}-{}",
+    &hex[..8],
+    &hex[8..12],
+    &hex[12..16],
+    &hex[16..20],
+    &hex[20..]
+  )
+}
@@ -0,0 +1,302 @@
+//! Computation of result fingerprints.
+//!
+//! Github code scanning tracks alerts across commits using the
+//! `partialFingerprints` of each result. If they are missing, alerts are
+//! matched by line number and churn whenever code above them moves. This
+//! module reads the source files referenced by the results of a log and adds
+//! two partial fingerprints:
+//!
+//! - `primaryLocationLineHash` - a rolling hash of the (whitespace stripped)
+//!   content starting at the first line of the result's primary location,
+//!   compatible with the one computed by the Github `upload-sarif` action
+//! - `ruleSnippetHash/v1` - a hash of the rule id and the (whitespace
+//!   normalized) lines of the primary location, which does not change when
+//!   the snippet moves, within or between files
+//!
+//! Fingerprints which are already present are left untouched.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::fingerprint::line_hashes;
+//!
+//! let hashes = line_hashes("fn main() {\n  println!(\"hello\");\n}\n");
+//! assert_eq!(hashes[1], "8ab55852e4bfd250:1");
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::num::Wrapping;
+use std::path::PathBuf;
+
+use crate::sarif;
+use crate::uri::{UriResolver, Url};
+
+/// The key of the Github compatible line hash in `partialFingerprints`.
+pub const PRIMARY_LOCATION_LINE_HASH: &str = "primaryLocationLineHash";
+/// The key of the rule and snippet hash in `partialFingerprints`.
+pub const RULE_SNIPPET_HASH: &str = "ruleSnippetHash/v1";
+
+const BLOCK_SIZE: usize = 100;
+const MOD: Wrapping<u64> = Wrapping(37);
+
+/// Returns the `primaryLocationLineHash` of every line of `contents`; the
+/// hash of line `n` is at index `n - 1`
+///
+/// The hash of a line covers the next 100 non whitespace characters of the
+/// file, and is suffixed with the number of times the same hash occurred
+/// before, ex. `e3b1bc4d1a8ff6f:1`.
+///
+/// # Arguments
+///
+/// * `contents` - The contents of a source file
+pub fn line_hashes(contents: &str) -> Vec<String> {
+  // this follows the algorithm of the Github codeql-action, which operates
+  // on UTF-16 code units and signed 64 bit integer arithmetic
+  const EOF: u64 = 65535;
+  let first_mod = (0..BLOCK_SIZE).fold(Wrapping(1u64), |m, _| m * MOD);
+  let mut window = [0u64; BLOCK_SIZE];
+  let mut line_numbers = [None; BLOCK_SIZE];
+  let mut hash = Wrapping(0u64);
+  let mut index = 0;
+  let mut line_number = 0;
+  let mut line_start = true;
+  let mut prev_cr = false;
+  let mut hash_counts: HashMap<u64, usize> = HashMap::new();
+  let mut hashes: Vec<String> = vec![];
+
+  let mut output_hash =
+    |hashes: &mut Vec<String>, line: usize, hash: Wrapping<u64>| {
+      let count = hash_counts.entry(hash.0).or_insert(0);
+      *count += 1;
+      if hashes.len() < line {
+        hashes.resize(line, String::new());
+      }
+      hashes[line - 1] = format!("{:x}:{}", hash.0, count);
+    };
+  let mut update_hash = |hash: &mut Wrapping<u64>, index: &mut usize, c| {
+    let begin = window[*index];
+    window[*index] = c;
+    *hash = MOD * *hash + Wrapping(c) - first_mod * Wrapping(begin);
+    *index = (*index + 1) % BLOCK_SIZE;
+  };
+
+  let characters = contents.encode_utf16().map(u64::from).chain([EOF]);
+  characters.for_each(|mut c| {
+    // skip tabs, spaces and line feeds directly following a carriage return
+    if c == u64::from(b' ') || c == u64::from(b'\t') || (prev_cr && c == 10) {
+      prev_cr = false;
+      return;
+    }
+    // carriage returns are treated as line feeds
+    prev_cr = c == 13;
+    if prev_cr {
+      c = 10;
+    }
+    if let Some(line) = line_numbers[index].take() {
+      output_hash(&mut hashes, line, hash);
+    }
+    if line_start {
+      line_start = false;
+      line_number += 1;
+      line_numbers[index] = Some(line_number);
+    }
+    if c == 10 {
+      line_start = true;
+    }
+    update_hash(&mut hash, &mut index, c);
+  });
+  (0..BLOCK_SIZE).for_each(|_| {
+    if let Some(line) = line_numbers[index].take() {
+      output_hash(&mut hashes, line, hash);
+    }
+    update_hash(&mut hash, &mut index, 0);
+  });
+  hashes
+}
+
+// 64 bit FNV-1a, which unlike the hashers of std is stable across releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+    (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+  })
+}
+
+/// Returns the `ruleSnippetHash/v1` of a rule and snippet
+///
+/// # Arguments
+///
+/// * `rule_id` - The id of the rule of the result
+/// * `snippet` - The source code of the result's primary location
+pub fn rule_snippet_hash(rule_id: &str, snippet: &str) -> String {
+  let snippet = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+  format!(
+    "{:016x}",
+    fnv1a(format!("{}\0{}", rule_id, snippet).as_bytes())
+  )
+}
+
+/// Adds fingerprints to the results of SARIF logs, reading the source files
+/// they refer to. Files are read once and cached.
+pub struct Fingerprinter {
+  resolver: UriResolver,
+  files: HashMap<PathBuf, Option<File>>,
+}
+
+struct File {
+  lines: Vec<String>,
+  hashes: Vec<String>,
+}
+
+impl Fingerprinter {
+  /// Creates a fingerprinter resolving relative artifact URIs against
+  /// `root`, usually the directory the tool was run in
+  ///
+  /// # Arguments
+  ///
+  /// * `root` - The directory relative URIs are resolved against
+  pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+    let root = root.into();
+    let root = std::env::current_dir()
+      .map(|dir| dir.join(&root))
+      .unwrap_or(root);
+    let resolver = match Url::from_directory_path(root) {
+      Ok(root) => UriResolver::new().default_base(root),
+      Err(_) => UriResolver::new(),
+    };
+    Fingerprinter::with_resolver(resolver)
+  }
+
+  /// Creates a fingerprinter resolving artifact URIs with `resolver`, ex. to
+  /// supply the base ids the log leaves undefined
+  ///
+  /// # Arguments
+  ///
+  /// * `resolver` - The resolver for artifact URIs
+  pub fn with_resolver(resolver: UriResolver) -> Self {
+    Fingerprinter {
+      resolver,
+      files: HashMap::new(),
+    }
+  }
+
+  /// Adds fingerprints to every result of `sarif`
+  ///
+  /// # Arguments
+  ///
+  /// * `sarif` - The SARIF log to add fingerprints to
+  pub fn fingerprint(&mut self, sarif: &mut sarif::Sarif) {
+    sarif.runs.iter_mut().for_each(|run| {
+      let mut results = run.results.take();
+      results
+        .iter_mut()
+        .flatten()
+        .for_each(|result| self.fingerprint_result(run, result));
+      run.results = results;
+    })
+  }
+
+  /// Adds fingerprints to a single result of `run`. Results whose source
+  /// file cannot be read are left unchanged.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run the result belongs to
+  /// * `result` - The result to add fingerprints to
+  pub fn fingerprint_result(
+    &mut self,
+    run: &sarif::Run,
+    result: &mut sarif::Result,
+  ) {
+    let physical_location = match result
+      .locations
+      .iter()
+      .flatten()
+      .find_map(|location| location.physical_location.as_ref())
+    {
+      Some(physical_location) => physical_location,
+      None => return,
+    };
+    let (path, region) = match (
+      physical_location
+        .artifact_location
+        .as_ref()
+        .and_then(|location| self.path(run, location)),
+      physical_location.region.as_ref(),
+    ) {
+      (Some(path), Some(region)) => (path, region),
+      _ => return,
+    };
+    let start_line = match region
+      .start_line
+      .and_then(|line| usize::try_from(line).ok())
+      .filter(|line| *line > 0)
+    {
+      Some(start_line) => start_line,
+      None => return,
+    };
+    let end_line = region
+      .end_line
+      .and_then(|line| usize::try_from(line).ok())
+      .unwrap_or(start_line)
+      .max(start_line);
+    let rule_id = result
+      .rule_id
+      .clone()
+      .or_else(|| run.resolve_rule(result).map(|rule| rule.id.clone()));
+
+    let file = match self
+      .files
+      .entry(path.clone())
+      .or_insert_with(|| {
+        fs::read_to_string(&path).ok().map(|contents| File {
+          lines: contents.lines().map(String::from).collect(),
+          hashes: line_hashes(&contents),
+        })
+      })
+      .as_ref()
+    {
+      Some(file) => file,
+      None => return,
+    };
+
+    let fingerprints = result
+      .partial_fingerprints
+      .get_or_insert_with(Default::default);
+    if let Some(hash) = file.hashes.get(start_line - 1) {
+      fingerprints
+        .entry(PRIMARY_LOCATION_LINE_HASH.into())
+        .or_insert_with(|| hash.clone());
+    }
+    if let (Some(rule_id), Some(lines)) = (
+      rule_id,
+      file
+        .lines
+        .get(start_line - 1..end_line.min(file.lines.len())),
+    ) {
+      fingerprints
+        .entry(RULE_SNIPPET_HASH.into())
+        .or_insert_with(|| rule_snippet_hash(&rule_id, &lines.join("\n")));
+    }
+  }
+
+  fn path(
+    &self,
+    run: &sarif::Run,
+    location: &sarif::ArtifactLocation,
+  ) -> Option<PathBuf> {
+    self.resolver.resolve_path(run, location).ok()
+  }
+}
+
+/// Adds fingerprints to every result of `sarif`, resolving relative artifact
+/// URIs against `root`
+///
+/// # Arguments
+///
+/// * `sarif` - The SARIF log to add fingerprints to
+/// * `root` - The directory relative URIs are resolved against
+pub fn add_fingerprints<P: Into<PathBuf>>(sarif: &mut sarif::Sarif, root: P) {
+  Fingerprinter::new(root).fingerprint(sarif)
+}
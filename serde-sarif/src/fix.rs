@@ -0,0 +1,478 @@
+//! Proposed fixes of results.
+//!
+//! A result may carry `fixes`, each of which changes one or more artifacts:
+//! its `artifactChanges` replace the text of `deletedRegion`s with
+//! `insertedContent`. The regions of a fix refer to the contents of the
+//! artifacts before any of its replacements are made.
+//!
+//! [edits] resolves the replacements of an artifact change into [Edit]s of
+//! the contents of the artifact, which [apply] makes and [unified_diff] shows
+//! as a unified diff. A [Fixer] collects the fixes of many results and
+//! applies them to the files they change, leaving out fixes which overlap.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::fix::{apply, edits, unified_diff};
+//! use serde_sarif::sarif;
+//!
+//! let run: sarif::Run = serde_json::from_value(serde_json::json!({
+//!   "tool": { "driver": { "name": "shellcheck" } }
+//! }))
+//! .unwrap();
+//! let change: sarif::ArtifactChange = serde_json::from_value(serde_json::json!({
+//!   "artifactLocation": { "uri": "run.sh" },
+//!   "replacements": [
+//!     {
+//!       "deletedRegion": { "startLine": 2, "startColumn": 6, "endColumn": 6 },
+//!       "insertedContent": { "text": "\"" }
+//!     },
+//!     {
+//!       "deletedRegion": { "startLine": 2, "startColumn": 11, "endColumn": 11 },
+//!       "insertedContent": { "text": "\"" }
+//!     }
+//!   ]
+//! }))
+//! .unwrap();
+//!
+//! let contents = "#!/bin/sh\necho $name\n";
+//! let edits = edits(&run, &change, contents).unwrap();
+//! assert_eq!(apply(contents, &edits).unwrap(), "#!/bin/sh\necho \"$name\"\n");
+//! assert_eq!(
+//!   unified_diff("run.sh", contents, &edits, 3).unwrap(),
+//!   "--- a/run.sh\n+++ b/run.sh\n@@ -1,2 +1,2 @@\n \
+//!    #!/bin/sh\n-echo $name\n+echo \"$name\"\n"
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::region::{ColumnUnit, SourceText};
+use crate::sarif;
+use crate::uri::{UriError, UriResolver};
+
+/// An error resolving or applying the replacements of a fix.
+#[derive(Error, Debug)]
+pub enum FixError {
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Uri(#[from] UriError),
+  #[error("the deleted region of a replacement lies outside of the artifact")]
+  Region,
+  #[error("replacing binary content is not supported")]
+  Binary,
+  #[error("replacements overlap at byte {0}")]
+  Overlap(usize),
+}
+
+/// A replacement of a byte range of the contents of an artifact.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Edit {
+  /// The bytes to delete
+  pub range: Range<usize>,
+  /// The text to insert in their place
+  pub text: String,
+}
+
+/// Returns the edits the replacements of an artifact change make to the
+/// contents of the artifact.
+///
+/// # Arguments
+///
+/// * `run` - The run of the result, which gives the unit of columns
+/// * `change` - The artifact change
+/// * `contents` - The contents of the artifact
+pub fn edits(
+  run: &sarif::Run,
+  change: &sarif::ArtifactChange,
+  contents: &str,
+) -> Result<Vec<Edit>, FixError> {
+  let source = SourceText::new(contents);
+  change
+    .replacements
+    .iter()
+    .map(|replacement| {
+      let range = source
+        .byte_range(&replacement.deleted_region, ColumnUnit::of(run))
+        .ok_or(FixError::Region)?;
+      let text = match &replacement.inserted_content {
+        Some(content) => match (&content.text, &content.binary) {
+          (Some(text), _) => text.clone(),
+          (None, Some(_)) => return Err(FixError::Binary),
+          (None, None) => String::new(),
+        },
+        None => String::new(),
+      };
+      Ok(Edit { range, text })
+    })
+    .collect()
+}
+
+// Returns the edits ordered by their position, checking that none overlap;
+// insertions at the same position are kept in their original order
+fn sorted(edits: &[Edit]) -> Result<Vec<&Edit>, FixError> {
+  let mut sorted: Vec<&Edit> = edits.iter().collect();
+  sorted.sort_by_key(|edit| (edit.range.start, edit.range.end));
+  sorted.windows(2).try_for_each(|pair| {
+    if pair[1].range.start < pair[0].range.end {
+      Err(FixError::Overlap(pair[1].range.start))
+    } else {
+      Ok(())
+    }
+  })?;
+  Ok(sorted)
+}
+
+// Returns whether edits of different fixes conflict: they overlap, or one
+// inserts at either end of, or inside, the other, where the order in which
+// they are made is ambiguous
+fn conflicts(a: &Edit, b: &Edit) -> bool {
+  let inserts_at = |insertion: &Edit, other: &Edit| {
+    insertion.range.is_empty()
+      && other.range.start <= insertion.range.start
+      && insertion.range.start <= other.range.end
+  };
+  (a.range.start < b.range.end && b.range.start < a.range.end)
+    || inserts_at(a, b)
+    || inserts_at(b, a)
+}
+
+/// Returns the contents with the edits made. Edits must not overlap.
+///
+/// # Arguments
+///
+/// * `contents` - The contents of the artifact
+/// * `edits` - The edits, in any order
+pub fn apply(contents: &str, edits: &[Edit]) -> Result<String, FixError> {
+  let mut result = contents.to_string();
+  // edits are made from the bottom up, so that the offsets of those above
+  // stay valid
+  for edit in sorted(edits)?.into_iter().rev() {
+    if edit.range.end > result.len() {
+      return Err(FixError::Region);
+    }
+    result.replace_range(edit.range.clone(), &edit.text);
+  }
+  Ok(result)
+}
+
+// The lines changed by some adjacent edits: `old` lines starting at the
+// (0-based) line `first` are replaced with the `new` lines
+struct Change<'a> {
+  first: usize,
+  old: Vec<&'a str>,
+  new: Vec<String>,
+}
+
+impl Change<'_> {
+  fn end(&self) -> usize {
+    self.first + self.old.len()
+  }
+}
+
+// Returns the lines of some text, each with its newline
+fn lines(text: &str) -> Vec<&str> {
+  text.split_inclusive('\n').collect()
+}
+
+// Returns the changes the edits make to the lines of the contents, where the
+// lines both versions share are left out
+fn changes<'a>(
+  contents: &'a str,
+  edits: &[&Edit],
+) -> Result<Vec<Change<'a>>, FixError> {
+  let line_starts: Vec<usize> = std::iter::once(0)
+    .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+    .collect();
+  let line_of =
+    |offset: usize| line_starts.partition_point(|start| *start <= offset) - 1;
+  // group the edits whose lines overlap
+  let mut groups: Vec<(usize, usize, Vec<&Edit>)> = vec![];
+  for edit in edits {
+    if edit.range.end > contents.len() {
+      return Err(FixError::Region);
+    }
+    let (first, last) = (line_of(edit.range.start), line_of(edit.range.end));
+    match groups.last_mut() {
+      Some(group) if first <= group.1 => {
+        group.1 = group.1.max(last);
+        group.2.push(edit);
+      }
+      _ => groups.push((first, last, vec![edit])),
+    }
+  }
+  Ok(
+    groups
+      .into_iter()
+      .filter_map(|(first, last, edits)| {
+        let start = line_starts[first];
+        let end = line_starts.get(last + 1).copied().unwrap_or(contents.len());
+        let mut text = contents[start..end].to_string();
+        edits.iter().rev().for_each(|edit| {
+          text.replace_range(
+            edit.range.start - start..edit.range.end - start,
+            &edit.text,
+          )
+        });
+        let mut old = lines(&contents[start..end]);
+        let mut new: Vec<String> =
+          lines(&text).into_iter().map(String::from).collect();
+        let common = old.iter().zip(&new).take_while(|(a, b)| *a == b).count();
+        old.drain(..common);
+        new.drain(..common);
+        while !old.is_empty() && old.last().copied() == new.last().map(|l| &**l)
+        {
+          old.pop();
+          new.pop();
+        }
+        if old.is_empty() && new.is_empty() {
+          None
+        } else {
+          Some(Change {
+            first: first + common,
+            old,
+            new,
+          })
+        }
+      })
+      .collect(),
+  )
+}
+
+// Appends a line of a diff, marking a line without a newline
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+  diff.push(prefix);
+  diff.push_str(line);
+  if !line.ends_with('\n') {
+    diff.push_str("\n\\ No newline at end of file\n");
+  }
+}
+
+/// Returns the edits as a unified diff of the contents, or an empty string
+/// if they change nothing.
+///
+/// # Arguments
+///
+/// * `path` - The path of the artifact shown in the header of the diff
+/// * `contents` - The contents of the artifact
+/// * `edits` - The edits, in any order
+/// * `context` - The number of unchanged lines to show around changes
+pub fn unified_diff(
+  path: &str,
+  contents: &str,
+  edits: &[Edit],
+  context: usize,
+) -> Result<String, FixError> {
+  let changes = changes(contents, &sorted(edits)?)?;
+  if changes.is_empty() {
+    return Ok(String::new());
+  }
+  let old_lines = lines(contents);
+  let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+  // the difference in the number of lines made by the changes so far
+  let mut delta = 0_i64;
+  let mut changes = changes.into_iter().peekable();
+  while let Some(change) = changes.next() {
+    // the changes of a hunk are at most twice the context apart
+    let mut hunk = vec![change];
+    while let Some(next) = changes
+      .next_if(|next| next.first <= hunk[hunk.len() - 1].end() + 2 * context)
+    {
+      hunk.push(next);
+    }
+    let start = hunk[0].first.saturating_sub(context);
+    let end = (hunk[hunk.len() - 1].end() + context).min(old_lines.len());
+    let mut body = String::new();
+    let (mut old_count, mut new_count) = (0, 0);
+    let mut line = start;
+    for change in &hunk {
+      old_lines[line..change.first]
+        .iter()
+        .for_each(|l| push_line(&mut body, ' ', l));
+      change.old.iter().for_each(|l| push_line(&mut body, '-', l));
+      change.new.iter().for_each(|l| push_line(&mut body, '+', l));
+      old_count += change.first - line + change.old.len();
+      new_count += change.first - line + change.new.len();
+      line = change.end();
+    }
+    old_lines[line..end.max(line)]
+      .iter()
+      .for_each(|l| push_line(&mut body, ' ', l));
+    old_count += end.saturating_sub(line);
+    new_count += end.saturating_sub(line);
+    // an empty range starts at the line before it
+    let old_start = start as i64 + i64::from(old_count > 0);
+    let new_start = start as i64 + delta + i64::from(new_count > 0);
+    diff.push_str(&format!(
+      "@@ -{},{} +{},{} @@\n",
+      old_start, old_count, new_start, new_count
+    ));
+    diff.push_str(&body);
+    delta += new_count as i64 - old_count as i64;
+  }
+  Ok(diff)
+}
+
+/// Collects the fixes of results and applies them to the files they change.
+///
+/// The fixes of a result are alternatives, of which the first which does not
+/// overlap a fix collected before is taken. All the replacements of a fix
+/// are made, or none. Files are read when a fix first changes them and are
+/// only written by [Fixer::write].
+#[derive(Clone, Debug)]
+pub struct Fixer<'a> {
+  resolver: &'a UriResolver,
+  files: BTreeMap<PathBuf, FixedFile>,
+  fixes: usize,
+}
+
+// A file changed by fixes
+#[derive(Clone, Debug)]
+struct FixedFile {
+  // the uri of the artifact, as shown in diffs
+  name: String,
+  contents: String,
+  edits: Vec<Edit>,
+}
+
+impl<'a> Fixer<'a> {
+  /// Returns a fixer without any fixes.
+  ///
+  /// # Arguments
+  ///
+  /// * `resolver` - Resolves the locations of the artifacts fixes change
+  pub fn new(resolver: &'a UriResolver) -> Self {
+    Fixer {
+      resolver,
+      files: BTreeMap::new(),
+      fixes: 0,
+    }
+  }
+
+  /// Adds the first fix of a result which neither overlaps itself nor a fix
+  /// added before, where an insertion at the edge of an edit of another fix
+  /// counts as overlapping it. Returns whether the result has a fix; if all
+  /// of its fixes overlap, returns the error of the first. Edits which were
+  /// added before by another fix, ex. of a duplicate result, are skipped.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run of the result
+  /// * `result` - The result
+  pub fn add(
+    &mut self,
+    run: &sarif::Run,
+    result: &sarif::Result,
+  ) -> Result<bool, FixError> {
+    let mut first_error = None;
+    for fix in result.fixes.iter().flatten() {
+      match self.add_fix(run, fix) {
+        Ok(()) => {
+          self.fixes += 1;
+          return Ok(true);
+        }
+        Err(err @ FixError::Overlap(_)) => {
+          first_error.get_or_insert(err);
+        }
+        Err(err) => return Err(err),
+      }
+    }
+    first_error.map_or(Ok(false), Err)
+  }
+
+  fn add_fix(
+    &mut self,
+    run: &sarif::Run,
+    fix: &sarif::Fix,
+  ) -> Result<(), FixError> {
+    // the edits of each file, checked before any of them are added
+    let mut changes: BTreeMap<PathBuf, Vec<Edit>> = BTreeMap::new();
+    for change in &fix.artifact_changes {
+      let location = &change.artifact_location;
+      let path = self.resolver.resolve_path(run, location)?;
+      if !self.files.contains_key(&path) {
+        let file = FixedFile {
+          name: location
+            .uri
+            .clone()
+            .unwrap_or_else(|| path.to_string_lossy().into_owned()),
+          contents: std::fs::read_to_string(&path)?,
+          edits: vec![],
+        };
+        self.files.insert(path.clone(), file);
+      }
+      let contents = &self.files[&path].contents;
+      changes
+        .entry(path)
+        .or_default()
+        .extend(edits(run, change, contents)?);
+    }
+    for (path, edits) in &mut changes {
+      let added = &self.files[path].edits;
+      edits.retain(|edit| !added.contains(edit));
+      sorted(edits)?;
+      if let Some(edit) = edits
+        .iter()
+        .find(|edit| added.iter().any(|other| conflicts(edit, other)))
+      {
+        return Err(FixError::Overlap(edit.range.start));
+      }
+    }
+    for (path, edits) in changes {
+      if let Some(file) = self.files.get_mut(&path) {
+        file.edits.extend(edits);
+      }
+    }
+    Ok(())
+  }
+
+  /// Returns the number of fixes added.
+  pub fn fixes(&self) -> usize {
+    self.fixes
+  }
+
+  // Returns the files which the fixes change
+  fn changed(&self) -> impl Iterator<Item = (&Path, &FixedFile)> {
+    self
+      .files
+      .iter()
+      .filter(|(_, file)| !file.edits.is_empty())
+      .map(|(path, file)| (path.as_path(), file))
+  }
+
+  /// Returns the paths of the files the fixes change.
+  pub fn paths(&self) -> impl Iterator<Item = &Path> {
+    self.changed().map(|(path, _)| path)
+  }
+
+  /// Returns a unified diff of the changes the fixes make to each file.
+  ///
+  /// # Arguments
+  ///
+  /// * `context` - The number of unchanged lines to show around changes
+  pub fn diffs(
+    &self,
+    context: usize,
+  ) -> Result<Vec<(&Path, String)>, FixError> {
+    self
+      .changed()
+      .map(|(path, file)| {
+        let diff =
+          unified_diff(&file.name, &file.contents, &file.edits, context)?;
+        Ok((path, diff))
+      })
+      .collect()
+  }
+
+  /// Makes the fixes, writing each file they change.
+  pub fn write(&self) -> Result<(), FixError> {
+    self.changed().try_for_each(|(path, file)| {
+      Ok(std::fs::write(path, apply(&file.contents, &file.edits)?)?)
+    })
+  }
+}
@@ -0,0 +1,129 @@
+//! Code flows, the paths through the code along which a result arises.
+//!
+//! A result's `codeFlows` each consist of `threadFlows`, and each thread flow
+//! is a sequence of locations visited by one thread, ex. the steps by which
+//! tainted data travels from a source to a sink. A thread flow location may
+//! refer by `index` to one of the run's `threadFlowLocations` to share its
+//! properties with other thread flows, in which case the properties it does
+//! not specify itself are those of the one it refers to.
+//!
+//! [steps] resolves the locations of a thread flow into [Step]s.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::flow::steps;
+//! use serde_sarif::sarif::{self, ThreadFlowLocationImportance};
+//!
+//! let run: sarif::Run = serde_json::from_value(serde_json::json!({
+//!   "tool": { "driver": { "name": "taint" } },
+//!   "threadFlowLocations": [{ "kinds": ["call"], "nestingLevel": 1 }]
+//! }))
+//! .unwrap();
+//! let thread_flow: sarif::ThreadFlow = serde_json::from_value(serde_json::json!({
+//!   "locations": [
+//!     { "importance": "essential", "kinds": ["source"] },
+//!     { "index": 0 },
+//!     { "importance": "unimportant" }
+//!   ]
+//! }))
+//! .unwrap();
+//!
+//! let steps = steps(&run, &thread_flow);
+//! assert_eq!(steps[1].kinds, ["call"]);
+//! assert_eq!(steps[1].nesting_level, 1);
+//! let important: Vec<usize> = steps
+//!   .iter()
+//!   .filter(|step| {
+//!     step.importance.is_at_least(&ThreadFlowLocationImportance::Important)
+//!   })
+//!   .map(|step| step.number)
+//!   .collect();
+//! assert_eq!(important, vec![1, 2]);
+//! ```
+
+use std::convert::TryFrom;
+
+use crate::sarif::{self, ThreadFlowLocationImportance};
+
+/// A step of a thread flow, with the properties of the thread flow location
+/// it refers to resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step<'a> {
+  /// The position of the step in the thread flow, starting at 1
+  pub number: usize,
+  /// The code location of the step
+  pub location: Option<&'a sarif::Location>,
+  /// What happens at the step, ex. `call` or `return`
+  pub kinds: &'a [String],
+  /// The depth of the step in the call hierarchy of the thread flow
+  pub nesting_level: i64,
+  /// How important the step is to understand the thread flow
+  pub importance: ThreadFlowLocationImportance,
+  /// The thread flow location of the step
+  pub thread_flow_location: &'a sarif::ThreadFlowLocation,
+}
+
+impl ThreadFlowLocationImportance {
+  /// Returns whether this importance is at least `importance`, where
+  /// `essential` is more important than `important`, which is more important
+  /// than `unimportant`. Unknown values count as `important`, the default.
+  ///
+  /// # Arguments
+  ///
+  /// * `importance` - The importance to compare to
+  pub fn is_at_least(&self, importance: &ThreadFlowLocationImportance) -> bool {
+    let rank = |importance: &ThreadFlowLocationImportance| match importance {
+      ThreadFlowLocationImportance::Essential => 2,
+      ThreadFlowLocationImportance::Unimportant => 0,
+      _ => 1,
+    };
+    rank(self) >= rank(importance)
+  }
+}
+
+/// Returns the steps of a thread flow in order.
+///
+/// # Arguments
+///
+/// * `run` - The run of the result the thread flow belongs to
+/// * `thread_flow` - The thread flow
+pub fn steps<'a>(
+  run: &'a sarif::Run,
+  thread_flow: &'a sarif::ThreadFlow,
+) -> Vec<Step<'a>> {
+  thread_flow
+    .locations
+    .iter()
+    .enumerate()
+    .map(|(i, local)| {
+      let shared = local
+        .index
+        .and_then(|index| usize::try_from(index).ok())
+        .and_then(|index| run.thread_flow_locations.as_ref()?.get(index));
+      Step {
+        number: i + 1,
+        location: inherit(local, shared, |tfl| tfl.location.as_ref()),
+        kinds: inherit(local, shared, |tfl| tfl.kinds.as_deref())
+          .unwrap_or_default(),
+        nesting_level: inherit(local, shared, |tfl| tfl.nesting_level.as_ref())
+          .copied()
+          .unwrap_or_default(),
+        importance: inherit(local, shared, |tfl| tfl.importance.as_ref())
+          .cloned()
+          .unwrap_or(ThreadFlowLocationImportance::Important),
+        thread_flow_location: local,
+      }
+    })
+    .collect()
+}
+
+// Returns a property of a thread flow location, else that of the run's thread
+// flow location it refers to
+fn inherit<'a, T: ?Sized>(
+  local: &'a sarif::ThreadFlowLocation,
+  shared: Option<&'a sarif::ThreadFlowLocation>,
+  property: impl Fn(&'a sarif::ThreadFlowLocation) -> Option<&'a T>,
+) -> Option<&'a T> {
+  property(local).or_else(|| shared.and_then(&property))
+}
@@ -23,16 +23,13 @@
 //! ## Example
 //!
 //!```rust
-//! use serde_sarif::sarif::Sarif;
+//! use serde_sarif::sarif::{Sarif, Version};
 //!
 //! let sarif: Sarif = serde_json::from_str(
 //!   r#"{ "version": "2.1.0", "runs": [] }"#
 //! ).unwrap();
 //!
-//! assert_eq!(
-//!   sarif.version.to_string(),
-//!   "\"2.1.0\"".to_string()
-//! );
+//! assert_eq!(sarif.version, Version::V2_1_0);
 //! ```
 //!
 //! Because many of the [sarif::Sarif] structures contain a lot of optional fields, it is
@@ -53,7 +50,8 @@
 //! ## Internal Implementation Details
 //!
 //! The root [sarif::Sarif] struct is automatically generated from the latest Sarif
-//! JSON schema, this is done at build time (via the buildscript).
+//! JSON schema, this is done at build time (via the buildscript). So are the
+//! [visit::Visit] and [visit::VisitMut] traits, which walk it.
 //!
 //! ## Crate Features
 //!
@@ -69,14 +67,31 @@
 //!
 //! ### Converters
 //! - **clang-tidy-converters** Provides conversions between clang tidy and SARIF
-//!  types
+//!   types
 //! - **clippy-converters** Provides conversions between Clippy
-//!    and SARIF types
+//!   and SARIF types
 //! - **hadolint-converters** Provides conversions between hadolint
-//!    and SARIF types
+//!   and SARIF types
 //! - **shellcheck-converters** Provides conversions between shellcheck
-//!    and SARIF types
+//!   and SARIF types
 //!
 
+pub mod baseline;
 pub mod converters;
+pub mod external;
+pub mod fingerprint;
+pub mod fix;
+pub mod flow;
+pub mod localize;
+pub mod merge;
+pub mod message;
+pub mod properties;
+pub mod query;
+pub mod region;
 pub mod sarif;
+pub mod stream;
+pub mod taxonomy;
+pub mod upgrade;
+pub mod uri;
+pub mod validate;
+pub mod visit;
@@ -0,0 +1,365 @@
+//! Localization of messages and rule metadata.
+//!
+//! A run's messages are written in its `language` (`en-US` by default). A tool
+//! may provide the localized data of its components, ex. the message strings
+//! and descriptions of its rules, in other languages as translations: tool
+//! components in `runs[].translations` whose `associatedComponent` is the
+//! component they translate, and whose rules and notifications carry the same
+//! `id` (and `guid`) as the descriptors they translate. Translations may also
+//! live in separate `externalProperties` files, which [load_translations]
+//! reads.
+//!
+//! A [Localizer] resolves message strings, rule descriptions and notification
+//! messages in a desired language, preferring the translations of the run
+//! and falling back to the data of the components themselves.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::localize::Localizer;
+//! use serde_sarif::sarif;
+//!
+//! let run: sarif::Run = serde_json::from_value(serde_json::json!({
+//!   "tool": { "driver": {
+//!     "name": "tool",
+//!     "rules": [{
+//!       "id": "UNUSED",
+//!       "messageStrings": { "default": { "text": "{0} is unused" } }
+//!     }]
+//!   } },
+//!   "translations": [{
+//!     "name": "tool",
+//!     "language": "de-DE",
+//!     "associatedComponent": { "name": "tool" },
+//!     "rules": [{
+//!       "id": "UNUSED",
+//!       "messageStrings": { "default": { "text": "{0} wird nicht verwendet" } }
+//!     }]
+//!   }]
+//! }))
+//! .unwrap();
+//! let result: sarif::Result = serde_json::from_value(serde_json::json!({
+//!   "ruleId": "UNUSED",
+//!   "message": { "id": "default", "arguments": ["x"] }
+//! }))
+//! .unwrap();
+//!
+//! let message = |language| {
+//!   Localizer::new(&run, language)
+//!     .message(&result.message, &result)
+//!     .map(|message| message.to_string())
+//! };
+//! assert_eq!(message("de").as_deref(), Some("x wird nicht verwendet"));
+//! // there is no Japanese translation, so the driver's strings are used
+//! assert_eq!(message("ja-JP").as_deref(), Some("x is unused"));
+//! ```
+
+use crate::external::{load, ExternalPropertiesError};
+use crate::message::{format_template, message_string, FormattedMessage};
+use crate::sarif;
+use crate::uri::UriResolver;
+
+/// The language of a run which does not specify one.
+pub const DEFAULT_LANGUAGE: &str = "en-US";
+
+/// Resolves the localized data of a run in a desired language.
+#[derive(Clone, Debug)]
+pub struct Localizer<'a> {
+  run: &'a sarif::Run,
+  language: String,
+  translations: Vec<&'a sarif::ToolComponent>,
+}
+
+impl<'a> Localizer<'a> {
+  /// Returns a localizer using the translations of the run into `language`.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run whose data to localize
+  /// * `language` - The desired language, ex. `de-DE` or `ja`
+  pub fn new(run: &'a sarif::Run, language: &str) -> Self {
+    Self {
+      run,
+      language: language.to_string(),
+      translations: vec![],
+    }
+    .translations(run.translations.iter().flatten())
+  }
+
+  /// Adds translations which are not part of the run, ex. those read with
+  /// [load_translations]. Translations into other languages are ignored.
+  ///
+  /// # Arguments
+  ///
+  /// * `translations` - The translation tool components to add
+  pub fn translations<I>(mut self, translations: I) -> Self
+  where
+    I: IntoIterator<Item = &'a sarif::ToolComponent>,
+  {
+    let language = self.language.clone();
+    self
+      .translations
+      .extend(translations.into_iter().filter(|t| {
+        t.language
+          .as_deref()
+          .is_some_and(|tag| language_matches(tag, &language))
+          && t.contents.as_ref().is_none_or(|contents| {
+            contents.contains(&sarif::ToolComponentContents::LocalizedData)
+          })
+      }));
+    // translations into exactly the desired language come first
+    self.translations.sort_by_key(|t| {
+      !t.language
+        .as_deref()
+        .is_some_and(|tag| tag.eq_ignore_ascii_case(&language))
+    });
+    self
+  }
+
+  /// Returns whether the desired language is the language of the run, in
+  /// which case the messages of the log need no translation.
+  pub fn is_native(&self) -> bool {
+    language_matches(
+      self.run.language.as_deref().unwrap_or(DEFAULT_LANGUAGE),
+      &self.language,
+    )
+  }
+
+  /// Returns the message string of a message of a result in the desired
+  /// language, falling back to [sarif::Message::lookup].
+  ///
+  /// # Arguments
+  ///
+  /// * `message` - The message, ex. `result.message`
+  /// * `result` - The result the message belongs to
+  pub fn lookup(
+    &self,
+    message: &'a sarif::Message,
+    result: &'a sarif::Result,
+  ) -> Option<&'a str> {
+    let component = self.run.resolve_tool_component(
+      result
+        .rule
+        .as_ref()
+        .and_then(|rule| rule.tool_component.as_ref()),
+    );
+    let descriptor = self.run.resolve_rule(result);
+    self
+      .translate(message, component, descriptor, false)
+      .or_else(|| message_string(message, descriptor, component))
+  }
+
+  /// Formats a message of a result in the desired language, see
+  /// [sarif::Message::format].
+  ///
+  /// # Arguments
+  ///
+  /// * `message` - The message, ex. `result.message`
+  /// * `result` - The result the message belongs to
+  pub fn message(
+    &self,
+    message: &'a sarif::Message,
+    result: &'a sarif::Result,
+  ) -> Option<FormattedMessage<'a>> {
+    let template = self.lookup(message, result)?;
+    Some(format_template(
+      template,
+      message.arguments.as_deref().unwrap_or_default(),
+      result.related_locations.as_deref().unwrap_or_default(),
+    ))
+  }
+
+  /// Formats the message of a notification in the desired language, looking
+  /// up message strings in the notification's descriptor.
+  ///
+  /// # Arguments
+  ///
+  /// * `notification` - A notification of the run
+  pub fn notification(
+    &self,
+    notification: &'a sarif::Notification,
+  ) -> Option<FormattedMessage<'a>> {
+    let message = &notification.message;
+    let component = self.run.resolve_tool_component(
+      notification
+        .descriptor
+        .as_ref()
+        .and_then(|descriptor| descriptor.tool_component.as_ref()),
+    );
+    let descriptor = self.run.resolve_notification(notification);
+    let template = self
+      .translate(message, component, descriptor, true)
+      .or_else(|| message_string(message, descriptor, component))?;
+    Some(format_template(
+      template,
+      message.arguments.as_deref().unwrap_or_default(),
+      &[],
+    ))
+  }
+
+  /// Returns the short description of the rule of a result in the desired
+  /// language.
+  ///
+  /// # Arguments
+  ///
+  /// * `result` - A result of the run
+  pub fn short_description(&self, result: &sarif::Result) -> Option<&'a str> {
+    self
+      .rules(result)
+      .find_map(|rule| rule.short_description.as_ref())
+      .map(|mfms| mfms.text.as_str())
+  }
+
+  /// Returns the full description of the rule of a result in the desired
+  /// language.
+  ///
+  /// # Arguments
+  ///
+  /// * `result` - A result of the run
+  pub fn full_description(&self, result: &sarif::Result) -> Option<&'a str> {
+    self
+      .rules(result)
+      .find_map(|rule| rule.full_description.as_ref())
+      .map(|mfms| mfms.text.as_str())
+  }
+
+  // Returns the translations of the rule of a result, followed by the rule
+  fn rules(
+    &self,
+    result: &sarif::Result,
+  ) -> impl Iterator<Item = &'a sarif::ReportingDescriptor> {
+    let component = self.run.resolve_tool_component(
+      result
+        .rule
+        .as_ref()
+        .and_then(|rule| rule.tool_component.as_ref()),
+    );
+    let rule = self.run.resolve_rule(result);
+    let translated = match (component, rule) {
+      (Some(component), Some(rule)) if !self.is_native() => self
+        .translations_of(component)
+        .filter_map(|translation| translated(translation, rule, false))
+        .collect(),
+      _ => vec![],
+    };
+    translated.into_iter().chain(rule)
+  }
+
+  // Looks up a message string in the translations of a component, unless
+  // the message is already in the desired language
+  fn translate(
+    &self,
+    message: &sarif::Message,
+    component: Option<&'a sarif::ToolComponent>,
+    descriptor: Option<&'a sarif::ReportingDescriptor>,
+    notification: bool,
+  ) -> Option<&'a str> {
+    if self.is_native() && message.text.is_some() {
+      return None;
+    }
+    let id = message.id.as_ref()?;
+    self.translations_of(component?).find_map(|translation| {
+      descriptor
+        .and_then(|descriptor| {
+          translated(translation, descriptor, notification)
+        })
+        .and_then(|descriptor| descriptor.message_strings.as_ref())
+        .and_then(|message_strings| message_strings.get(id))
+        .or_else(|| {
+          translation
+            .global_message_strings
+            .as_ref()
+            .and_then(|message_strings| message_strings.get(id))
+        })
+        .map(|mfms| mfms.text.as_str())
+    })
+  }
+
+  // Returns the translations whose associated component is `component`;
+  // translations without one translate the driver
+  fn translations_of<'b>(
+    &'b self,
+    component: &'a sarif::ToolComponent,
+  ) -> impl Iterator<Item = &'a sarif::ToolComponent> + 'b {
+    self
+      .translations
+      .iter()
+      .copied()
+      .filter(move |translation| {
+        self
+          .run
+          .resolve_tool_component(translation.associated_component.as_ref())
+          .is_some_and(|associated| std::ptr::eq(associated, component))
+      })
+  }
+}
+
+// Returns the descriptor of a translation which translates `descriptor`
+fn translated<'a>(
+  translation: &'a sarif::ToolComponent,
+  descriptor: &sarif::ReportingDescriptor,
+  notification: bool,
+) -> Option<&'a sarif::ReportingDescriptor> {
+  let descriptors = if notification {
+    translation.notifications.as_deref()
+  } else {
+    translation.rules.as_deref()
+  };
+  descriptors?.iter().find(|translated| {
+    match (translated.guid.as_ref(), descriptor.guid.as_ref()) {
+      (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+      _ => translated.id == descriptor.id,
+    }
+  })
+}
+
+// Compares language tags, ex. `de-DE`, ignoring their region if it is not
+// specified by both
+fn language_matches(tag: &str, desired: &str) -> bool {
+  let primary =
+    |tag: &str| tag.split('-').next().unwrap_or_default().to_string();
+  let (has_region, wants_region) = (tag.contains('-'), desired.contains('-'));
+  if has_region && wants_region {
+    tag.eq_ignore_ascii_case(desired)
+  } else {
+    primary(tag).eq_ignore_ascii_case(&primary(desired))
+  }
+}
+
+/// Loads the translations of a run which live in separate files: the
+/// `externalProperties` documents among the run's artifacts whose roles
+/// include `translation`, and those the `locations` of the run's
+/// translations refer to.
+///
+/// # Arguments
+///
+/// * `run` - The run whose translations to load
+/// * `resolver` - Resolves the locations of the files
+pub fn load_translations(
+  run: &sarif::Run,
+  resolver: &UriResolver,
+) -> Result<Vec<sarif::ToolComponent>, ExternalPropertiesError> {
+  let artifacts = run
+    .artifacts
+    .iter()
+    .flatten()
+    .filter(|artifact| {
+      artifact
+        .roles
+        .iter()
+        .flatten()
+        .any(|role| *role == sarif::ArtifactRoles::Translation)
+    })
+    .filter_map(|artifact| artifact.location.as_ref());
+  let locations = run
+    .translations
+    .iter()
+    .flatten()
+    .flat_map(|translation| translation.locations.iter().flatten());
+  let mut translations = vec![];
+  for location in artifacts.chain(locations) {
+    let external = load(resolver.resolve_path(run, location)?)?;
+    translations.extend(external.translations.into_iter().flatten());
+  }
+  Ok(translations)
+}
@@ -0,0 +1,601 @@
+//! Merging of several SARIF logs into one.
+//!
+//! Tools such as Github code scanning accept a single SARIF file per upload,
+//! while each converter in this repository produces a log of its own.
+//! [merge] combines them, either keeping every run as is or coalescing the
+//! runs of the same tool into a single run. The `properties` of the logs are
+//! merged too: their tags are unioned, and each other property is taken from
+//! the first log which has it.
+//!
+//! When runs are coalesced, the rules of their tool drivers are unioned by
+//! id, and the other arrays of each run which results refer to by index, ex.
+//! `artifacts`, `logicalLocations` or `webRequests`, are appended to those of
+//! the first. Extensions, taxonomies, translations and policies are only
+//! appended if the first run does not have them already. Every index into
+//! these arrays is rewritten to point at the merged arrays.
+//! `originalUriBaseIds` are unioned as well; a base id which is defined
+//! differently by two runs is renamed in the later run. Runs which count
+//! columns in different units (`columnKind`) are not coalesced, as their
+//! regions cannot be combined.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::merge::{merge, MergeStrategy};
+//! use serde_sarif::sarif::Sarif;
+//!
+//! let log = |rule: &str| -> Sarif {
+//!   serde_json::from_value(serde_json::json!({
+//!     "version": "2.1.0",
+//!     "runs": [{
+//!       "tool": { "driver": { "name": "clippy", "rules": [{ "id": rule }] } },
+//!       "results": [{ "message": { "text": "message" }, "ruleIndex": 0 }]
+//!     }]
+//!   }))
+//!   .unwrap()
+//! };
+//!
+//! let merged =
+//!   merge(vec![log("a"), log("b")], MergeStrategy::SideBySide).unwrap();
+//! assert_eq!(merged.runs.len(), 2);
+//!
+//! let merged =
+//!   merge(vec![log("a"), log("b")], MergeStrategy::CoalesceByTool).unwrap();
+//! assert_eq!(merged.runs.len(), 1);
+//! let results = merged.runs[0].results.as_ref().unwrap();
+//! assert_eq!(results[1].rule_index, Some(1));
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+use thiserror::Error;
+
+use crate::region::ColumnUnit;
+use crate::sarif;
+
+/// An error merging logs.
+#[derive(Error, Debug)]
+pub enum MergeError {
+  #[error(
+    "cannot coalesce the runs of {0}, which count columns in different units"
+  )]
+  ColumnKind(String),
+}
+
+/// How the runs of the merged logs are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+  /// Every run is kept as is.
+  SideBySide,
+  /// Runs whose `tool.driver.name` is the same are combined into one run.
+  CoalesceByTool,
+}
+
+/// Merges several SARIF logs into one
+///
+/// # Arguments
+///
+/// * `logs` - The logs to merge
+/// * `strategy` - How the runs of the logs are combined
+pub fn merge<I: IntoIterator<Item = sarif::Sarif>>(
+  logs: I,
+  strategy: MergeStrategy,
+) -> Result<sarif::Sarif, MergeError> {
+  let mut runs: Vec<sarif::Run> = vec![];
+  let mut inline_external_properties = vec![];
+  let mut properties: Option<sarif::PropertyBag> = None;
+  for log in logs {
+    inline_external_properties
+      .extend(log.inline_external_properties.into_iter().flatten());
+    if let Some(bag) = log.properties {
+      properties.get_or_insert_with(Default::default).merge(bag);
+    }
+    for run in log.runs {
+      match strategy {
+        MergeStrategy::SideBySide => runs.push(run),
+        MergeStrategy::CoalesceByTool => {
+          match runs
+            .iter_mut()
+            .find(|r| r.tool.driver.name == run.tool.driver.name)
+          {
+            Some(target) if ColumnUnit::of(target) != ColumnUnit::of(&run) => {
+              return Err(MergeError::ColumnKind(run.tool.driver.name))
+            }
+            Some(target) => coalesce(target, run),
+            None => runs.push(run),
+          }
+        }
+      }
+    }
+  }
+
+  Ok(sarif::Sarif {
+    schema: Some(sarif::SCHEMA_URL.into()),
+    inline_external_properties: if inline_external_properties.is_empty() {
+      None
+    } else {
+      Some(inline_external_properties)
+    },
+    properties,
+    runs,
+    version: sarif::Version::V2_1_0,
+  })
+}
+
+// Appends the elements of `source` to `target`, returning the new index of
+// each appended element. Elements for which `find` returns an index in
+// `target` are not appended but mapped to that index instead.
+fn append<T>(
+  target: &mut Option<Vec<T>>,
+  source: Option<Vec<T>>,
+  find: impl Fn(&[T], &T) -> Option<usize>,
+) -> Vec<i64> {
+  let source = match source {
+    Some(source) if !source.is_empty() => source,
+    _ => return vec![],
+  };
+  let target = target.get_or_insert_with(Vec::new);
+  source
+    .into_iter()
+    .map(|element| {
+      let index = find(target, &element).unwrap_or_else(|| {
+        target.push(element);
+        target.len() - 1
+      });
+      index as i64
+    })
+    .collect()
+}
+
+// Returns the new index of each element of `source` once it is appended to
+// `target` as is
+fn offsets<T>(target: &Option<Vec<T>>, source: &Option<Vec<T>>) -> Vec<i64> {
+  let offset = target.as_ref().map_or(0, |t| t.len());
+  (0..source.as_ref().map_or(0, |s| s.len()))
+    .map(|i| (offset + i) as i64)
+    .collect()
+}
+
+// Returns the index of an element equal to `element`
+fn find_equal<T: PartialEq>(elements: &[T], element: &T) -> Option<usize> {
+  elements.iter().position(|e| e == element)
+}
+
+// Maps an index through `mapping`, leaving unset (negative) or out of range
+// indices unchanged
+fn remap(index: &mut Option<i64>, mapping: &[i64]) {
+  if let Some(i) = index {
+    if let Some(mapped) = usize::try_from(*i).ok().and_then(|i| mapping.get(i))
+    {
+      *i = *mapped;
+    }
+  }
+}
+
+// The new indices of the elements of the source run's arrays in the coalesced
+// run, and the base ids renamed in the source run
+#[derive(Default)]
+struct Mapping {
+  rules: Vec<i64>,
+  extensions: Vec<i64>,
+  taxonomies: Vec<i64>,
+  invocations: Vec<i64>,
+  artifacts: Vec<i64>,
+  logical_locations: Vec<i64>,
+  addresses: Vec<i64>,
+  graphs: Vec<i64>,
+  thread_flow_locations: Vec<i64>,
+  web_requests: Vec<i64>,
+  web_responses: Vec<i64>,
+  uri_base_ids: HashMap<String, String>,
+}
+
+impl Mapping {
+  fn artifact_location(&self, location: &mut sarif::ArtifactLocation) {
+    remap(&mut location.index, &self.artifacts);
+    if let Some(renamed) = location
+      .uri_base_id
+      .as_ref()
+      .and_then(|id| self.uri_base_ids.get(id))
+    {
+      location.uri_base_id = Some(renamed.clone());
+    }
+  }
+
+  fn logical_location(&self, location: &mut sarif::LogicalLocation) {
+    remap(&mut location.index, &self.logical_locations);
+    remap(&mut location.parent_index, &self.logical_locations);
+  }
+
+  fn address(&self, address: &mut sarif::Address) {
+    remap(&mut address.index, &self.addresses);
+    remap(&mut address.parent_index, &self.addresses);
+  }
+
+  fn location(&self, location: &mut sarif::Location) {
+    if let Some(physical_location) = location.physical_location.as_mut() {
+      physical_location
+        .artifact_location
+        .iter_mut()
+        .for_each(|l| self.artifact_location(l));
+      physical_location
+        .address
+        .iter_mut()
+        .for_each(|a| self.address(a));
+    }
+    location
+      .logical_locations
+      .iter_mut()
+      .flatten()
+      .for_each(|l| self.logical_location(l));
+  }
+
+  fn graph(&self, graph: &mut sarif::Graph) {
+    graph
+      .nodes
+      .iter_mut()
+      .flatten()
+      .for_each(|node| self.node(node));
+  }
+
+  fn node(&self, node: &mut sarif::Node) {
+    node.location.iter_mut().for_each(|l| self.location(l));
+    node
+      .children
+      .iter_mut()
+      .flatten()
+      .for_each(|child| self.node(child));
+  }
+
+  fn stack(&self, stack: &mut sarif::Stack) {
+    stack
+      .frames
+      .iter_mut()
+      .flat_map(|frame| frame.location.iter_mut())
+      .for_each(|l| self.location(l))
+  }
+
+  // taxa refer to their taxonomy by its index into the run's taxonomies
+  fn taxa(&self, taxa: &mut Option<Vec<sarif::ReportingDescriptorReference>>) {
+    taxa
+      .iter_mut()
+      .flatten()
+      .filter_map(|taxon| taxon.tool_component.as_mut())
+      .for_each(|component| remap(&mut component.index, &self.taxonomies))
+  }
+
+  fn thread_flow_location(&self, location: &mut sarif::ThreadFlowLocation) {
+    location.location.iter_mut().for_each(|l| self.location(l));
+    location.stack.iter_mut().for_each(|s| self.stack(s));
+    self.taxa(&mut location.taxa);
+    if let Some(request) = location.web_request.as_mut() {
+      remap(&mut request.index, &self.web_requests);
+    }
+    if let Some(response) = location.web_response.as_mut() {
+      remap(&mut response.index, &self.web_responses);
+    }
+  }
+
+  // A relationship whose target names a tool component is taken to refer to
+  // a taxon, else it refers to another rule of the driver
+  fn relationships(&self, rule: &mut sarif::ReportingDescriptor) {
+    rule
+      .relationships
+      .iter_mut()
+      .flatten()
+      .for_each(|relationship| {
+        let target = &mut relationship.target;
+        match target.tool_component.as_mut() {
+          Some(component) => remap(&mut component.index, &self.taxonomies),
+          None => remap(&mut target.index, &self.rules),
+        }
+      })
+  }
+
+  // A reference to a rule of the driver, or to a descriptor of an extension
+  fn rule_reference(&self, rule: &mut sarif::ReportingDescriptorReference) {
+    match rule.tool_component.as_mut() {
+      Some(component) => remap(&mut component.index, &self.extensions),
+      None => remap(&mut rule.index, &self.rules),
+    }
+  }
+
+  // The notification descriptors of the driver are not merged, so only those
+  // of extensions move
+  fn notification_reference(
+    &self,
+    descriptor: &mut sarif::ReportingDescriptorReference,
+  ) {
+    if let Some(component) = descriptor.tool_component.as_mut() {
+      remap(&mut component.index, &self.extensions);
+    }
+  }
+
+  fn exception(&self, exception: &mut sarif::Exception) {
+    exception.stack.iter_mut().for_each(|s| self.stack(s));
+    exception
+      .inner_exceptions
+      .iter_mut()
+      .flatten()
+      .for_each(|e| self.exception(e));
+  }
+
+  fn notification(&self, notification: &mut sarif::Notification) {
+    notification
+      .associated_rule
+      .iter_mut()
+      .for_each(|rule| self.rule_reference(rule));
+    notification
+      .descriptor
+      .iter_mut()
+      .for_each(|descriptor| self.notification_reference(descriptor));
+    notification
+      .locations
+      .iter_mut()
+      .flatten()
+      .for_each(|l| self.location(l));
+    notification
+      .exception
+      .iter_mut()
+      .for_each(|e| self.exception(e));
+  }
+
+  fn invocation(&self, invocation: &mut sarif::Invocation) {
+    invocation
+      .rule_configuration_overrides
+      .iter_mut()
+      .flatten()
+      .for_each(|o| self.rule_reference(&mut o.descriptor));
+    invocation
+      .notification_configuration_overrides
+      .iter_mut()
+      .flatten()
+      .for_each(|o| self.notification_reference(&mut o.descriptor));
+    invocation
+      .tool_execution_notifications
+      .iter_mut()
+      .flatten()
+      .chain(
+        invocation
+          .tool_configuration_notifications
+          .iter_mut()
+          .flatten(),
+      )
+      .for_each(|n| self.notification(n));
+    invocation
+      .executable_location
+      .iter_mut()
+      .chain(invocation.response_files.iter_mut().flatten())
+      .chain(invocation.stdin.iter_mut())
+      .chain(invocation.stdout.iter_mut())
+      .chain(invocation.stderr.iter_mut())
+      .chain(invocation.stdout_stderr.iter_mut())
+      .chain(invocation.working_directory.iter_mut())
+      .for_each(|l| self.artifact_location(l));
+  }
+
+  fn result(&self, result: &mut sarif::Result) {
+    let rule_component = result
+      .rule
+      .as_mut()
+      .and_then(|rule| rule.tool_component.as_mut());
+    match rule_component {
+      // the rule belongs to an extension, so only the extension moves
+      Some(component) => remap(&mut component.index, &self.extensions),
+      None => {
+        remap(&mut result.rule_index, &self.rules);
+        if let Some(rule) = result.rule.as_mut() {
+          remap(&mut rule.index, &self.rules);
+        }
+      }
+    }
+    self.taxa(&mut result.taxa);
+    if let Some(provenance) = result.provenance.as_mut() {
+      remap(&mut provenance.invocation_index, &self.invocations);
+    }
+    if let Some(request) = result.web_request.as_mut() {
+      remap(&mut request.index, &self.web_requests);
+    }
+    if let Some(response) = result.web_response.as_mut() {
+      remap(&mut response.index, &self.web_responses);
+    }
+    result
+      .graph_traversals
+      .iter_mut()
+      .flatten()
+      .for_each(|traversal| {
+        remap(&mut traversal.run_graph_index, &self.graphs)
+      });
+    result
+      .graphs
+      .iter_mut()
+      .flatten()
+      .for_each(|g| self.graph(g));
+    result
+      .analysis_target
+      .iter_mut()
+      .for_each(|l| self.artifact_location(l));
+    result
+      .fixes
+      .iter_mut()
+      .flatten()
+      .flat_map(|fix| fix.artifact_changes.iter_mut())
+      .for_each(|change| self.artifact_location(&mut change.artifact_location));
+    result
+      .locations
+      .iter_mut()
+      .flatten()
+      .chain(result.related_locations.iter_mut().flatten())
+      .for_each(|l| self.location(l));
+    result
+      .stacks
+      .iter_mut()
+      .flatten()
+      .for_each(|s| self.stack(s));
+    result
+      .code_flows
+      .iter_mut()
+      .flatten()
+      .flat_map(|code_flow| code_flow.thread_flows.iter_mut())
+      .flat_map(|thread_flow| thread_flow.locations.iter_mut())
+      .for_each(|location| {
+        remap(&mut location.index, &self.thread_flow_locations);
+        self.thread_flow_location(location)
+      });
+  }
+}
+
+// Combines `source` into `target`, which was produced by the same tool and
+// counts columns in the same unit
+fn coalesce(target: &mut sarif::Run, mut source: sarif::Run) {
+  let rule_count = target.tool.driver.rules.as_ref().map_or(0, |r| r.len());
+  let mut mapping = Mapping {
+    rules: append(
+      &mut target.tool.driver.rules,
+      source.tool.driver.rules.take(),
+      |rules, rule| rules.iter().position(|r| r.id == rule.id),
+    ),
+    extensions: append(
+      &mut target.tool.extensions,
+      source.tool.extensions.take(),
+      |extensions, extension| {
+        extensions.iter().position(|e| {
+          e.name == extension.name
+            && e.guid == extension.guid
+            && e.version == extension.version
+        })
+      },
+    ),
+    taxonomies: append(
+      &mut target.taxonomies,
+      source.taxonomies.take(),
+      find_equal,
+    ),
+    invocations: offsets(&target.invocations, &source.invocations),
+    artifacts: offsets(&target.artifacts, &source.artifacts),
+    logical_locations: offsets(
+      &target.logical_locations,
+      &source.logical_locations,
+    ),
+    addresses: offsets(&target.addresses, &source.addresses),
+    graphs: offsets(&target.graphs, &source.graphs),
+    thread_flow_locations: offsets(
+      &target.thread_flow_locations,
+      &source.thread_flow_locations,
+    ),
+    web_requests: offsets(&target.web_requests, &source.web_requests),
+    web_responses: offsets(&target.web_responses, &source.web_responses),
+    uri_base_ids: HashMap::new(),
+  };
+  append(
+    &mut target.translations,
+    source.translations.take(),
+    find_equal,
+  );
+  append(&mut target.policies, source.policies.take(), find_equal);
+
+  // base ids defined differently by the two runs are renamed in the source
+  let source_ids = source.original_uri_base_ids.take().unwrap_or_default();
+  let target_ids = match target.original_uri_base_ids.as_mut() {
+    Some(target_ids) => target_ids,
+    None if source_ids.is_empty() => &mut BTreeMap::new(),
+    None => target.original_uri_base_ids.insert(BTreeMap::new()),
+  };
+  mapping.uri_base_ids = source_ids
+    .iter()
+    .filter(|(key, location)| {
+      target_ids.get(*key).is_some_and(|l| l != *location)
+    })
+    .map(|(key, _)| {
+      let renamed = (2..)
+        .map(|n| format!("{}_{}", key, n))
+        .find(|id| !target_ids.contains_key(id) && !source_ids.contains_key(id))
+        .unwrap_or_else(|| key.clone());
+      (key.clone(), renamed)
+    })
+    .collect();
+  source_ids.into_iter().for_each(|(key, mut location)| {
+    mapping.artifact_location(&mut location);
+    let key = mapping.uri_base_ids.get(&key).cloned().unwrap_or(key);
+    target_ids.entry(key).or_insert(location);
+  });
+
+  // the rules appended from the source may relate to taxa and other rules
+  target
+    .tool
+    .driver
+    .rules
+    .iter_mut()
+    .flat_map(|rules| rules.iter_mut().skip(rule_count))
+    .for_each(|rule| mapping.relationships(rule));
+
+  let mut invocations = source.invocations.take();
+  invocations
+    .iter_mut()
+    .flatten()
+    .for_each(|invocation| mapping.invocation(invocation));
+  append(&mut target.invocations, invocations, |_, _| None);
+
+  let mut artifacts = source.artifacts.take();
+  artifacts.iter_mut().flatten().for_each(|artifact| {
+    remap(&mut artifact.parent_index, &mapping.artifacts);
+    artifact
+      .location
+      .iter_mut()
+      .for_each(|l| mapping.artifact_location(l));
+  });
+  append(&mut target.artifacts, artifacts, |_, _| None);
+
+  let mut logical_locations = source.logical_locations.take();
+  logical_locations
+    .iter_mut()
+    .flatten()
+    .for_each(|l| mapping.logical_location(l));
+  append(&mut target.logical_locations, logical_locations, |_, _| {
+    None
+  });
+
+  let mut addresses = source.addresses.take();
+  addresses
+    .iter_mut()
+    .flatten()
+    .for_each(|a| mapping.address(a));
+  append(&mut target.addresses, addresses, |_, _| None);
+
+  let mut thread_flow_locations = source.thread_flow_locations.take();
+  thread_flow_locations
+    .iter_mut()
+    .flatten()
+    .for_each(|l| mapping.thread_flow_location(l));
+  append(
+    &mut target.thread_flow_locations,
+    thread_flow_locations,
+    |_, _| None,
+  );
+
+  let mut web_requests = source.web_requests.take();
+  web_requests
+    .iter_mut()
+    .flatten()
+    .for_each(|request| remap(&mut request.index, &mapping.web_requests));
+  append(&mut target.web_requests, web_requests, |_, _| None);
+
+  let mut web_responses = source.web_responses.take();
+  web_responses
+    .iter_mut()
+    .flatten()
+    .for_each(|response| remap(&mut response.index, &mapping.web_responses));
+  append(&mut target.web_responses, web_responses, |_, _| None);
+
+  let mut graphs = source.graphs.take();
+  graphs.iter_mut().flatten().for_each(|g| mapping.graph(g));
+  append(&mut target.graphs, graphs, |_, _| None);
+
+  let mut results = match source.results.take() {
+    Some(results) => results,
+    None => return,
+  };
+  results.iter_mut().for_each(|result| mapping.result(result));
+  target.results.get_or_insert_with(Vec::new).extend(results);
+}
@@ -0,0 +1,274 @@
+//! Formatting of message strings.
+//!
+//! The text of a SARIF `message` is not always given verbatim. A message may
+//! instead refer by `id` to a string defined by the rule of its result or by
+//! the tool component, and message strings may contain placeholders, ex.
+//! `{0}`, which are replaced by the message's `arguments`. A literal curly
+//! bracket is written `{{` or `}}`.
+//!
+//! Plain text messages may also embed links, ex. `[this call](1)`, whose
+//! target is either the `id` of one of the result's `relatedLocations` or a
+//! URI. Square brackets which are not part of a link are escaped with a
+//! backslash.
+//!
+//! [sarif::Message::format] looks up the message string and returns it as a
+//! [FormattedMessage], a sequence of [Span]s of text and resolved links.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::message::{LinkTarget, Span};
+//! use serde_sarif::sarif;
+//!
+//! let run: sarif::Run = serde_json::from_value(serde_json::json!({
+//!   "tool": { "driver": {
+//!     "name": "tool",
+//!     "rules": [{
+//!       "id": "TAINT",
+//!       "messageStrings": {
+//!         "default": { "text": "{0} flows into [this call](1)" }
+//!       }
+//!     }]
+//!   } }
+//! }))
+//! .unwrap();
+//! let result: sarif::Result = serde_json::from_value(serde_json::json!({
+//!   "ruleId": "TAINT",
+//!   "message": { "id": "default", "arguments": ["user input"] },
+//!   "relatedLocations": [{ "id": 1, "message": { "text": "sink" } }]
+//! }))
+//! .unwrap();
+//!
+//! let message = result.message.format(&run, &result).unwrap();
+//! assert_eq!(message.to_string(), "user input flows into this call");
+//! match &message.spans[1] {
+//!   Span::Link { text, target: LinkTarget::Location(location) } => {
+//!     assert_eq!(text, "this call");
+//!     assert_eq!(location.id, Some(1));
+//!   }
+//!   span => panic!("unexpected span {:?}", span),
+//! }
+//! ```
+
+use crate::sarif;
+use std::fmt;
+
+/// The target of a link embedded in a message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkTarget<'a> {
+  /// One of the result's related locations, referred to by its `id`
+  Location(&'a sarif::Location),
+  /// Any other target
+  Uri(String),
+}
+
+/// A span of a formatted message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Span<'a> {
+  /// Text with its placeholders substituted and escapes removed
+  Text(String),
+  /// An embedded link
+  Link {
+    /// The text of the link
+    text: String,
+    /// What the link refers to
+    target: LinkTarget<'a>,
+  },
+}
+
+/// A message with its placeholders substituted and its embedded links
+/// resolved. Displays as plain text, which drops link targets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FormattedMessage<'a> {
+  /// The text and links of the message, in order
+  pub spans: Vec<Span<'a>>,
+}
+
+impl FormattedMessage<'_> {
+  /// Returns the links of the message together with their text.
+  pub fn links(&self) -> impl Iterator<Item = (&str, &LinkTarget<'_>)> {
+    self.spans.iter().filter_map(|span| match span {
+      Span::Link { text, target } => Some((text.as_str(), target)),
+      Span::Text(_) => None,
+    })
+  }
+}
+
+impl fmt::Display for FormattedMessage<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.spans.iter().try_for_each(|span| match span {
+      Span::Text(text) | Span::Link { text, .. } => f.write_str(text),
+    })
+  }
+}
+
+impl sarif::Message {
+  /// Returns the message string, following the lookup procedure of the
+  /// specification (§3.11.7): the message's own `text`, else the string its
+  /// `id` refers to in the `messageStrings` of the result's rule, else the
+  /// one in the `globalMessageStrings` of the rule's tool component.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run of the result
+  /// * `result` - The result the message belongs to
+  pub fn lookup<'a>(
+    &'a self,
+    run: &'a sarif::Run,
+    result: &sarif::Result,
+  ) -> Option<&'a str> {
+    let component = run.resolve_tool_component(
+      result
+        .rule
+        .as_ref()
+        .and_then(|rule| rule.tool_component.as_ref()),
+    );
+    message_string(self, run.resolve_rule(result), component)
+  }
+
+  /// Looks up the message string, substitutes the message's arguments for
+  /// its placeholders and resolves its embedded links against the
+  /// `relatedLocations` of the result. Returns `None` if there is no
+  /// message string. Placeholders without an argument, and links to
+  /// locations the result does not define, are left as text.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run of the result
+  /// * `result` - The result the message belongs to
+  pub fn format<'a>(
+    &'a self,
+    run: &'a sarif::Run,
+    result: &'a sarif::Result,
+  ) -> Option<FormattedMessage<'a>> {
+    let template = self.lookup(run, result)?;
+    Some(format_template(
+      template,
+      self.arguments.as_deref().unwrap_or_default(),
+      result.related_locations.as_deref().unwrap_or_default(),
+    ))
+  }
+}
+
+// Returns the text of a message, else the string its id refers to in the
+// messageStrings of a descriptor, else in the globalMessageStrings of a tool
+// component
+pub(crate) fn message_string<'a>(
+  message: &'a sarif::Message,
+  descriptor: Option<&'a sarif::ReportingDescriptor>,
+  component: Option<&'a sarif::ToolComponent>,
+) -> Option<&'a str> {
+  if let Some(text) = message.text.as_ref() {
+    return Some(text);
+  }
+  let id = message.id.as_ref()?;
+  descriptor
+    .and_then(|descriptor| descriptor.message_strings.as_ref())
+    .and_then(|message_strings| message_strings.get(id))
+    .or_else(|| {
+      component
+        .and_then(|component| component.global_message_strings.as_ref())
+        .and_then(|message_strings| message_strings.get(id))
+    })
+    .map(|mfms| mfms.text.as_str())
+}
+
+// Substitutes the arguments of a message string for its placeholders and
+// resolves its embedded links against the related locations
+pub(crate) fn format_template<'a>(
+  template: &str,
+  arguments: &[String],
+  related: &'a [sarif::Location],
+) -> FormattedMessage<'a> {
+  let mut spans = vec![];
+  let mut text = String::new();
+  let mut rest = template;
+  while !rest.is_empty() {
+    if let Some((link_text, target, len)) = link(rest) {
+      let link_text = substitute(link_text, arguments);
+      let target = match target.parse::<i64>() {
+        Ok(id) => related
+          .iter()
+          .find(|location| location.id == Some(id))
+          .map(LinkTarget::Location),
+        Err(_) => Some(LinkTarget::Uri(target.to_string())),
+      };
+      if let Some(target) = target {
+        if !text.is_empty() {
+          spans.push(Span::Text(std::mem::take(&mut text)));
+        }
+        spans.push(Span::Link {
+          text: link_text,
+          target,
+        });
+      } else {
+        text.push_str(&link_text);
+      }
+      rest = &rest[len..];
+      continue;
+    }
+    let len = token(rest, arguments, &mut text);
+    rest = &rest[len..];
+  }
+  if !text.is_empty() {
+    spans.push(Span::Text(text));
+  }
+  FormattedMessage { spans }
+}
+
+// Appends the first token of a message string to the text, substituting
+// placeholders and unescaping brackets, and returns its length
+fn token(rest: &str, arguments: &[String], text: &mut String) -> usize {
+  for escape in &["{{", "}}", "\\[", "\\]", "\\\\"] {
+    if rest.starts_with(escape) {
+      text.push_str(&escape[1..]);
+      return 2;
+    }
+  }
+  if let Some(placeholder) = rest.strip_prefix('{') {
+    let digits = placeholder
+      .find(|c: char| !c.is_ascii_digit())
+      .unwrap_or(placeholder.len());
+    if digits > 0 && placeholder[digits..].starts_with('}') {
+      if let Some(argument) = placeholder[..digits]
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| arguments.get(index))
+      {
+        text.push_str(argument);
+        return digits + 2;
+      }
+    }
+  }
+  let c = rest.chars().next().unwrap_or_default();
+  text.push(c);
+  c.len_utf8()
+}
+
+fn substitute(template: &str, arguments: &[String]) -> String {
+  let mut text = String::new();
+  let mut rest = template;
+  while !rest.is_empty() {
+    rest = &rest[token(rest, arguments, &mut text)..];
+  }
+  text
+}
+
+// Splits an embedded link, ex. `[text](target)`, at the start of a message
+// string into its text and target, and returns them with its length
+fn link(rest: &str) -> Option<(&str, &str, usize)> {
+  let inner = rest.strip_prefix('[')?;
+  let mut chars = inner.char_indices();
+  let end = loop {
+    match chars.next()? {
+      (_, '\\') => {
+        chars.next();
+      }
+      (i, ']') => break i,
+      _ => {}
+    }
+  };
+  let target = inner[end + 1..].strip_prefix('(')?;
+  let close = target.find(')')?;
+  Some((&inner[..end], &target[..close], end + close + 4))
+}
@@ -0,0 +1,313 @@
+//! Typed access to property bags.
+//!
+//! Every SARIF object has a `properties` bag holding data the specification
+//! does not define, ex. the `precision` and `security-severity` of a rule
+//! which GitHub code scanning reads. The only property the specification
+//! names is `tags`; all others are kept in
+//! [sarif::PropertyBag::additional_properties] as JSON values.
+//!
+//! [sarif::PropertyBag::get] and [sarif::PropertyBag::set] read and write a
+//! property as any type implementing `serde`'s traits, and
+//! [sarif::PropertyBag::extension] and [sarif::PropertyBag::set_extension] do
+//! so for a struct of several properties, like the well-known ones of
+//! [GithubRuleProperties].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::properties::{GithubRuleProperties, Precision};
+//! use serde_sarif::sarif;
+//!
+//! let mut rule: sarif::ReportingDescriptor =
+//!   serde_json::from_value(serde_json::json!({
+//!     "id": "js/sql-injection",
+//!     "properties": { "tags": ["security"], "security-severity": "8.8" }
+//!   }))
+//!   .unwrap();
+//! let properties = rule.properties.get_or_insert_with(Default::default);
+//! assert_eq!(properties.tags(), ["security"]);
+//! assert_eq!(
+//!   properties.get::<String>("security-severity").unwrap().as_deref(),
+//!   Some("8.8")
+//! );
+//!
+//! let mut github: GithubRuleProperties = properties.extension().unwrap();
+//! assert_eq!(github.security_severity, Some(8.8));
+//! github.precision = Some(Precision::High);
+//! properties.set_extension(&github).unwrap();
+//! properties.add_tag("external/cwe/cwe-089");
+//!
+//! let json = serde_json::to_value(&rule).unwrap();
+//! assert_eq!(json["properties"]["precision"], "high");
+//! assert_eq!(json["properties"]["tags"][1], "external/cwe/cwe-089");
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use thiserror::Error;
+
+use crate::sarif;
+
+/// An error reading or writing a property.
+#[derive(Error, Debug)]
+pub enum PropertyError {
+  #[error("property {key} is invalid: {source}")]
+  Invalid {
+    key: String,
+    source: serde_json::Error,
+  },
+  #[error("an extension must be a struct of properties")]
+  NotAStruct,
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+}
+
+impl sarif::PropertyBag {
+  /// Returns the tags of the bag.
+  pub fn tags(&self) -> &[String] {
+    self.tags.as_deref().unwrap_or_default()
+  }
+
+  /// Returns whether the bag has a tag.
+  ///
+  /// # Arguments
+  ///
+  /// * `tag` - The tag, ex. `security`
+  pub fn has_tag(&self, tag: &str) -> bool {
+    self.tags().iter().any(|t| t == tag)
+  }
+
+  /// Adds a tag, unless the bag has it already. Tags are distinct.
+  ///
+  /// # Arguments
+  ///
+  /// * `tag` - The tag, ex. `security`
+  pub fn add_tag<S: Into<String>>(&mut self, tag: S) {
+    let tag = tag.into();
+    let tags = self.tags.get_or_insert_with(Vec::new);
+    if !tags.contains(&tag) {
+      tags.push(tag);
+    }
+  }
+
+  /// Merges another bag into this one: its tags are added, and its other
+  /// properties are set unless this bag has them already.
+  ///
+  /// # Arguments
+  ///
+  /// * `other` - The bag to merge
+  pub fn merge(&mut self, other: sarif::PropertyBag) {
+    other
+      .tags
+      .into_iter()
+      .flatten()
+      .for_each(|tag| self.add_tag(tag));
+    other
+      .additional_properties
+      .into_iter()
+      .for_each(|(key, value)| {
+        self.additional_properties.entry(key).or_insert(value);
+      });
+  }
+
+  /// Returns a property as a `T`, or `None` if the bag does not have it.
+  ///
+  /// # Arguments
+  ///
+  /// * `key` - The name of the property, ex. `security-severity`
+  pub fn get<T: DeserializeOwned>(
+    &self,
+    key: &str,
+  ) -> Result<Option<T>, PropertyError> {
+    let value = if key == "tags" {
+      match &self.tags {
+        Some(tags) => serde_json::to_value(tags)?,
+        None => return Ok(None),
+      }
+    } else {
+      match self.additional_properties.get(key) {
+        Some(value) => value.clone(),
+        None => return Ok(None),
+      }
+    };
+    serde_json::from_value(value).map(Some).map_err(|source| {
+      PropertyError::Invalid {
+        key: key.to_string(),
+        source,
+      }
+    })
+  }
+
+  /// Sets a property, replacing any previous value.
+  ///
+  /// # Arguments
+  ///
+  /// * `key` - The name of the property, ex. `security-severity`
+  /// * `value` - The value of the property
+  pub fn set<S: Into<String>, T: Serialize>(
+    &mut self,
+    key: S,
+    value: T,
+  ) -> Result<(), PropertyError> {
+    let key = key.into();
+    let value = serde_json::to_value(value)?;
+    if key == "tags" {
+      self.tags = serde_json::from_value(value)
+        .map_err(|source| PropertyError::Invalid { key, source })?;
+    } else {
+      self.additional_properties.insert(key, value);
+    }
+    Ok(())
+  }
+
+  /// Removes a property, returning its value.
+  ///
+  /// # Arguments
+  ///
+  /// * `key` - The name of the property
+  pub fn remove(&mut self, key: &str) -> Option<serde_json::Value> {
+    if key == "tags" {
+      self.tags.take().map(serde_json::Value::from)
+    } else {
+      self.additional_properties.remove(key)
+    }
+  }
+
+  /// Reads the properties of an extension, a struct whose fields are
+  /// properties, ex. [GithubRuleProperties]. Properties the bag does not
+  /// have are left to the struct's defaults.
+  pub fn extension<T: DeserializeOwned>(&self) -> Result<T, PropertyError> {
+    Ok(serde_json::from_value(serde_json::to_value(self)?)?)
+  }
+
+  /// Sets the properties of an extension, see [sarif::PropertyBag::extension].
+  /// Properties the extension does not serialize are kept.
+  ///
+  /// # Arguments
+  ///
+  /// * `extension` - The extension
+  pub fn set_extension<T: Serialize>(
+    &mut self,
+    extension: &T,
+  ) -> Result<(), PropertyError> {
+    match serde_json::to_value(extension)? {
+      serde_json::Value::Object(properties) => properties
+        .into_iter()
+        .try_for_each(|(key, value)| self.set(key, value)),
+      _ => Err(PropertyError::NotAStruct),
+    }
+  }
+}
+
+/// How often a rule is expected to report false positives, as shown by
+/// GitHub code scanning.
+#[derive(
+  Display,
+  Debug,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  Hash,
+  EnumString,
+  Serialize,
+  Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum Precision {
+  VeryHigh,
+  High,
+  Medium,
+  Low,
+}
+
+/// The severity of a rule which is not a security rule, as shown by GitHub
+/// code scanning.
+#[derive(
+  Display,
+  Debug,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  Hash,
+  EnumString,
+  Serialize,
+  Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum ProblemSeverity {
+  Error,
+  Warning,
+  Recommendation,
+}
+
+impl From<&sarif::ResultLevel> for ProblemSeverity {
+  fn from(level: &sarif::ResultLevel) -> Self {
+    match level {
+      sarif::ResultLevel::Error => ProblemSeverity::Error,
+      sarif::ResultLevel::Warning => ProblemSeverity::Warning,
+      _ => ProblemSeverity::Recommendation,
+    }
+  }
+}
+
+/// The properties of a rule which GitHub code scanning reads, besides its
+/// tags.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GithubRuleProperties {
+  /// How often the rule reports false positives
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub precision: Option<Precision>,
+  /// The severity of the rule, if it is not a security rule
+  #[serde(
+    rename = "problem.severity",
+    skip_serializing_if = "Option::is_none"
+  )]
+  pub problem_severity: Option<ProblemSeverity>,
+  /// The severity of a security rule, from 0.0 to 10.0, which GitHub maps to
+  /// low (up to 3.9), medium, high (from 7.0) and critical (from 9.0). It is
+  /// written as a string, but may be read from a number.
+  #[serde(
+    rename = "security-severity",
+    default,
+    skip_serializing_if = "Option::is_none",
+    with = "security_severity"
+  )]
+  pub security_severity: Option<f64>,
+}
+
+// security-severity is a number written as a string, in the shortest form
+// which reads back as the same number
+mod security_severity {
+  use serde::{Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(
+    value: &Option<f64>,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    match value {
+      Some(value) => serializer.serialize_str(&value.to_string()),
+      None => serializer.serialize_none(),
+    }
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Option<f64>, D::Error> {
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+      Some(serde_json::Value::String(value)) => {
+        value.parse().map(Some).map_err(serde::de::Error::custom)
+      }
+      Some(serde_json::Value::Number(value)) => Ok(value.as_f64()),
+      Some(value) => Err(serde::de::Error::custom(format!(
+        "invalid security-severity {}",
+        value
+      ))),
+      None => Ok(None),
+    }
+  }
+}
@@ -0,0 +1,533 @@
+//! Filtering of the results of SARIF logs.
+//!
+//! A [Query] selects results by their resolved level, `kind`, rule id, tool,
+//! artifact URI, `baselineState`, suppression and property bag tags. A result
+//! matches a filter if it matches any of its values, and matches the query if
+//! it matches every filter which is set; an empty query matches everything.
+//!
+//! Rule ids and artifact URIs are matched with globs, where `*` matches any
+//! characters except `/`, `**` matches any characters including `/` and `?`
+//! matches a single character except `/`. URIs are matched as written in the
+//! log, ex. `src/**/*.rs`.
+//!
+//! [Query::results] iterates over the matching results of a log, paired with
+//! the run they belong to, and [Query::prune] copies a log keeping only the
+//! matching results, and the rules and artifacts they refer to.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::query::Query;
+//! use serde_sarif::sarif::{ResultLevel, Sarif};
+//!
+//! let sarif: Sarif = serde_json::from_value(serde_json::json!({
+//!   "version": "2.1.0",
+//!   "runs": [{
+//!     "tool": { "driver": {
+//!       "name": "clippy",
+//!       "rules": [
+//!         { "id": "clippy::needless_return" },
+//!         { "id": "clippy::absurd_extreme_comparisons",
+//!           "defaultConfiguration": { "level": "error" } }
+//!       ]
+//!     } },
+//!     "results": [
+//!       { "ruleIndex": 0, "message": { "text": "unneeded `return` statement" } },
+//!       { "ruleIndex": 1, "message": { "text": "this comparison is always true" } }
+//!     ]
+//!   }]
+//! }))
+//! .unwrap();
+//!
+//! let query = Query::new().level(ResultLevel::Error).rule("clippy::*");
+//! assert_eq!(query.results(&sarif).count(), 1);
+//!
+//! let pruned = query.prune(&sarif);
+//! let run = &pruned.runs[0];
+//! assert_eq!(run.tool.driver.rules.as_ref().unwrap().len(), 1);
+//! assert_eq!(run.results.as_ref().unwrap()[0].rule_index, Some(0));
+//! ```
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+use regex::Regex;
+
+use crate::sarif;
+use crate::visit::{walk_artifact_mut, walk_invocation_mut, Visit, VisitMut};
+
+/// Selects results of SARIF logs.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+  levels: Vec<sarif::ResultLevel>,
+  kinds: Vec<sarif::ResultKind>,
+  rules: Vec<Regex>,
+  tools: Vec<String>,
+  paths: Vec<Regex>,
+  baseline_states: Vec<sarif::ResultBaselineState>,
+  suppressed: Option<bool>,
+  tags: Vec<String>,
+}
+
+impl Query {
+  /// Creates a query which matches every result
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Matches results of the given level, as resolved by [resolve_level]
+  ///
+  /// # Arguments
+  ///
+  /// * `level` - The level
+  pub fn level(mut self, level: sarif::ResultLevel) -> Self {
+    self.levels.push(level);
+    self
+  }
+
+  /// Matches results of the given kind, which is `fail` if absent
+  ///
+  /// # Arguments
+  ///
+  /// * `kind` - The kind
+  pub fn kind(mut self, kind: sarif::ResultKind) -> Self {
+    self.kinds.push(kind);
+    self
+  }
+
+  /// Matches results whose rule id matches a glob
+  ///
+  /// # Arguments
+  ///
+  /// * `pattern` - The glob, ex. `clippy::*`
+  pub fn rule(mut self, pattern: &str) -> Self {
+    self.rules.push(glob(pattern));
+    self
+  }
+
+  /// Matches results of runs of the given tool
+  ///
+  /// # Arguments
+  ///
+  /// * `name` - The name of the tool's driver, ex. `clippy`
+  pub fn tool<S: Into<String>>(mut self, name: S) -> Self {
+    self.tools.push(name.into());
+    self
+  }
+
+  /// Matches results with a location whose artifact URI matches a glob
+  ///
+  /// # Arguments
+  ///
+  /// * `pattern` - The glob, ex. `src/**/*.rs`
+  pub fn path(mut self, pattern: &str) -> Self {
+    self.paths.push(glob(pattern));
+    self
+  }
+
+  /// Matches results of the given baseline state
+  ///
+  /// # Arguments
+  ///
+  /// * `state` - The baseline state
+  pub fn baseline_state(mut self, state: sarif::ResultBaselineState) -> Self {
+    self.baseline_states.push(state);
+    self
+  }
+
+  /// Matches results which are, or are not, suppressed, see [is_suppressed]
+  ///
+  /// # Arguments
+  ///
+  /// * `suppressed` - Whether to match suppressed results
+  pub fn suppressed(mut self, suppressed: bool) -> Self {
+    self.suppressed = Some(suppressed);
+    self
+  }
+
+  /// Matches results tagged with `tag`, either in their own property bag or
+  /// in that of their rule
+  ///
+  /// # Arguments
+  ///
+  /// * `tag` - The tag
+  pub fn tag<S: Into<String>>(mut self, tag: S) -> Self {
+    self.tags.push(tag.into());
+    self
+  }
+
+  /// Returns whether a result of `run` matches the query
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run the result belongs to
+  /// * `result` - The result
+  pub fn matches(&self, run: &sarif::Run, result: &sarif::Result) -> bool {
+    let rule = run.resolve_rule(result);
+    let tags = || {
+      let result_tags =
+        result.properties.as_ref().and_then(|p| p.tags.as_ref());
+      let rule_tags = rule
+        .and_then(|rule| rule.properties.as_ref())
+        .and_then(|p| p.tags.as_ref());
+      result_tags.into_iter().chain(rule_tags).flatten()
+    };
+    (self.levels.is_empty()
+      || self.levels.contains(&resolve_level(run, result)))
+      && (self.kinds.is_empty()
+        || self
+          .kinds
+          .contains(result.kind.as_ref().unwrap_or(&sarif::ResultKind::Fail)))
+      && (self.rules.is_empty()
+        || result
+          .rule_id
+          .as_deref()
+          .or_else(|| rule.map(|rule| rule.id.as_str()))
+          .is_some_and(|id| self.rules.iter().any(|rule| rule.is_match(id))))
+      && (self.tools.is_empty() || self.tools.contains(&run.tool.driver.name))
+      && (self.paths.is_empty()
+        || uris(run, result)
+          .any(|uri| self.paths.iter().any(|path| path.is_match(uri))))
+      && (self.baseline_states.is_empty()
+        || result
+          .baseline_state
+          .as_ref()
+          .is_some_and(|state| self.baseline_states.contains(state)))
+      && self
+        .suppressed
+        .is_none_or(|suppressed| is_suppressed(result) == suppressed)
+      && (self.tags.is_empty() || tags().any(|tag| self.tags.contains(tag)))
+  }
+
+  /// Returns the results of `sarif` which match the query, paired with the
+  /// run they belong to
+  ///
+  /// # Arguments
+  ///
+  /// * `sarif` - The SARIF log
+  pub fn results<'a>(
+    &'a self,
+    sarif: &'a sarif::Sarif,
+  ) -> impl Iterator<Item = (&'a sarif::Run, &'a sarif::Result)> + 'a {
+    sarif.runs.iter().flat_map(move |run| {
+      run
+        .results
+        .iter()
+        .flatten()
+        .filter(move |result| self.matches(run, result))
+        .map(move |result| (run, result))
+    })
+  }
+
+  /// Returns a copy of `sarif` with only the results which match the query.
+  /// Rules and artifacts which are no longer referred to are removed, and
+  /// the indices referring to the remaining ones updated.
+  ///
+  /// # Arguments
+  ///
+  /// * `sarif` - The SARIF log
+  pub fn prune(&self, sarif: &sarif::Sarif) -> sarif::Sarif {
+    let mut sarif = sarif.clone();
+    sarif.runs.iter_mut().for_each(|run| {
+      let results = run.results.take().map(|results| {
+        results
+          .into_iter()
+          .filter(|result| self.matches(run, result))
+          .collect()
+      });
+      run.results = results;
+      prune_rules(run);
+      prune_artifacts(run);
+    });
+    sarif
+  }
+}
+
+// Converts a glob to an anchored regex
+fn glob(pattern: &str) -> Regex {
+  let mut regex = String::from("^");
+  let mut chars = pattern.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '*' if chars.peek() == Some(&'*') => {
+        chars.next();
+        // `**/` also matches no directories at all
+        if chars.peek() == Some(&'/') {
+          chars.next();
+          regex.push_str("(?:.*/)?");
+        } else {
+          regex.push_str(".*");
+        }
+      }
+      '*' => regex.push_str("[^/]*"),
+      '?' => regex.push_str("[^/]"),
+      c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+    }
+  }
+  regex.push('$');
+  Regex::new(&regex).expect("escaped globs are valid regexes")
+}
+
+// Whether the rule of a result is one of the driver's, rather than of an
+// extension
+fn refers_to_driver(result: &sarif::Result) -> bool {
+  result
+    .rule
+    .as_ref()
+    .and_then(|rule| rule.tool_component.as_ref())
+    .is_none()
+}
+
+fn get<T>(items: Option<&Vec<T>>, index: Option<i64>) -> Option<&T> {
+  items?.get(usize::try_from(index?).ok()?)
+}
+
+// Returns the artifact URIs of the locations of a result
+fn uris<'a>(
+  run: &'a sarif::Run,
+  result: &'a sarif::Result,
+) -> impl Iterator<Item = &'a str> {
+  result
+    .locations
+    .iter()
+    .flatten()
+    .filter_map(|location| location.physical_location.as_ref())
+    .filter_map(|physical_location| {
+      physical_location.artifact_location.as_ref()
+    })
+    .filter_map(move |artifact_location| {
+      artifact_location.uri.as_deref().or_else(|| {
+        get(run.artifacts.as_ref(), artifact_location.index)?
+          .location
+          .as_ref()?
+          .uri
+          .as_deref()
+      })
+    })
+}
+
+/// Returns the level of a result as determined by §3.27.10 of the SARIF
+/// specification: results whose `kind` is other than `fail` have the level
+/// `none`, otherwise the level is the result's own, that of the rule
+/// configuration override of the invocation which produced it, or the
+/// default level of its rule, in that order, and `warning` if none of these
+/// are present.
+///
+/// # Arguments
+///
+/// * `run` - The run the result belongs to
+/// * `result` - The result
+pub fn resolve_level(
+  run: &sarif::Run,
+  result: &sarif::Result,
+) -> sarif::ResultLevel {
+  if result
+    .kind
+    .as_ref()
+    .is_some_and(|kind| kind != &sarif::ResultKind::Fail)
+  {
+    return sarif::ResultLevel::None;
+  }
+  if let Some(level) = result.level.as_ref() {
+    return level.clone();
+  }
+  let descriptor = match run.resolve_rule(result) {
+    Some(descriptor) => descriptor,
+    None => return sarif::ResultLevel::Warning,
+  };
+  let invocation = get(
+    run.invocations.as_ref(),
+    result.provenance.as_ref().and_then(|p| p.invocation_index),
+  );
+  invocation
+    .and_then(|invocation| invocation.rule_configuration_overrides.as_ref())
+    .and_then(|overrides| {
+      overrides.iter().find(|o| {
+        run
+          .resolve_descriptor(&o.descriptor, false)
+          .is_some_and(|d| std::ptr::eq(d, descriptor))
+      })
+    })
+    .and_then(|o| o.configuration.level.as_ref())
+    .or_else(|| descriptor.default_configuration.as_ref()?.level.as_ref())
+    .map_or(sarif::ResultLevel::Warning, sarif::ResultLevel::from)
+}
+
+/// Returns whether a result is suppressed, which it is if it has a
+/// suppression which is accepted (the default status) and none which is
+/// under review or rejected
+///
+/// # Arguments
+///
+/// * `result` - The result
+pub fn is_suppressed(result: &sarif::Result) -> bool {
+  let statuses = || {
+    result
+      .suppressions
+      .iter()
+      .flatten()
+      .map(|suppression| suppression.status.as_ref())
+  };
+  statuses().any(|status| {
+    status.is_none_or(|status| status == &sarif::SupressionStatus::Accepted)
+  }) && !statuses().any(|status| {
+    matches!(
+      status,
+      Some(sarif::SupressionStatus::UnderReview)
+        | Some(sarif::SupressionStatus::Rejected)
+    )
+  })
+}
+
+// Maps the old indices of kept items to their new ones, and the indices of
+// removed items to None
+fn remap(index: &mut Option<i64>, mapping: &[Option<i64>]) {
+  *index = index
+    .and_then(|i| usize::try_from(i).ok())
+    .and_then(|i| mapping.get(i).copied().flatten());
+}
+
+fn mapping(len: usize, kept: &BTreeSet<usize>) -> Vec<Option<i64>> {
+  let mut next = 0;
+  (0..len)
+    .map(|i| {
+      kept.contains(&i).then(|| {
+        next += 1;
+        next - 1
+      })
+    })
+    .collect()
+}
+
+fn retain<T>(items: Vec<T>, kept: &BTreeSet<usize>) -> Vec<T> {
+  items
+    .into_iter()
+    .enumerate()
+    .filter(|(i, _)| kept.contains(i))
+    .map(|(_, item)| item)
+    .collect()
+}
+
+struct RemapRules<'a>(&'a [Option<i64>]);
+
+impl VisitMut for RemapRules<'_> {
+  fn visit_result_mut(&mut self, result: &mut sarif::Result) {
+    if refers_to_driver(result) {
+      remap(&mut result.rule_index, self.0);
+      if let Some(rule) = result.rule.as_mut() {
+        remap(&mut rule.index, self.0);
+      }
+    }
+  }
+
+  fn visit_invocation_mut(&mut self, invocation: &mut sarif::Invocation) {
+    invocation
+      .rule_configuration_overrides
+      .iter_mut()
+      .flatten()
+      .filter(|o| o.descriptor.tool_component.is_none())
+      .for_each(|o| remap(&mut o.descriptor.index, self.0));
+    walk_invocation_mut(self, invocation)
+  }
+
+  fn visit_notification_mut(&mut self, notification: &mut sarif::Notification) {
+    if let Some(rule) = notification
+      .associated_rule
+      .as_mut()
+      .filter(|rule| rule.tool_component.is_none())
+    {
+      remap(&mut rule.index, self.0);
+    }
+  }
+}
+
+// Returns the rules which the results, the rule configuration overrides of
+// the invocations and the rules associated with notifications refer to
+fn referenced_rules(run: &sarif::Run) -> Vec<&sarif::ReportingDescriptor> {
+  let results = run
+    .results
+    .iter()
+    .flatten()
+    .filter_map(|result| run.resolve_rule(result));
+  let invocations = || run.invocations.iter().flatten();
+  let overrides = invocations()
+    .flat_map(|invocation| invocation.rule_configuration_overrides.iter())
+    .flatten()
+    .filter_map(|o| run.resolve_descriptor(&o.descriptor, false));
+  let notifications = invocations()
+    .flat_map(|invocation| {
+      let execution = invocation.tool_execution_notifications.iter();
+      execution.chain(invocation.tool_configuration_notifications.iter())
+    })
+    .flatten()
+    .filter_map(|notification| run.resolve_associated_rule(notification));
+  results.chain(overrides).chain(notifications).collect()
+}
+
+// Removes the driver rules which nothing refers to
+fn prune_rules(run: &mut sarif::Run) {
+  let kept: BTreeSet<usize> = match run.tool.driver.rules.as_ref() {
+    Some(rules) => referenced_rules(run)
+      .into_iter()
+      .filter_map(|rule| rules.iter().position(|r| std::ptr::eq(r, rule)))
+      .collect(),
+    None => return,
+  };
+  let rules = run.tool.driver.rules.take().unwrap_or_default();
+  let mapping = mapping(rules.len(), &kept);
+  run.tool.driver.rules = Some(retain(rules, &kept));
+  RemapRules(&mapping).visit_run_mut(run);
+}
+
+#[derive(Default)]
+struct ArtifactIndices(BTreeSet<usize>);
+
+impl Visit for ArtifactIndices {
+  fn visit_artifact_location(&mut self, location: &sarif::ArtifactLocation) {
+    if let Some(index) = location.index.and_then(|i| usize::try_from(i).ok()) {
+      self.0.insert(index);
+    }
+  }
+}
+
+struct RemapArtifacts<'a>(&'a [Option<i64>]);
+
+impl VisitMut for RemapArtifacts<'_> {
+  fn visit_artifact_location_mut(
+    &mut self,
+    location: &mut sarif::ArtifactLocation,
+  ) {
+    remap(&mut location.index, self.0);
+  }
+
+  fn visit_artifact_mut(&mut self, artifact: &mut sarif::Artifact) {
+    remap(&mut artifact.parent_index, self.0);
+    walk_artifact_mut(self, artifact)
+  }
+}
+
+// Removes the artifacts which nothing outside of the artifacts refers to,
+// other than the parents of those which remain
+fn prune_artifacts(run: &mut sarif::Run) {
+  let artifacts = match run.artifacts.take() {
+    Some(artifacts) => artifacts,
+    None => return,
+  };
+  let mut indices = ArtifactIndices::default();
+  indices.visit_run(run);
+  let mut kept = BTreeSet::new();
+  indices.0.into_iter().for_each(|mut index| {
+    while index < artifacts.len() && kept.insert(index) {
+      match artifacts[index]
+        .parent_index
+        .and_then(|i| usize::try_from(i).ok())
+      {
+        Some(parent) => index = parent,
+        None => break,
+      }
+    }
+  });
+  let mapping = mapping(artifacts.len(), &kept);
+  run.artifacts = Some(retain(artifacts, &kept));
+  RemapArtifacts(&mapping).visit_run_mut(run);
+}
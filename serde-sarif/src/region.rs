@@ -0,0 +1,323 @@
+//! Conversion between the coordinate systems of regions.
+//!
+//! A SARIF `region` may locate text by line and column, by character offset
+//! and length, or by byte offset and length, and a producer is free to give
+//! any combination of them. Columns and character offsets are counted in the
+//! unit given by the run's `columnKind`: UTF-16 code units or Unicode code
+//! points, which differ for characters outside the basic multilingual plane.
+//! Tools themselves often count bytes.
+//!
+//! [SourceText] converts between these given the contents of the file, and
+//! [SourceText::normalize] fills in every property of a partially specified
+//! region, ex. the `endLine` which defaults to the `startLine`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::region::{ColumnUnit, SourceText};
+//! use serde_sarif::sarif::RegionBuilder;
+//!
+//! let text = SourceText::new("let 🦀 = 1;\nlet x = 2;\n");
+//! let region = RegionBuilder::default()
+//!   .start_line(1)
+//!   .start_column(5)
+//!   .end_column(7)
+//!   .build()
+//!   .unwrap();
+//!
+//! // the crab is two UTF-16 code units, but four bytes long
+//! assert_eq!(text.byte_range(&region, ColumnUnit::Utf16CodeUnits), Some(4..8));
+//!
+//! let region = text
+//!   .normalize(&region, ColumnUnit::Utf16CodeUnits)
+//!   .unwrap();
+//! assert_eq!(region.end_line, Some(1));
+//! assert_eq!((region.char_offset, region.char_length), (Some(4), Some(2)));
+//! assert_eq!((region.byte_offset, region.byte_length), (Some(4), Some(4)));
+//! ```
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use crate::sarif;
+
+/// The unit columns and character offsets are counted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnUnit {
+  /// Bytes of the UTF-8 encoded text, as reported by many tools.
+  Bytes,
+  /// UTF-16 code units (`utf16CodeUnits`).
+  Utf16CodeUnits,
+  /// Unicode code points (`unicodeCodePoints`).
+  UnicodeCodePoints,
+}
+
+impl ColumnUnit {
+  /// Returns the unit of the columns of `run`, which are UTF-16 code units
+  /// if its `columnKind` is absent
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run the regions belong to
+  pub fn of(run: &sarif::Run) -> Self {
+    run
+      .column_kind
+      .as_ref()
+      .map_or(ColumnUnit::Utf16CodeUnits, ColumnUnit::from)
+  }
+
+  fn width(self, c: char) -> usize {
+    match self {
+      ColumnUnit::Bytes => c.len_utf8(),
+      ColumnUnit::Utf16CodeUnits => c.len_utf16(),
+      ColumnUnit::UnicodeCodePoints => 1,
+    }
+  }
+}
+
+impl From<&sarif::ResultColumnKind> for ColumnUnit {
+  fn from(column_kind: &sarif::ResultColumnKind) -> Self {
+    match column_kind {
+      sarif::ResultColumnKind::UnicodeCodePoints => {
+        ColumnUnit::UnicodeCodePoints
+      }
+      _ => ColumnUnit::Utf16CodeUnits,
+    }
+  }
+}
+
+// Returns the byte offset of the start of each line
+fn line_starts(contents: &str) -> Vec<usize> {
+  std::iter::once(0)
+    .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+    .collect()
+}
+
+/// The contents of a text file, indexed by line.
+pub struct SourceText<'a> {
+  contents: Cow<'a, str>,
+  // byte offset of the start of each line
+  line_starts: Vec<usize>,
+}
+
+impl<'a> SourceText<'a> {
+  /// Indexes the lines of `contents`
+  ///
+  /// # Arguments
+  ///
+  /// * `contents` - The contents of the file
+  pub fn new(contents: &'a str) -> Self {
+    SourceText {
+      line_starts: line_starts(contents),
+      contents: Cow::Borrowed(contents),
+    }
+  }
+
+  /// Indexes the lines of `contents`, which the index keeps, ex. to cache the
+  /// index of a file
+  ///
+  /// # Arguments
+  ///
+  /// * `contents` - The contents of the file
+  pub fn owned(contents: String) -> SourceText<'static> {
+    SourceText {
+      line_starts: line_starts(&contents),
+      contents: Cow::Owned(contents),
+    }
+  }
+
+  // Returns the byte range of a (1-based) line, excluding its newline
+  fn line(&self, line: i64) -> Option<Range<usize>> {
+    let index = usize::try_from(line).ok()?.checked_sub(1)?;
+    let start = *self.line_starts.get(index)?;
+    let end = self
+      .line_starts
+      .get(index + 1)
+      .map_or(self.contents.len(), |next| next - 1);
+    let end = if self.contents[start..end].ends_with('\r') {
+      end - 1
+    } else {
+      end
+    };
+    Some(start..end)
+  }
+
+  /// Returns the byte offset of a (1-based) line and column. Columns past
+  /// the end of the line are clamped to it.
+  ///
+  /// # Arguments
+  ///
+  /// * `line` - The line number
+  /// * `column` - The column number, counted in `unit`
+  /// * `unit` - The unit of the column
+  pub fn byte_offset(
+    &self,
+    line: i64,
+    column: i64,
+    unit: ColumnUnit,
+  ) -> Option<usize> {
+    let range = self.line(line)?;
+    let target = usize::try_from(column.max(1) - 1).ok()?;
+    let mut width = 0;
+    let offset = self.contents[range.clone()]
+      .char_indices()
+      .find(|(_, c)| {
+        width += unit.width(*c);
+        width > target
+      })
+      .map_or(range.end, |(i, _)| range.start + i);
+    Some(offset)
+  }
+
+  /// Returns the (1-based) line and column of a byte offset
+  ///
+  /// # Arguments
+  ///
+  /// * `offset` - The byte offset
+  /// * `unit` - The unit to count the column in
+  pub fn position(&self, offset: usize, unit: ColumnUnit) -> (i64, i64) {
+    let offset = self.floor(offset);
+    let index = self.line_starts.partition_point(|start| *start <= offset) - 1;
+    let start = self.line_starts[index];
+    let column = self.width(start..offset, unit) + 1;
+    (index as i64 + 1, column as i64)
+  }
+
+  /// Converts the column of a (1-based) line from one unit to another
+  ///
+  /// # Arguments
+  ///
+  /// * `line` - The line number
+  /// * `column` - The column number, counted in `from`
+  /// * `from` - The unit of `column`
+  /// * `to` - The unit of the returned column
+  pub fn convert_column(
+    &self,
+    line: i64,
+    column: i64,
+    from: ColumnUnit,
+    to: ColumnUnit,
+  ) -> Option<i64> {
+    let offset = self.byte_offset(line, column, from)?;
+    Some(self.position(offset, to).1)
+  }
+
+  /// Returns the byte offset of a character offset
+  ///
+  /// # Arguments
+  ///
+  /// * `offset` - The character offset, counted in `unit`
+  /// * `unit` - The unit of the offset
+  pub fn byte_offset_of_char(
+    &self,
+    offset: i64,
+    unit: ColumnUnit,
+  ) -> Option<usize> {
+    let target = usize::try_from(offset).ok()?;
+    let mut width = 0;
+    Some(
+      self
+        .contents
+        .char_indices()
+        .find(|(_, c)| {
+          width += unit.width(*c);
+          width > target
+        })
+        .map_or(self.contents.len(), |(i, _)| i),
+    )
+  }
+
+  /// Returns the character offset of a byte offset
+  ///
+  /// # Arguments
+  ///
+  /// * `offset` - The byte offset
+  /// * `unit` - The unit to count characters in
+  pub fn char_offset(&self, offset: usize, unit: ColumnUnit) -> i64 {
+    self.width(0..self.floor(offset), unit) as i64
+  }
+
+  // Rounds a byte offset down to a character boundary within the contents
+  fn floor(&self, offset: usize) -> usize {
+    let mut offset = offset.min(self.contents.len());
+    while !self.contents.is_char_boundary(offset) {
+      offset -= 1;
+    }
+    offset
+  }
+
+  fn width(&self, range: Range<usize>, unit: ColumnUnit) -> usize {
+    match unit {
+      ColumnUnit::Bytes => range.len(),
+      _ => self.contents[range].chars().map(|c| unit.width(c)).sum(),
+    }
+  }
+
+  /// Returns the byte range a region covers, using its byte offset, its
+  /// lines and columns or its character offset, in that order of preference
+  ///
+  /// # Arguments
+  ///
+  /// * `region` - The region
+  /// * `unit` - The unit the region's columns and character offsets are
+  ///   counted in, usually [ColumnUnit::of] the run
+  pub fn byte_range(
+    &self,
+    region: &sarif::Region,
+    unit: ColumnUnit,
+  ) -> Option<Range<usize>> {
+    let to_usize = |value: i64| usize::try_from(value).ok();
+    let (start, end) = if let Some(offset) = region.byte_offset {
+      let start = self.floor(to_usize(offset)?);
+      let length = region.byte_length.and_then(to_usize).unwrap_or(0);
+      (start, self.floor(start + length))
+    } else if let Some(start_line) = region.start_line {
+      let start_column = region.start_column.unwrap_or(1);
+      let start = self.byte_offset(start_line, start_column, unit)?;
+      let end_line = region.end_line.unwrap_or(start_line);
+      // without an end column, the region extends to the end of the line
+      let end = match region.end_column {
+        Some(end_column) => self.byte_offset(end_line, end_column, unit)?,
+        None => self.line(end_line)?.end,
+      };
+      (start, end)
+    } else if let Some(offset) = region.char_offset {
+      let start = self.byte_offset_of_char(offset, unit)?;
+      let length = region.char_length.unwrap_or(0);
+      (start, self.byte_offset_of_char(offset + length, unit)?)
+    } else {
+      return None;
+    };
+    Some(start..end.max(start))
+  }
+
+  /// Returns a copy of `region` with its lines and columns, character offset
+  /// and length, and byte offset and length all specified
+  ///
+  /// # Arguments
+  ///
+  /// * `region` - The region
+  /// * `unit` - The unit the region's columns and character offsets are
+  ///   counted in, usually [ColumnUnit::of] the run
+  pub fn normalize(
+    &self,
+    region: &sarif::Region,
+    unit: ColumnUnit,
+  ) -> Option<sarif::Region> {
+    let range = self.byte_range(region, unit)?;
+    let (start_line, start_column) = self.position(range.start, unit);
+    let (end_line, end_column) = self.position(range.end, unit);
+    let char_offset = self.char_offset(range.start, unit);
+    let mut region = region.clone();
+    region.start_line = Some(start_line);
+    region.start_column = Some(start_column);
+    region.end_line = Some(end_line);
+    region.end_column = Some(end_column);
+    region.char_offset = Some(char_offset);
+    region.char_length = Some(self.char_offset(range.end, unit) - char_offset);
+    region.byte_offset = Some(range.start as i64);
+    region.byte_length = Some(range.len() as i64);
+    Some(region)
+  }
+}
@@ -1,18 +1,17 @@
-#![allow(clippy::derive_partial_eq_without_eq)]
-
 use std::convert::TryFrom;
+use std::str::FromStr;
 use strum_macros::Display;
 use strum_macros::EnumString;
-use thiserror::Error;
 
 include!(concat!(env!("OUT_DIR"), "/sarif.rs"));
 
 #[doc = "The SARIF format version of this log file."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum Version {
   #[strum(serialize = "2.1.0")]
   V2_1_0,
+  #[strum(default)]
+  Unknown(String),
 }
 
 // todo: should be generated / synced with schema.json
@@ -20,8 +19,7 @@ pub static SCHEMA_URL: &str =
   "https://schemastore.azurewebsites.net/schemas/json/sarif-2.1.0-rtm.5.json";
 
 #[doc = "The role or roles played by the artifact in the analysis."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ArtifactRoles {
   #[strum(serialize = "analysisTarget")]
   AnalysisTarget,
@@ -69,19 +67,21 @@ pub enum ArtifactRoles {
   ToolSpecifiedConfiguration,
   #[strum(serialize = "debugOutputFile")]
   DebugOutputFile,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "The SARIF format version of this external properties object."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ExternalPropertiesVersion {
   #[strum(serialize = "2.1.0")]
   V2_1_0,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "A value specifying the severity level of the result."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum NotificationLevel {
   #[strum(serialize = "none")]
   None,
@@ -91,11 +91,12 @@ pub enum NotificationLevel {
   Warning,
   #[strum(serialize = "error")]
   Error,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "Specifies the failure level for the report."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ReportingConfigurationLevel {
   #[strum(serialize = "none")]
   None,
@@ -105,11 +106,12 @@ pub enum ReportingConfigurationLevel {
   Warning,
   #[strum(serialize = "error")]
   Error,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "A value that categorizes results by evaluation state."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ResultKind {
   #[strum(serialize = "notApplicable")]
   NotApplicable,
@@ -123,11 +125,12 @@ pub enum ResultKind {
   Open,
   #[strum(serialize = "informational")]
   Informational,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "A value specifying the severity level of the result."]
-#[derive(Clone, Copy, Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ResultLevel {
   #[strum(serialize = "none")]
   None,
@@ -137,11 +140,12 @@ pub enum ResultLevel {
   Warning,
   #[strum(serialize = "error")]
   Error,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "The state of a result relative to a baseline of a previous run."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ResultBaselineState {
   #[strum(serialize = "new")]
   New,
@@ -151,86 +155,124 @@ pub enum ResultBaselineState {
   Updated,
   #[strum(serialize = "absent")]
   Absent,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "Specifies the unit in which the tool measures columns."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ResultColumnKind {
   #[strum(serialize = "utf16CodeUnits")]
   Utf16CodeUnits,
   #[strum(serialize = "unicodeCodePoints")]
   UnicodeCodePoints,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "A string that indicates where the suppression is persisted."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum SupressionKind {
   #[strum(serialize = "inSource")]
   InSource,
   #[strum(serialize = "external")]
   External,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "A string that indicates the review status of the suppression."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum SupressionStatus {
   #[strum(serialize = "accepted")]
   Accepted,
   #[strum(serialize = "underReview")]
   UnderReview,
+  #[strum(serialize = "rejected")]
+  Rejected,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "Specifies the importance of this location in understanding the code flow in which it occurs. The order from most to least important is \"essential\", \"important\", \"unimportant\". Default: \"important\"."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ThreadFlowLocationImportance {
   #[strum(serialize = "important")]
   Important,
   #[strum(serialize = "essential")]
   Essential,
+  #[strum(serialize = "unimportant")]
+  Unimportant,
+  #[strum(default)]
+  Unknown(String),
 }
 
 #[doc = "The kinds of data contained in this object."]
-#[derive(Display, Debug, Serialize, Deserialize, EnumString)]
-#[serde(untagged)]
+#[derive(Display, Debug, Clone, PartialEq, Eq, Hash, EnumString)]
 pub enum ToolComponentContents {
   #[strum(serialize = "localizedData")]
   LocalizedData,
   #[strum(serialize = "nonLocalizedData")]
   NonLocalizedData,
+  #[strum(default)]
+  Unknown(String),
+}
+
+// The enums above are (de)serialized through their strum string form rather
+// than derived, so that values outside of the schema land in the `Unknown`
+// variant and are written back out unchanged.
+macro_rules! serde_via_strum {
+  ($($ty:ident),* $(,)?) => {
+    $(
+      impl Serialize for $ty {
+        fn serialize<S: serde::Serializer>(
+          &self,
+          serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+          serializer.collect_str(self)
+        }
+      }
+
+      impl<'de> Deserialize<'de> for $ty {
+        fn deserialize<D: serde::Deserializer<'de>>(
+          deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+          let value = String::deserialize(deserializer)?;
+          $ty::from_str(&value).map_err(serde::de::Error::custom)
+        }
+      }
+    )*
+  };
 }
 
-// todo: implement for other error types, probably convert to procmacro
-#[derive(Error, Debug)]
-pub enum BuilderError {
-  #[error(transparent)]
-  LocationBuilderError {
-    #[from]
-    source: LocationBuilderError,
-  },
-  #[error(transparent)]
-  PhysicalLocationBuilderError {
-    #[from]
-    source: PhysicalLocationBuilderError,
-  },
-  #[error(transparent)]
-  RegionBuilderError {
-    #[from]
-    source: RegionBuilderError,
-  },
-  #[error(transparent)]
-  ArtifactLocationBuilderError {
-    #[from]
-    source: ArtifactLocationBuilderError,
-  },
-  #[error(transparent)]
-  ResultBuilderError {
-    #[from]
-    source: ResultBuilderError,
-  },
+serde_via_strum!(
+  Version,
+  ArtifactRoles,
+  ExternalPropertiesVersion,
+  NotificationLevel,
+  ReportingConfigurationLevel,
+  ResultKind,
+  ResultLevel,
+  ResultBaselineState,
+  ResultColumnKind,
+  SupressionKind,
+  SupressionStatus,
+  ThreadFlowLocationImportance,
+  ToolComponentContents,
+);
+
+impl From<&ReportingConfigurationLevel> for ResultLevel {
+  fn from(level: &ReportingConfigurationLevel) -> Self {
+    match level {
+      ReportingConfigurationLevel::None => ResultLevel::None,
+      ReportingConfigurationLevel::Note => ResultLevel::Note,
+      ReportingConfigurationLevel::Warning => ResultLevel::Warning,
+      ReportingConfigurationLevel::Error => ResultLevel::Error,
+      ReportingConfigurationLevel::Unknown(level) => {
+        ResultLevel::Unknown(level.clone())
+      }
+    }
+  }
 }
 
 // Note that due to the blanket implementation in core, TryFrom<AsRef<String>>
@@ -271,3 +313,142 @@ impl TryFrom<ToolComponent> for Tool {
     ToolBuilder::default().driver(tool_component).build()
   }
 }
+
+impl Run {
+  /// Returns the tool component a reference refers to, or the driver if
+  /// there is no reference. Components are looked up by their index into
+  /// the tool's extensions, else by guid, else by name.
+  ///
+  /// # Arguments
+  ///
+  /// * `reference` - The tool component reference, ex. of a rule
+  pub fn resolve_tool_component(
+    &self,
+    reference: Option<&ToolComponentReference>,
+  ) -> Option<&ToolComponent> {
+    let reference = match reference {
+      Some(reference) => reference,
+      None => return Some(&self.tool.driver),
+    };
+    let extensions = self.tool.extensions.as_deref().unwrap_or_default();
+    if let Some(index) = is_set(reference.index) {
+      return usize::try_from(index).ok().and_then(|i| extensions.get(i));
+    }
+    let mut components = std::iter::once(&self.tool.driver).chain(extensions);
+    match (reference.guid.as_ref(), reference.name.as_ref()) {
+      (Some(guid), _) => components.find(|c| c.guid.as_ref() == Some(guid)),
+      (None, Some(name)) => components.find(|c| &c.name == name),
+      (None, None) => None,
+    }
+  }
+
+  /// Returns the rule of a result, which is defined by the tool component
+  /// its `rule` refers to, or else by the driver. The rule is looked up by
+  /// `ruleIndex` (or `rule.index`), else by `rule.guid`, else by `ruleId`
+  /// (or `rule.id`). Hierarchical rule ids, ex. `CA2101/md5`, resolve to
+  /// the nearest rule they are a sub-id of if there is no rule for the full
+  /// id. A negative index, ex. the default of -1, counts as not set.
+  ///
+  /// # Arguments
+  ///
+  /// * `result` - A result of the run
+  pub fn resolve_rule(&self, result: &Result) -> Option<&ReportingDescriptor> {
+    let reference = result.rule.as_ref();
+    let component = self.resolve_tool_component(
+      reference.and_then(|reference| reference.tool_component.as_ref()),
+    )?;
+    find_descriptor(
+      component.rules.as_ref(),
+      is_set(result.rule_index).or_else(|| is_set(reference?.index)),
+      reference.and_then(|reference| reference.guid.as_deref()),
+      result
+        .rule_id
+        .as_deref()
+        .or_else(|| reference?.id.as_deref()),
+    )
+  }
+
+  /// Returns the descriptor a reference refers to, looking it up among the
+  /// notifications of its tool component if `notification` is set, and
+  /// among its rules otherwise
+  ///
+  /// # Arguments
+  ///
+  /// * `reference` - The descriptor reference
+  /// * `notification` - Whether the reference is to a notification
+  pub fn resolve_descriptor(
+    &self,
+    reference: &ReportingDescriptorReference,
+    notification: bool,
+  ) -> Option<&ReportingDescriptor> {
+    let component =
+      self.resolve_tool_component(reference.tool_component.as_ref())?;
+    let descriptors = if notification {
+      component.notifications.as_ref()
+    } else {
+      component.rules.as_ref()
+    };
+    find_descriptor(
+      descriptors,
+      reference.index,
+      reference.guid.as_deref(),
+      reference.id.as_deref(),
+    )
+  }
+
+  /// Returns the descriptor of a notification of the run
+  ///
+  /// # Arguments
+  ///
+  /// * `notification` - A notification of one of the run's invocations
+  pub fn resolve_notification(
+    &self,
+    notification: &Notification,
+  ) -> Option<&ReportingDescriptor> {
+    self.resolve_descriptor(notification.descriptor.as_ref()?, true)
+  }
+
+  /// Returns the rule a notification of the run is associated with
+  ///
+  /// # Arguments
+  ///
+  /// * `notification` - A notification of one of the run's invocations
+  pub fn resolve_associated_rule(
+    &self,
+    notification: &Notification,
+  ) -> Option<&ReportingDescriptor> {
+    self.resolve_descriptor(notification.associated_rule.as_ref()?, false)
+  }
+}
+
+pub(crate) fn find_descriptor<'a>(
+  descriptors: Option<&'a Vec<ReportingDescriptor>>,
+  index: Option<i64>,
+  guid: Option<&str>,
+  id: Option<&str>,
+) -> Option<&'a ReportingDescriptor> {
+  let descriptors = descriptors?;
+  if let Some(index) = is_set(index) {
+    return usize::try_from(index).ok().and_then(|i| descriptors.get(i));
+  }
+  if let Some(descriptor) = guid.and_then(|guid| {
+    descriptors
+      .iter()
+      .find(|descriptor| descriptor.guid.as_deref() == Some(guid))
+  }) {
+    return Some(descriptor);
+  }
+  let mut id = id?;
+  loop {
+    if let Some(descriptor) = descriptors.iter().find(|d| d.id == id) {
+      return Some(descriptor);
+    }
+    id = &id[..id.rfind('/')?];
+  }
+}
+
+// Returns an index unless it is negative; -1 is the schema's default and
+// means the index is not set
+fn is_set(index: Option<i64>) -> Option<i64> {
+  index.filter(|index| *index >= 0)
+}
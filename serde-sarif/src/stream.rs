@@ -0,0 +1,578 @@
+//! Incremental reading and writing of SARIF logs.
+//!
+//! Deserializing a whole [sarif::Sarif] requires holding every result in
+//! memory at once, which is prohibitive for logs of hundreds of megabytes.
+//! [ResultReader] instead walks `runs[*].results[*]`, deserializing a single
+//! [sarif::Result] at a time and handing it out together with the metadata
+//! (tool, rules, originalUriBaseIds, ...) of the run it belongs to.
+//! [SarifStreamWriter] is its counterpart, serializing results one at a time
+//! as they are produced.
+//!
+//! Since the run metadata may follow the results in the log (serializing a
+//! [sarif::Run] writes `results` before `tool`, and so does
+//! [SarifStreamWriter]), the reader makes two passes over the input: the
+//! first collects the metadata of every run while skipping over the results,
+//! the second yields the results. The input must therefore implement [Seek].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::sarif;
+//! use serde_sarif::stream::{ResultReader, SarifStreamWriter};
+//! use std::convert::TryFrom;
+//! use std::io::Cursor;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let mut writer = SarifStreamWriter::new(vec![])?;
+//! let mut run = writer.begin_run(
+//!   sarif::RunBuilder::default()
+//!     .tool(sarif::Tool::try_from(
+//!       sarif::ToolComponentBuilder::default().name("tool").build()?,
+//!     )?)
+//!     .build()?,
+//! )?;
+//! let rule_index = run.write_rule(
+//!   sarif::ReportingDescriptorBuilder::default().id("rule").build()?,
+//! );
+//! run.write_result(
+//!   &sarif::ResultBuilder::default()
+//!     .message(sarif::Message::try_from("message")?)
+//!     .rule_id("rule")
+//!     .rule_index(rule_index)
+//!     .build()?,
+//! )?;
+//! run.end()?;
+//! let log = writer.finish()?;
+//!
+//! for run_result in ResultReader::new(Cursor::new(log))? {
+//!   let run_result = run_result?;
+//!   assert_eq!(run_result.run.tool.driver.name, "tool");
+//!   assert_eq!(run_result.result.rule_id, Some("rule".into()));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use serde::de::{DeserializeOwned, Error as _, IgnoredAny};
+use serde::{Deserialize, Serialize};
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter};
+use serde_json::{Error, Map, Value};
+
+use crate::sarif;
+
+/// A result read by a [ResultReader].
+#[derive(Clone, Debug)]
+pub struct RunResult {
+  /// The index of the run the result belongs to in the log.
+  pub run_index: usize,
+  /// The run the result belongs to. Its `results` are always `None`, the
+  /// rest of the run, in particular the tool and its rules, is populated.
+  pub run: Arc<sarif::Run>,
+  /// The result itself.
+  pub result: sarif::Result,
+}
+
+/// An iterator over the results of a SARIF log which deserializes one result
+/// at a time.
+pub struct ResultReader<R> {
+  scanner: Scanner<R>,
+  runs: Vec<Arc<sarif::Run>>,
+  state: State,
+  // number of runs entered so far by the second pass
+  run_count: usize,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+  Start,
+  Log { first: bool },
+  Runs { first: bool },
+  Run { first: bool },
+  Results { first: bool },
+  Done,
+}
+
+impl<R: Read + Seek> ResultReader<R> {
+  /// Creates a reader over the SARIF log in `reader`, reading the metadata
+  /// of every run up front
+  ///
+  /// # Arguments
+  ///
+  /// * `reader` - A reader containing a SARIF log, positioned at its start
+  pub fn new(reader: R) -> Result<Self, Error> {
+    let mut scanner = Scanner {
+      reader: BufReader::new(reader),
+    };
+    let start = scanner.reader.stream_position().map_err(Error::io)?;
+    let runs = scanner.runs()?;
+    scanner
+      .reader
+      .seek(SeekFrom::Start(start))
+      .map_err(Error::io)?;
+    Ok(ResultReader {
+      scanner,
+      runs,
+      state: State::Start,
+      run_count: 0,
+    })
+  }
+}
+
+impl<R> ResultReader<R> {
+  /// Returns every run of the log, without their results
+  pub fn runs(&self) -> &[Arc<sarif::Run>] {
+    &self.runs
+  }
+}
+
+impl<R: Read> ResultReader<R> {
+  fn advance(&mut self) -> Result<Option<RunResult>, Error> {
+    let scanner = &mut self.scanner;
+    loop {
+      self.state = match self.state {
+        State::Start => {
+          scanner.expect(b'{')?;
+          State::Log { first: true }
+        }
+        State::Log { first } => match scanner.next_key(first)? {
+          Some(key) if key == "runs" && scanner.peek()? == Some(b'[') => {
+            scanner.expect(b'[')?;
+            State::Runs { first: true }
+          }
+          Some(_) => {
+            scanner.value::<IgnoredAny>()?;
+            State::Log { first: false }
+          }
+          None => State::Done,
+        },
+        State::Runs { first } => {
+          if scanner.next_element(first)? {
+            scanner.expect(b'{')?;
+            self.run_count += 1;
+            State::Run { first: true }
+          } else {
+            State::Log { first: false }
+          }
+        }
+        State::Run { first } => match scanner.next_key(first)? {
+          Some(key) if key == "results" && scanner.peek()? == Some(b'[') => {
+            scanner.expect(b'[')?;
+            State::Results { first: true }
+          }
+          Some(_) => {
+            scanner.value::<IgnoredAny>()?;
+            State::Run { first: false }
+          }
+          None => State::Runs { first: false },
+        },
+        State::Results { first } => {
+          if scanner.next_element(first)? {
+            let run_index = self.run_count - 1;
+            let run = self.runs.get(run_index).cloned().ok_or_else(|| {
+              Error::custom("the log changed between the two passes")
+            })?;
+            let result = scanner.value()?;
+            self.state = State::Results { first: false };
+            return Ok(Some(RunResult {
+              run_index,
+              run,
+              result,
+            }));
+          } else {
+            State::Run { first: false }
+          }
+        }
+        State::Done => return Ok(None),
+      }
+    }
+  }
+}
+
+impl<R: Read> Iterator for ResultReader<R> {
+  type Item = Result<RunResult, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.advance() {
+      Ok(run_result) => run_result.map(Ok),
+      Err(error) => {
+        self.state = State::Done;
+        Some(Err(error))
+      }
+    }
+  }
+}
+
+// A minimal JSON scanner used to navigate the structure of the log; the
+// values themselves are deserialized by serde_json straight from the reader.
+struct Scanner<R> {
+  reader: BufReader<R>,
+}
+
+impl<R: Read> Scanner<R> {
+  // Returns the next non whitespace byte without consuming it
+  fn peek(&mut self) -> Result<Option<u8>, Error> {
+    loop {
+      let byte = self.reader.fill_buf().map_err(Error::io)?.first().copied();
+      match byte {
+        Some(byte) if byte.is_ascii_whitespace() => self.reader.consume(1),
+        byte => return Ok(byte),
+      }
+    }
+  }
+
+  fn expect(&mut self, expected: u8) -> Result<(), Error> {
+    match self.peek()? {
+      Some(byte) if byte == expected => {
+        self.reader.consume(1);
+        Ok(())
+      }
+      Some(byte) => Err(Error::custom(format!(
+        "expected `{}`, found `{}`",
+        expected as char, byte as char
+      ))),
+      None => Err(Error::custom(format!(
+        "expected `{}`, found end of input",
+        expected as char
+      ))),
+    }
+  }
+
+  // Deserializes the next value. serde_json stops reading right after the
+  // end of objects, arrays, strings and literals, but has to read past the
+  // end of a number, so numbers are scanned here.
+  fn value<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+    match self.peek()? {
+      Some(b'-') | Some(b'0'..=b'9') => {
+        let mut number = vec![];
+        loop {
+          let byte =
+            self.reader.fill_buf().map_err(Error::io)?.first().copied();
+          match byte {
+            Some(byte @ (b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')) => {
+              number.push(byte);
+              self.reader.consume(1);
+            }
+            _ => break,
+          }
+        }
+        serde_json::from_slice(&number)
+      }
+      _ => T::deserialize(&mut serde_json::Deserializer::from_reader(
+        &mut self.reader,
+      )),
+    }
+  }
+
+  // Advances to the next member of an object, returning its key, or None
+  // once the end of the object is reached
+  fn next_key(&mut self, first: bool) -> Result<Option<String>, Error> {
+    if self.peek()? == Some(b'}') {
+      self.reader.consume(1);
+      return Ok(None);
+    }
+    if !first {
+      self.expect(b',')?;
+    }
+    let key = self.value()?;
+    self.expect(b':')?;
+    Ok(Some(key))
+  }
+
+  // Advances to the next element of an array, returning false once the end
+  // of the array is reached
+  fn next_element(&mut self, first: bool) -> Result<bool, Error> {
+    if self.peek()? == Some(b']') {
+      self.reader.consume(1);
+      return Ok(false);
+    }
+    if !first {
+      self.expect(b',')?;
+    }
+    Ok(true)
+  }
+
+  // Reads every run of the log, skipping over their results
+  fn runs(&mut self) -> Result<Vec<Arc<sarif::Run>>, Error> {
+    let mut runs = vec![];
+    self.expect(b'{')?;
+    let mut first = true;
+    while let Some(key) = self.next_key(first)? {
+      first = false;
+      if key == "runs" && self.peek()? == Some(b'[') {
+        self.expect(b'[')?;
+        let mut first = true;
+        while self.next_element(first)? {
+          first = false;
+          runs.push(Arc::new(self.run()?));
+        }
+      } else {
+        self.value::<IgnoredAny>()?;
+      }
+    }
+    Ok(runs)
+  }
+
+  fn run(&mut self) -> Result<sarif::Run, Error> {
+    let mut run = Map::new();
+    self.expect(b'{')?;
+    let mut first = true;
+    while let Some(key) = self.next_key(first)? {
+      first = false;
+      if key == "results" {
+        self.value::<IgnoredAny>()?;
+      } else {
+        run.insert(key, self.value()?);
+      }
+    }
+    sarif::Run::deserialize(Value::Object(run))
+  }
+}
+
+/// A writer which serializes a SARIF log one result at a time.
+///
+/// Runs are started with [SarifStreamWriter::begin_run] and the log is
+/// completed by [SarifStreamWriter::finish]; dropping the writer before
+/// that leaves the output truncated.
+pub struct SarifStreamWriter<W, F = CompactFormatter> {
+  writer: W,
+  formatter: F,
+  first_run: bool,
+}
+
+impl<W: Write> SarifStreamWriter<W> {
+  /// Starts a compact SARIF log in `writer`
+  ///
+  /// # Arguments
+  ///
+  /// * `writer` - A `Write` to write the log to
+  pub fn new(writer: W) -> Result<Self, Error> {
+    Self::with_formatter(writer, CompactFormatter)
+  }
+}
+
+impl<W: Write> SarifStreamWriter<W, PrettyFormatter<'static>> {
+  /// Starts a pretty printed SARIF log in `writer`
+  ///
+  /// # Arguments
+  ///
+  /// * `writer` - A `Write` to write the log to
+  pub fn pretty(writer: W) -> Result<Self, Error> {
+    Self::with_formatter(writer, PrettyFormatter::new())
+  }
+}
+
+impl<W: Write, F: Formatter + Clone> SarifStreamWriter<W, F> {
+  /// Starts a SARIF log in `writer`, formatted by `formatter`
+  ///
+  /// # Arguments
+  ///
+  /// * `writer` - A `Write` to write the log to
+  /// * `formatter` - The formatter used to write the JSON
+  pub fn with_formatter(writer: W, formatter: F) -> Result<Self, Error> {
+    let mut log = SarifStreamWriter {
+      writer,
+      formatter,
+      first_run: true,
+    };
+    log
+      .formatter
+      .begin_object(&mut log.writer)
+      .map_err(Error::io)?;
+    log.member("$schema", sarif::SCHEMA_URL, true)?;
+    log.member("version", &sarif::Version::V2_1_0, false)?;
+    log.key("runs", false)?;
+    log
+      .formatter
+      .begin_array(&mut log.writer)
+      .map_err(Error::io)?;
+    Ok(log)
+  }
+
+  /// Starts a new run. Every field of `run` other than its results is
+  /// written once the run ends.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run metadata, ex. its tool
+  pub fn begin_run(
+    &mut self,
+    run: sarif::Run,
+  ) -> Result<RunWriter<'_, W, F>, Error> {
+    self
+      .formatter
+      .begin_array_value(&mut self.writer, self.first_run)
+      .map_err(Error::io)?;
+    self.first_run = false;
+    self
+      .formatter
+      .begin_object(&mut self.writer)
+      .map_err(Error::io)?;
+    self.key("results", true)?;
+    self
+      .formatter
+      .begin_array(&mut self.writer)
+      .map_err(Error::io)?;
+    Ok(RunWriter {
+      log: self,
+      run,
+      first_result: true,
+    })
+  }
+
+  /// Completes the log, returning the underlying writer
+  pub fn finish(mut self) -> Result<W, Error> {
+    self
+      .formatter
+      .end_array(&mut self.writer)
+      .map_err(Error::io)?;
+    self
+      .formatter
+      .end_object_value(&mut self.writer)
+      .map_err(Error::io)?;
+    self
+      .formatter
+      .end_object(&mut self.writer)
+      .map_err(Error::io)?;
+    self.writer.flush().map_err(Error::io)?;
+    Ok(self.writer)
+  }
+
+  fn key(&mut self, key: &str, first: bool) -> Result<(), Error> {
+    self
+      .formatter
+      .begin_object_key(&mut self.writer, first)
+      .map_err(Error::io)?;
+    self.value(key)?;
+    self
+      .formatter
+      .end_object_key(&mut self.writer)
+      .map_err(Error::io)?;
+    self
+      .formatter
+      .begin_object_value(&mut self.writer)
+      .map_err(Error::io)
+  }
+
+  fn member<T: Serialize + ?Sized>(
+    &mut self,
+    key: &str,
+    value: &T,
+    first: bool,
+  ) -> Result<(), Error> {
+    self.key(key, first)?;
+    self.value(value)?;
+    self
+      .formatter
+      .end_object_value(&mut self.writer)
+      .map_err(Error::io)
+  }
+
+  // Nested values are serialized with a copy of the formatter, which carries
+  // the current indentation over
+  fn value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+    value.serialize(&mut serde_json::Serializer::with_formatter(
+      &mut self.writer,
+      self.formatter.clone(),
+    ))
+  }
+}
+
+/// A run being written by a [SarifStreamWriter], created by
+/// [SarifStreamWriter::begin_run].
+///
+/// The run is completed by [RunWriter::end].
+pub struct RunWriter<'a, W, F> {
+  log: &'a mut SarifStreamWriter<W, F>,
+  run: sarif::Run,
+  first_result: bool,
+}
+
+impl<'a, W: Write, F: Formatter + Clone> RunWriter<'a, W, F> {
+  /// Adds a rule to the run's tool driver, returning its index for use as
+  /// a result's `ruleIndex`. Since the tool is written after the results,
+  /// rules are kept until the run ends.
+  ///
+  /// # Arguments
+  ///
+  /// * `rule` - The rule to add
+  pub fn write_rule(&mut self, rule: sarif::ReportingDescriptor) -> i64 {
+    let rules = self.run.tool.driver.rules.get_or_insert_with(Vec::new);
+    rules.push(rule);
+    i64::try_from(rules.len() - 1).unwrap_or(i64::MAX)
+  }
+
+  /// Adds an invocation to the run. Like rules, invocations are kept until
+  /// the run ends.
+  ///
+  /// # Arguments
+  ///
+  /// * `invocation` - The invocation to add
+  pub fn write_invocation(&mut self, invocation: sarif::Invocation) {
+    self
+      .run
+      .invocations
+      .get_or_insert_with(Vec::new)
+      .push(invocation);
+  }
+
+  /// Returns the rules added to the run's tool driver so far
+  pub fn rules(&self) -> &[sarif::ReportingDescriptor] {
+    self.run.tool.driver.rules.as_deref().unwrap_or_default()
+  }
+
+  /// Returns the run as written so far, without its results, which are not
+  /// kept
+  pub fn run(&self) -> &sarif::Run {
+    &self.run
+  }
+
+  /// Writes a result of the run
+  ///
+  /// # Arguments
+  ///
+  /// * `result` - The result to write
+  pub fn write_result(&mut self, result: &sarif::Result) -> Result<(), Error> {
+    let log = &mut *self.log;
+    log
+      .formatter
+      .begin_array_value(&mut log.writer, self.first_result)
+      .map_err(Error::io)?;
+    self.first_result = false;
+    log.value(result)?;
+    log
+      .formatter
+      .end_array_value(&mut log.writer)
+      .map_err(Error::io)
+  }
+
+  /// Completes the run, writing the rest of its fields
+  pub fn end(self) -> Result<(), Error> {
+    let log = self.log;
+    log
+      .formatter
+      .end_array(&mut log.writer)
+      .map_err(Error::io)?;
+    log
+      .formatter
+      .end_object_value(&mut log.writer)
+      .map_err(Error::io)?;
+    if let Value::Object(run) = serde_json::to_value(&self.run)? {
+      run
+        .iter()
+        .filter(|(key, _)| key.as_str() != "results")
+        .try_for_each(|(key, value)| log.member(key, value, false))?;
+    }
+    log
+      .formatter
+      .end_object(&mut log.writer)
+      .map_err(Error::io)?;
+    log
+      .formatter
+      .end_array_value(&mut log.writer)
+      .map_err(Error::io)
+  }
+}
@@ -0,0 +1,398 @@
+//! Categorization of rules by taxonomies, ex. CWE.
+//!
+//! A taxonomy is a tool component in `runs[].taxonomies` whose `taxa` are
+//! the categories, ex. the weaknesses of the Common Weakness Enumeration. A
+//! rule is categorized by `relationships` whose targets are taxa, and a result
+//! may refer to further taxa in its own `taxa`.
+//!
+//! The crate bundles a snapshot of CWE ([cwe]), restricted to the 2023 CWE
+//! Top 25 and weaknesses commonly reported by linters, and the OWASP Top 10
+//! ([owasp_top_ten]), so no network access is needed. A [TaxonomyMapping]
+//! maps rule ids to the ids of taxa and adds the relationships to the rules of
+//! a run. Weaknesses missing from the snapshot, ex. `CWE-611`, are added to it
+//! when mapped to, with a `helpUri` to their definition. Mapping files are JSON
+//! objects from rule ids to arrays of taxon ids,
+//! ex. `{ "2086": ["CWE-88"], "DL3002": ["CWE-250", "A05:2021"] }`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::sarif;
+//! use serde_sarif::taxonomy::{cwe, taxa, TaxonomyMapping};
+//!
+//! let mut run: sarif::Run = serde_json::from_value(serde_json::json!({
+//!   "tool": { "driver": { "name": "shellcheck", "rules": [{ "id": "2086" }] } },
+//!   "results": [{ "ruleIndex": 0, "message": { "text": "quote this" } }]
+//! }))
+//! .unwrap();
+//! TaxonomyMapping::new(vec![cwe()])
+//!   .map("2086", "CWE-88")
+//!   .unwrap()
+//!   .apply(&mut run);
+//!
+//! let result = &run.results.as_ref().unwrap()[0];
+//! let (taxonomy, taxon) = taxa(&run, result)[0];
+//! assert_eq!(taxonomy.name, "CWE");
+//! assert_eq!(taxon.id, "CWE-88");
+//! ```
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::sarif;
+
+/// An error reading a taxonomy mapping.
+#[derive(Error, Debug)]
+pub enum TaxonomyError {
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+  #[error(
+    "rule {rule} is mapped to {taxon}, which is in none of the taxonomies"
+  )]
+  UnknownTaxon { rule: String, taxon: String },
+}
+
+/// Returns the bundled snapshot of the Common Weakness Enumeration (version
+/// 4.13), whose taxa have ids like `CWE-79`. It is restricted to the 2023 CWE
+/// Top 25 and weaknesses commonly reported by linters; [TaxonomyMapping::map]
+/// adds any other weakness mapped to.
+pub fn cwe() -> sarif::ToolComponent {
+  serde_json::from_str(include_str!("../data/cwe.json"))
+    .expect("the bundled CWE taxonomy is valid")
+}
+
+/// Returns the bundled OWASP Top 10 (2021), whose taxa have ids like
+/// `A03:2021`.
+pub fn owasp_top_ten() -> sarif::ToolComponent {
+  serde_json::from_str(include_str!("../data/owasp-top-10.json"))
+    .expect("the bundled OWASP Top 10 taxonomy is valid")
+}
+
+/// Maps rule ids to the taxa of a set of taxonomies.
+#[derive(Clone, Debug, Default)]
+pub struct TaxonomyMapping {
+  taxonomies: Vec<sarif::ToolComponent>,
+  // the indices of the taxonomy and the taxon of each taxon a rule maps to
+  rules: BTreeMap<String, Vec<(usize, usize)>>,
+}
+
+impl TaxonomyMapping {
+  /// Returns an empty mapping to the taxa of `taxonomies`.
+  ///
+  /// # Arguments
+  ///
+  /// * `taxonomies` - The taxonomies, ex. [cwe]
+  pub fn new(taxonomies: Vec<sarif::ToolComponent>) -> Self {
+    TaxonomyMapping {
+      taxonomies,
+      rules: BTreeMap::new(),
+    }
+  }
+
+  /// Reads a mapping file, a JSON object from rule ids to arrays of taxon
+  /// ids.
+  ///
+  /// # Arguments
+  ///
+  /// * `reader` - A `Read` of the mapping file
+  /// * `taxonomies` - The taxonomies the taxon ids refer to
+  pub fn from_reader<R: Read>(
+    reader: R,
+    taxonomies: Vec<sarif::ToolComponent>,
+  ) -> Result<Self, TaxonomyError> {
+    let rules: BTreeMap<String, Vec<String>> = serde_json::from_reader(reader)?;
+    rules.into_iter().try_fold(
+      TaxonomyMapping::new(taxonomies),
+      |mapping, (rule, taxa)| {
+        taxa
+          .iter()
+          .try_fold(mapping, |mapping, taxon| mapping.map(rule.clone(), taxon))
+      },
+    )
+  }
+
+  /// Reads a mapping file, see [TaxonomyMapping::from_reader].
+  ///
+  /// # Arguments
+  ///
+  /// * `path` - The path of the mapping file
+  /// * `taxonomies` - The taxonomies the taxon ids refer to
+  pub fn load<P: AsRef<Path>>(
+    path: P,
+    taxonomies: Vec<sarif::ToolComponent>,
+  ) -> Result<Self, TaxonomyError> {
+    Self::from_reader(BufReader::new(File::open(path)?), taxonomies)
+  }
+
+  /// Maps a rule to a taxon, which is looked up in the taxonomies in order.
+  /// A well-formed CWE id, ex. `CWE-611`, which is in none of them is added to
+  /// the taxonomy named `CWE`, if any.
+  ///
+  /// # Arguments
+  ///
+  /// * `rule` - The id of the rule
+  /// * `taxon` - The id of the taxon, ex. `CWE-79`
+  pub fn map<S: Into<String>>(
+    mut self,
+    rule: S,
+    taxon: &str,
+  ) -> Result<Self, TaxonomyError> {
+    let rule = rule.into();
+    let found = self
+      .taxonomies
+      .iter()
+      .enumerate()
+      .find_map(|(i, taxonomy)| {
+        taxonomy
+          .taxa
+          .iter()
+          .flatten()
+          .position(|t| t.id == taxon)
+          .map(|j| (i, j))
+      })
+      .or_else(|| self.add_weakness(taxon));
+    match found {
+      Some(found) => {
+        self.rules.entry(rule).or_default().push(found);
+        Ok(self)
+      }
+      None => Err(TaxonomyError::UnknownTaxon {
+        rule,
+        taxon: taxon.to_string(),
+      }),
+    }
+  }
+
+  // Adds a weakness to the CWE taxonomy, returning the indices of the
+  // taxonomy and the new taxon
+  fn add_weakness(&mut self, taxon: &str) -> Option<(usize, usize)> {
+    let number = taxon
+      .strip_prefix("CWE-")
+      .filter(|n| !n.starts_with('0') && n.bytes().all(|b| b.is_ascii_digit()))
+      .and_then(|n| n.parse::<u32>().ok())?;
+    let i = self.taxonomies.iter().position(|t| t.name == "CWE")?;
+    let taxa = self.taxonomies[i].taxa.get_or_insert_with(Vec::new);
+    taxa.push(
+      sarif::ReportingDescriptorBuilder::default()
+        .id(taxon)
+        .help_uri(format!(
+          "https://cwe.mitre.org/data/definitions/{}.html",
+          number
+        ))
+        .build()
+        .expect("the id of the weakness is set"),
+    );
+    Some((i, taxa.len() - 1))
+  }
+
+  /// Adds a relationship to each taxon a rule maps to, unless the rule has
+  /// it already.
+  ///
+  /// # Arguments
+  ///
+  /// * `rule` - The rule to categorize
+  pub fn categorize(&self, rule: &mut sarif::ReportingDescriptor) {
+    self.relate(rule, &self.taxonomies)
+  }
+
+  // Adds the relationships of a rule to the taxa it maps to, which refer to
+  // the taxonomies of the same names in `taxonomies`. Taxa missing from
+  // these, and relationships the rule has already, are skipped.
+  fn relate(
+    &self,
+    rule: &mut sarif::ReportingDescriptor,
+    taxonomies: &[sarif::ToolComponent],
+  ) {
+    let taxa = match self.rules.get(&rule.id) {
+      Some(taxa) => taxa,
+      None => return,
+    };
+    let relationships = rule.relationships.get_or_insert_with(Vec::new);
+    for &(i, j) in taxa {
+      let name = &self.taxonomies[i].name;
+      let id = &self.taxonomies[i].taxa.as_deref().unwrap_or_default()[j].id;
+      let found = taxonomies.iter().find(|t| &t.name == name).and_then(|t| {
+        let taxon = t.taxa.iter().flatten().find(|taxon| &taxon.id == id)?;
+        Some((t, taxon))
+      });
+      let (taxonomy, taxon) = match found {
+        Some(found) => found,
+        None => continue,
+      };
+      if relationships.iter().any(|relationship| {
+        let target = &relationship.target;
+        target.id.as_ref() == Some(id)
+          && target
+            .tool_component
+            .as_ref()
+            .and_then(|component| component.name.as_ref())
+            == Some(name)
+      }) {
+        continue;
+      }
+      relationships.push(sarif::ReportingDescriptorRelationship {
+        target: sarif::ReportingDescriptorReference {
+          id: Some(taxon.id.clone()),
+          guid: taxon.guid.clone(),
+          tool_component: Some(sarif::ToolComponentReference {
+            name: Some(taxonomy.name.clone()),
+            guid: taxonomy.guid.clone(),
+            ..Default::default()
+          }),
+          ..Default::default()
+        },
+        kinds: None,
+        description: None,
+        properties: None,
+      });
+    }
+  }
+
+  /// Adds the taxonomies which rules are mapped to to a run, declares them as
+  /// supported by the driver and categorizes the rules of the driver. If the
+  /// run has a taxonomy of the same name already, the taxa mapped to which it
+  /// lacks are added to it instead.
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run to categorize
+  pub fn apply(&self, run: &mut sarif::Run) {
+    let mut used: Vec<usize> =
+      self.rules.values().flatten().map(|&(i, _)| i).collect();
+    used.sort_unstable();
+    used.dedup();
+    if used.is_empty() {
+      return;
+    }
+    let taxonomies = run.taxonomies.get_or_insert_with(Vec::new);
+    let supported = run
+      .tool
+      .driver
+      .supported_taxonomies
+      .get_or_insert_with(Vec::new);
+    for i in used {
+      let taxonomy = &self.taxonomies[i];
+      let existing =
+        match taxonomies.iter_mut().find(|t| t.name == taxonomy.name) {
+          Some(existing) => {
+            let taxa = existing.taxa.get_or_insert_with(Vec::new);
+            for taxon in self.mapped_taxa(i) {
+              if !taxa.iter().any(|t| t.id == taxon.id) {
+                taxa.push(taxon.clone());
+              }
+            }
+            existing
+          }
+          None => {
+            taxonomies.push(taxonomy.clone());
+            taxonomies.last_mut().expect("a taxonomy was pushed")
+          }
+        };
+      if !supported
+        .iter()
+        .any(|t| t.name.as_ref() == Some(&existing.name))
+      {
+        supported.push(sarif::ToolComponentReference {
+          name: Some(existing.name.clone()),
+          guid: existing.guid.clone(),
+          ..Default::default()
+        });
+      }
+    }
+    let taxonomies = run.taxonomies.as_deref().unwrap_or_default();
+    run
+      .tool
+      .driver
+      .rules
+      .iter_mut()
+      .flatten()
+      .for_each(|rule| self.relate(rule, taxonomies));
+  }
+
+  // Returns the taxa of a taxonomy which rules map to
+  fn mapped_taxa(
+    &self,
+    i: usize,
+  ) -> impl Iterator<Item = &sarif::ReportingDescriptor> {
+    let taxa = self.taxonomies[i].taxa.as_deref().unwrap_or_default();
+    self
+      .rules
+      .values()
+      .flatten()
+      .filter(move |&&(k, _)| k == i)
+      .map(move |&(_, j)| &taxa[j])
+  }
+}
+
+/// Returns the taxonomy and the taxon a reference refers to. Taxonomies are
+/// looked up by their index into the run's taxonomies, else by guid, else by
+/// name.
+///
+/// # Arguments
+///
+/// * `run` - The run defining the taxonomies
+/// * `reference` - The reference to the taxon
+pub fn resolve_taxon<'a>(
+  run: &'a sarif::Run,
+  reference: &sarif::ReportingDescriptorReference,
+) -> Option<(&'a sarif::ToolComponent, &'a sarif::ReportingDescriptor)> {
+  let component = reference.tool_component.as_ref()?;
+  let taxonomies = run.taxonomies.as_deref().unwrap_or_default();
+  let taxonomy = match (component.index, &component.guid, &component.name) {
+    (Some(index), _, _) => taxonomies.get(usize::try_from(index).ok()?),
+    (None, Some(guid), _) => taxonomies.iter().find(|t| {
+      t.guid
+        .as_ref()
+        .is_some_and(|g| g.eq_ignore_ascii_case(guid))
+    }),
+    (None, None, Some(name)) => taxonomies.iter().find(|t| &t.name == name),
+    (None, None, None) => None,
+  }?;
+  let taxon = sarif::find_descriptor(
+    taxonomy.taxa.as_ref(),
+    reference.index,
+    reference.guid.as_deref(),
+    reference.id.as_deref(),
+  )?;
+  Some((taxonomy, taxon))
+}
+
+/// Returns the taxa a result is categorized by: those of the result itself,
+/// followed by the targets of its rule's relationships which are taxa.
+///
+/// # Arguments
+///
+/// * `run` - The run of the result
+/// * `result` - The result
+pub fn taxa<'a>(
+  run: &'a sarif::Run,
+  result: &'a sarif::Result,
+) -> Vec<(&'a sarif::ToolComponent, &'a sarif::ReportingDescriptor)> {
+  let relationships = run
+    .resolve_rule(result)
+    .and_then(|rule| rule.relationships.as_ref())
+    .into_iter()
+    .flatten()
+    .map(|relationship| &relationship.target);
+  let mut taxa: Vec<(&sarif::ToolComponent, &sarif::ReportingDescriptor)> =
+    vec![];
+  for (taxonomy, taxon) in result
+    .taxa
+    .iter()
+    .flatten()
+    .chain(relationships)
+    .filter_map(|reference| resolve_taxon(run, reference))
+  {
+    if !taxa.iter().any(|(_, t)| std::ptr::eq(*t, taxon)) {
+      taxa.push((taxonomy, taxon));
+    }
+  }
+  taxa
+}
@@ -0,0 +1,1418 @@
+//! Upgrading of SARIF logs from older versions to 2.1.0.
+//!
+//! Some tools still emit SARIF 1.0.0, or one of the pre-release 2.0.0 (and
+//! 2.1.0) drafts, which do not deserialize into [sarif::Sarif]: the
+//! `version` is not 2.1.0 and many properties were renamed or restructured
+//! before the final version, ex. `resultFile` and `fileLocation` became
+//! `physicalLocation.artifactLocation`, `files` became `artifacts`, and rules
+//! keyed by `ruleKey` moved into `tool.driver.rules`.
+//!
+//! [upgrade] detects the version of a log and transforms it into a 2.1.0 log.
+//! Properties without a 2.1.0 equivalent are dropped. Logs which already are
+//! 2.1.0 are deserialized as is.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::upgrade::upgrade;
+//!
+//! let sarif = upgrade(serde_json::json!({
+//!   "version": "1.0.0",
+//!   "runs": [{
+//!     "tool": { "name": "legacy" },
+//!     "rules": { "C2001": { "id": "C2001", "shortDescription": "A rule" } },
+//!     "results": [{
+//!       "ruleKey": "C2001",
+//!       "level": "error",
+//!       "message": "Something went wrong",
+//!       "locations": [{
+//!         "resultFile": {
+//!           "uri": "file:///src/main.c",
+//!           "region": { "startLine": 3 }
+//!         }
+//!       }]
+//!     }]
+//!   }]
+//! }))
+//! .unwrap();
+//!
+//! let run = &sarif.runs[0];
+//! assert_eq!(run.tool.driver.name, "legacy");
+//! let result = &run.results.as_ref().unwrap()[0];
+//! assert_eq!(result.rule_id.as_deref(), Some("C2001"));
+//! assert_eq!(result.rule_index, Some(0));
+//! ```
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::sarif;
+
+/// The version of a SARIF log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceVersion {
+  /// SARIF 1.0.0
+  V1_0_0,
+  /// A pre-release draft of SARIF 2.0.0 or 2.1.0, ex. `2.0.0-csd.2.beta.2019-01-24`
+  V2_0_0,
+  /// SARIF 2.1.0, which needs no upgrade
+  V2_1_0,
+}
+
+/// An error upgrading a SARIF log.
+#[derive(Error, Debug)]
+pub enum UpgradeError {
+  #[error("the log has no version")]
+  MissingVersion,
+  #[error("unsupported SARIF version {0}")]
+  UnsupportedVersion(String),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+}
+
+/// Returns the version a SARIF `version` string denotes
+///
+/// # Arguments
+///
+/// * `version` - The `version` property of a SARIF log
+pub fn detect(version: &str) -> Option<SourceVersion> {
+  match version {
+    "1.0.0" => Some(SourceVersion::V1_0_0),
+    "2.1.0" => Some(SourceVersion::V2_1_0),
+    v if v.starts_with("2.0.0") || v.starts_with("2.1.0-") => {
+      Some(SourceVersion::V2_0_0)
+    }
+    _ => None,
+  }
+}
+
+/// Reads the version of the SARIF log in `reader`, without deserializing
+/// the rest of the log
+///
+/// # Arguments
+///
+/// * `reader` - A reader containing a SARIF log
+pub fn detect_reader<R: Read>(
+  reader: R,
+) -> Result<SourceVersion, UpgradeError> {
+  #[derive(Deserialize)]
+  struct Header {
+    version: Option<String>,
+  }
+  let header: Header = serde_json::from_reader(reader)?;
+  let version = header.version.ok_or(UpgradeError::MissingVersion)?;
+  detect(&version).ok_or(UpgradeError::UnsupportedVersion(version))
+}
+
+/// Upgrades a SARIF log of any supported version to a 2.1.0 [sarif::Sarif]
+///
+/// # Arguments
+///
+/// * `value` - The SARIF log
+pub fn upgrade(value: Value) -> Result<sarif::Sarif, UpgradeError> {
+  Ok(serde_json::from_value(upgrade_value(value)?)?)
+}
+
+/// Upgrades a SARIF log of any supported version to a 2.1.0 log, without
+/// deserializing it
+///
+/// # Arguments
+///
+/// * `value` - The SARIF log
+pub fn upgrade_value(value: Value) -> Result<Value, UpgradeError> {
+  let version = value
+    .get("version")
+    .and_then(Value::as_str)
+    .ok_or(UpgradeError::MissingVersion)?;
+  let source = detect(version)
+    .ok_or_else(|| UpgradeError::UnsupportedVersion(version.into()))?;
+  if source == SourceVersion::V2_1_0 {
+    return Ok(value);
+  }
+
+  let mut log = object(value);
+  let runs: Vec<Value> = array(log.remove("runs"))
+    .into_iter()
+    .map(|run| upgrade_run(object(run)))
+    .collect();
+  let mut upgraded = Map::new();
+  upgraded.insert("$schema".into(), sarif::SCHEMA_URL.into());
+  upgraded.insert("version".into(), "2.1.0".into());
+  upgraded.insert("runs".into(), runs.into());
+  insert(
+    &mut upgraded,
+    "properties",
+    log.remove("properties").and_then(properties),
+  );
+  Ok(Value::Object(upgraded))
+}
+
+// The indices of the run's rules, artifacts and logical locations, which the
+// older versions referred to by key
+#[derive(Default)]
+struct Context {
+  rule_ids: HashMap<String, String>,
+  rule_indices: HashMap<String, usize>,
+  artifact_indices: HashMap<String, usize>,
+  logical_location_indices: HashMap<String, usize>,
+}
+
+fn object(value: Value) -> Map<String, Value> {
+  match value {
+    Value::Object(map) => map,
+    _ => Map::new(),
+  }
+}
+
+fn array(value: Option<Value>) -> Vec<Value> {
+  match value {
+    Some(Value::Array(values)) => values,
+    _ => vec![],
+  }
+}
+
+fn insert(map: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+  if let Some(value) = value {
+    map.insert(key.into(), value);
+  }
+}
+
+// Moves the properties with the given keys which have the type `check`
+// expects from `from` to `to`
+fn copy(
+  from: &mut Map<String, Value>,
+  to: &mut Map<String, Value>,
+  keys: &[&str],
+  check: fn(&Value) -> bool,
+) {
+  keys.iter().for_each(|key| {
+    if let Some(value) = from.remove(*key).filter(check) {
+      to.insert((*key).into(), value);
+    }
+  })
+}
+
+// Moves the first of the properties named `keys` which has the type `check`
+// expects from `from` to `to`, under the name `key`
+fn rename(
+  from: &mut Map<String, Value>,
+  to: &mut Map<String, Value>,
+  keys: &[&str],
+  key: &str,
+  check: fn(&Value) -> bool,
+) {
+  let value = keys.iter().filter_map(|key| from.remove(*key)).find(check);
+  insert(to, key, value);
+}
+
+fn is_string(value: &Value) -> bool {
+  value.is_string()
+}
+
+fn is_integer(value: &Value) -> bool {
+  value.is_i64()
+}
+
+fn is_bool(value: &Value) -> bool {
+  value.is_boolean()
+}
+
+fn is_strings(value: &Value) -> bool {
+  value
+    .as_array()
+    .is_some_and(|values| values.iter().all(Value::is_string))
+}
+
+fn is_string_map(value: &Value) -> bool {
+  value
+    .as_object()
+    .is_some_and(|map| map.values().all(Value::is_string))
+}
+
+fn properties(value: Value) -> Option<Value> {
+  let mut properties = object(value);
+  if properties.get("tags").is_some_and(|tags| !is_strings(tags)) {
+    properties.remove("tags");
+  }
+  Some(Value::Object(properties))
+}
+
+fn level(value: Option<Value>) -> Option<Value> {
+  value.filter(|level| {
+    matches!(
+      level.as_str(),
+      Some("none") | Some("note") | Some("warning") | Some("error")
+    )
+  })
+}
+
+// Both plain strings (1.0.0) and message objects (2.0.0) become messages
+fn message(value: Value) -> Option<Value> {
+  match value {
+    Value::String(text) => Some(json!({ "text": text })),
+    Value::Object(mut from) => {
+      let mut message = Map::new();
+      copy(&mut from, &mut message, &["text", "markdown"], is_string);
+      rename(
+        &mut from,
+        &mut message,
+        &["id", "messageId"],
+        "id",
+        is_string,
+      );
+      copy(&mut from, &mut message, &["arguments"], is_strings);
+      rename(
+        &mut from,
+        &mut message,
+        &["richText"],
+        "markdown",
+        is_string,
+      );
+      Some(Value::Object(message))
+    }
+    _ => None,
+  }
+}
+
+fn multiformat_message(value: Value) -> Option<Value> {
+  let mut message = object(message(value)?);
+  message.remove("id");
+  message.remove("arguments");
+  Some(Value::Object(message)).filter(|m| m.get("text").is_some())
+}
+
+fn artifact_location(value: Value) -> Option<Value> {
+  let mut from = match value {
+    Value::String(uri) => return Some(json!({ "uri": uri })),
+    Value::Object(from) => from,
+    _ => return None,
+  };
+  let mut location = Map::new();
+  copy(&mut from, &mut location, &["uri", "uriBaseId"], is_string);
+  rename(
+    &mut from,
+    &mut location,
+    &["index", "fileIndex"],
+    "index",
+    is_integer,
+  );
+  Some(Value::Object(location))
+}
+
+fn region(value: Value) -> Option<Value> {
+  let mut from = object(value);
+  let mut region = Map::new();
+  copy(
+    &mut from,
+    &mut region,
+    &[
+      "startLine",
+      "startColumn",
+      "endLine",
+      "endColumn",
+      "byteOffset",
+      "byteLength",
+    ],
+    is_integer,
+  );
+  rename(
+    &mut from,
+    &mut region,
+    &["charOffset", "offset"],
+    "charOffset",
+    is_integer,
+  );
+  rename(
+    &mut from,
+    &mut region,
+    &["charLength", "length"],
+    "charLength",
+    is_integer,
+  );
+  copy(&mut from, &mut region, &["sourceLanguage"], is_string);
+  insert(
+    &mut region,
+    "snippet",
+    from.remove("snippet").and_then(multiformat_message),
+  );
+  insert(
+    &mut region,
+    "message",
+    from.remove("message").and_then(message),
+  );
+  Some(Value::Object(region))
+}
+
+// 1.0.0 physical locations have their uri inline, 2.0.0 ones have a
+// fileLocation
+fn physical_location(value: Value) -> Option<Value> {
+  let mut from = object(value);
+  let mut location = Map::new();
+  let artifact = match from
+    .remove("artifactLocation")
+    .or_else(|| from.remove("fileLocation"))
+  {
+    Some(artifact) => artifact_location(artifact),
+    None => {
+      let mut artifact = Map::new();
+      copy(&mut from, &mut artifact, &["uri", "uriBaseId"], is_string);
+      Some(Value::Object(artifact)).filter(|a| a != &json!({}))
+    }
+  };
+  insert(&mut location, "artifactLocation", artifact);
+  insert(
+    &mut location,
+    "region",
+    from.remove("region").and_then(region),
+  );
+  insert(
+    &mut location,
+    "contextRegion",
+    from.remove("contextRegion").and_then(region),
+  );
+  Some(Value::Object(location))
+}
+
+fn logical_location_reference(
+  name: Option<Value>,
+  context: &Context,
+) -> Option<Value> {
+  let name = name?.as_str()?.to_string();
+  let mut location = Map::new();
+  insert(
+    &mut location,
+    "index",
+    context
+      .logical_location_indices
+      .get(&name)
+      .map(|i| json!(i)),
+  );
+  location.insert("fullyQualifiedName".into(), name.into());
+  Some(json!([location]))
+}
+
+// Handles 2.0.0 locations as well as 1.0.0 (annotated code) locations,
+// whose physical location is their resultFile
+fn location(value: Value, context: &Context) -> Option<Value> {
+  let mut from = object(value);
+  let mut location = Map::new();
+  let physical = from
+    .remove("physicalLocation")
+    .or_else(|| from.remove("resultFile"))
+    .or_else(|| from.remove("analysisTarget"));
+  insert(
+    &mut location,
+    "physicalLocation",
+    physical.and_then(physical_location),
+  );
+  match from.remove("logicalLocations").filter(Value::is_array) {
+    Some(logical) => {
+      location.insert("logicalLocations".into(), logical);
+    }
+    None => insert(
+      &mut location,
+      "logicalLocations",
+      logical_location_reference(
+        from
+          .remove("fullyQualifiedLogicalName")
+          .or_else(|| from.remove("logicalLocationKey")),
+        context,
+      ),
+    ),
+  }
+  copy(&mut from, &mut location, &["id"], is_integer);
+  insert(
+    &mut location,
+    "message",
+    from.remove("message").and_then(message),
+  );
+  let annotations: Vec<Value> = array(from.remove("annotations"))
+    .into_iter()
+    .filter_map(|annotation| {
+      // 2.0.0 annotations wrap their regions
+      let mut annotation = object(annotation);
+      let message = annotation.remove("message");
+      let mut region = object(region(
+        annotation
+          .remove("locations")
+          .and_then(|locations| array(Some(locations)).into_iter().next())
+          .unwrap_or(Value::Object(annotation)),
+      )?);
+      insert(&mut region, "message", message.and_then(self::message));
+      Some(Value::Object(region))
+    })
+    .collect();
+  if !annotations.is_empty() {
+    location.insert("annotations".into(), annotations.into());
+  }
+  Some(Value::Object(location))
+}
+
+fn stack(value: Value, context: &Context) -> Option<Value> {
+  let mut from = object(value);
+  let mut stack = Map::new();
+  insert(
+    &mut stack,
+    "message",
+    from.remove("message").and_then(message),
+  );
+  let frames: Vec<Value> = array(from.remove("frames"))
+    .into_iter()
+    .map(|frame| {
+      let mut from = object(frame);
+      let mut frame = Map::new();
+      let location = match from.remove("location") {
+        Some(location) => self::location(location, context),
+        // 1.0.0 frames have their location inline
+        None => {
+          let mut physical = Map::new();
+          copy(&mut from, &mut physical, &["uri", "uriBaseId"], is_string);
+          let mut region = Map::new();
+          rename(&mut from, &mut region, &["line"], "startLine", is_integer);
+          rename(
+            &mut from,
+            &mut region,
+            &["column"],
+            "startColumn",
+            is_integer,
+          );
+          physical.insert("region".into(), Value::Object(region));
+          let mut location = Map::new();
+          location.insert("physicalLocation".into(), Value::Object(physical));
+          insert(&mut location, "message", from.remove("message"));
+          insert(
+            &mut location,
+            "fullyQualifiedLogicalName",
+            from.remove("fullyQualifiedLogicalName"),
+          );
+          self::location(Value::Object(location), context)
+        }
+      };
+      insert(&mut frame, "location", location);
+      copy(&mut from, &mut frame, &["module"], is_string);
+      copy(&mut from, &mut frame, &["threadId"], is_integer);
+      copy(&mut from, &mut frame, &["parameters"], is_strings);
+      Value::Object(frame)
+    })
+    .collect();
+  stack.insert("frames".into(), frames.into());
+  Some(Value::Object(stack))
+}
+
+fn thread_flow_location(value: Value, context: &Context) -> Value {
+  let mut from = object(value);
+  let mut location = Map::new();
+  let inner = match from.remove("location") {
+    Some(inner) => self::location(inner, context),
+    // 1.0.0 code flow locations are annotated code locations
+    None => self::location(Value::Object(from.clone()), context),
+  };
+  insert(&mut location, "location", inner);
+  insert(
+    &mut location,
+    "stack",
+    from.remove("stack").and_then(|s| stack(s, context)),
+  );
+  rename(
+    &mut from,
+    &mut location,
+    &["executionOrder", "step"],
+    "executionOrder",
+    is_integer,
+  );
+  copy(&mut from, &mut location, &["nestingLevel"], is_integer);
+  copy(
+    &mut from,
+    &mut location,
+    &["module", "importance"],
+    is_string,
+  );
+  match from.remove("kind") {
+    Some(Value::String(kind)) => {
+      location.insert("kinds".into(), json!([kind]));
+    }
+    _ => copy(&mut from, &mut location, &["kinds"], is_strings),
+  }
+  Value::Object(location)
+}
+
+fn code_flow(value: Value, context: &Context) -> Value {
+  let mut from = object(value);
+  let mut code_flow = Map::new();
+  insert(
+    &mut code_flow,
+    "message",
+    from.remove("message").and_then(message),
+  );
+  let thread_flows: Vec<Value> = match from.remove("threadFlows") {
+    Some(thread_flows) => array(Some(thread_flows))
+      .into_iter()
+      .map(|thread_flow| {
+        let mut from = object(thread_flow);
+        let mut thread_flow = Map::new();
+        copy(&mut from, &mut thread_flow, &["id"], is_string);
+        insert(
+          &mut thread_flow,
+          "message",
+          from.remove("message").and_then(message),
+        );
+        let locations: Vec<Value> = array(from.remove("locations"))
+          .into_iter()
+          .map(|location| thread_flow_location(location, context))
+          .collect();
+        thread_flow.insert("locations".into(), locations.into());
+        Value::Object(thread_flow)
+      })
+      .collect(),
+    // 1.0.0 code flows have a single, implicit thread flow
+    None => {
+      let locations: Vec<Value> = array(from.remove("locations"))
+        .into_iter()
+        .map(|location| thread_flow_location(location, context))
+        .collect();
+      vec![json!({ "locations": locations })]
+    }
+  };
+  code_flow.insert("threadFlows".into(), thread_flows.into());
+  Value::Object(code_flow)
+}
+
+fn fix(value: Value) -> Option<Value> {
+  let mut from = object(value);
+  let mut fix = Map::new();
+  insert(
+    &mut fix,
+    "description",
+    from.remove("description").and_then(message),
+  );
+  let changes = from
+    .remove("artifactChanges")
+    .or_else(|| from.remove("fileChanges"));
+  let changes: Vec<Value> = array(changes)
+    .into_iter()
+    .filter_map(|change| {
+      let mut from = object(change);
+      let mut change = Map::new();
+      let artifact = match from
+        .remove("artifactLocation")
+        .or_else(|| from.remove("fileLocation"))
+      {
+        Some(artifact) => artifact_location(artifact),
+        None => {
+          let mut artifact = Map::new();
+          copy(&mut from, &mut artifact, &["uri", "uriBaseId"], is_string);
+          Some(Value::Object(artifact))
+        }
+      };
+      change.insert("artifactLocation".into(), artifact?);
+      let replacements: Vec<Value> = array(from.remove("replacements"))
+        .into_iter()
+        .filter_map(|replacement| {
+          let mut from = object(replacement);
+          let mut replacement = Map::new();
+          let deleted = match from.remove("deletedRegion") {
+            Some(deleted) => region(deleted),
+            // 1.0.0 replacements are byte ranges
+            None => {
+              let mut deleted = Map::new();
+              rename(
+                &mut from,
+                &mut deleted,
+                &["offset"],
+                "byteOffset",
+                is_integer,
+              );
+              rename(
+                &mut from,
+                &mut deleted,
+                &["deletedLength"],
+                "byteLength",
+                is_integer,
+              );
+              Some(Value::Object(deleted))
+            }
+          };
+          replacement.insert("deletedRegion".into(), deleted?);
+          let inserted = match from.remove("insertedContent") {
+            Some(Value::Object(mut content)) => {
+              let mut inserted = Map::new();
+              copy(&mut content, &mut inserted, &["text", "binary"], is_string);
+              Some(Value::Object(inserted))
+            }
+            _ => from
+              .remove("insertedBytes")
+              .filter(Value::is_string)
+              .map(|bytes| json!({ "binary": bytes })),
+          };
+          insert(&mut replacement, "insertedContent", inserted);
+          Some(Value::Object(replacement))
+        })
+        .collect();
+      change.insert("replacements".into(), replacements.into());
+      Some(Value::Object(change))
+    })
+    .collect();
+  fix.insert("artifactChanges".into(), changes.into());
+  Some(Value::Object(fix))
+}
+
+fn notification(value: Value, context: &Context) -> Value {
+  let mut from = object(value);
+  let mut notification = Map::new();
+  notification.insert(
+    "message".into(),
+    from
+      .remove("message")
+      .and_then(message)
+      .unwrap_or_else(|| json!({ "text": "" })),
+  );
+  insert(&mut notification, "level", level(from.remove("level")));
+  insert(
+    &mut notification,
+    "descriptor",
+    from
+      .remove("id")
+      .filter(Value::is_string)
+      .map(|id| json!({ "id": id })),
+  );
+  insert(
+    &mut notification,
+    "associatedRule",
+    from
+      .remove("ruleId")
+      .or_else(|| from.remove("ruleKey"))
+      .and_then(|id| id.as_str().map(String::from))
+      .map(|id| {
+        let id = context.rule_ids.get(&id).cloned().unwrap_or(id);
+        json!({ "id": id })
+      }),
+  );
+  rename(
+    &mut from,
+    &mut notification,
+    &["timeUtc", "time"],
+    "timeUtc",
+    is_string,
+  );
+  copy(&mut from, &mut notification, &["threadId"], is_integer);
+  let location = from
+    .remove("physicalLocation")
+    .and_then(physical_location)
+    .map(|physical| json!([{ "physicalLocation": physical }]));
+  insert(&mut notification, "locations", location);
+  Value::Object(notification)
+}
+
+fn invocation(value: Value, context: &Context) -> Value {
+  let mut from = object(value);
+  let mut invocation = Map::new();
+  copy(
+    &mut from,
+    &mut invocation,
+    &[
+      "commandLine",
+      "machine",
+      "account",
+      "exitCodeDescription",
+      "exitSignalName",
+    ],
+    is_string,
+  );
+  copy(&mut from, &mut invocation, &["arguments"], is_strings);
+  copy(
+    &mut from,
+    &mut invocation,
+    &["processId", "exitCode", "exitSignalNumber"],
+    is_integer,
+  );
+  copy(
+    &mut from,
+    &mut invocation,
+    &["environmentVariables"],
+    is_string_map,
+  );
+  rename(
+    &mut from,
+    &mut invocation,
+    &["startTimeUtc", "startTime"],
+    "startTimeUtc",
+    is_string,
+  );
+  rename(
+    &mut from,
+    &mut invocation,
+    &["endTimeUtc", "endTime"],
+    "endTimeUtc",
+    is_string,
+  );
+  let executable = from
+    .remove("executableLocation")
+    .or_else(|| from.remove("fileName"));
+  insert(
+    &mut invocation,
+    "executableLocation",
+    executable.and_then(artifact_location),
+  );
+  insert(
+    &mut invocation,
+    "workingDirectory",
+    from.remove("workingDirectory").and_then(artifact_location),
+  );
+  [
+    ("toolExecutionNotifications", "toolNotifications"),
+    (
+      "toolConfigurationNotifications",
+      "configurationNotifications",
+    ),
+  ]
+  .iter()
+  .for_each(|(key, old)| {
+    let notifications: Vec<Value> =
+      array(from.remove(*key).or_else(|| from.remove(*old)))
+        .into_iter()
+        .map(|n| notification(n, context))
+        .collect();
+    if !notifications.is_empty() {
+      invocation.insert((*key).into(), notifications.into());
+    }
+  });
+  // executionSuccessful is required by 2.1.0
+  let successful = from
+    .remove("executionSuccessful")
+    .filter(is_bool)
+    .unwrap_or_else(|| {
+      let exit_code = invocation.get("exitCode").and_then(Value::as_i64);
+      (exit_code.unwrap_or(0) == 0).into()
+    });
+  invocation.insert("executionSuccessful".into(), successful);
+  Value::Object(invocation)
+}
+
+fn rule(key: &str, value: Value) -> Value {
+  let mut from = object(value);
+  let mut rule = Map::new();
+  let id = from
+    .remove("id")
+    .and_then(|id| id.as_str().map(String::from))
+    .unwrap_or_else(|| key.into());
+  rule.insert("id".into(), id.into());
+  insert(
+    &mut rule,
+    "name",
+    from.remove("name").and_then(|name| match name {
+      Value::String(name) => Some(name.into()),
+      // 2.0.0 rule names are messages
+      Value::Object(mut name) => name.remove("text"),
+      _ => None,
+    }),
+  );
+  ["shortDescription", "fullDescription", "help"]
+    .iter()
+    .for_each(|key| {
+      insert(
+        &mut rule,
+        key,
+        from.remove(*key).and_then(multiformat_message),
+      )
+    });
+  copy(&mut from, &mut rule, &["helpUri", "guid"], is_string);
+  let message_strings: Map<String, Value> = object(
+    from
+      .remove("messageStrings")
+      .or_else(|| from.remove("messageFormats"))
+      .unwrap_or(Value::Null),
+  )
+  .into_iter()
+  .filter_map(|(id, message)| Some((id, multiformat_message(message)?)))
+  .collect();
+  if !message_strings.is_empty() {
+    rule.insert("messageStrings".into(), message_strings.into());
+  }
+  let mut configuration = Map::new();
+  match from.remove("configuration") {
+    // 1.0.0 configurations are "enabled" or "disabled"
+    Some(Value::String(enabled)) => {
+      configuration.insert("enabled".into(), (enabled != "disabled").into());
+    }
+    Some(Value::Object(mut from)) => {
+      copy(&mut from, &mut configuration, &["enabled"], is_bool);
+      insert(
+        &mut configuration,
+        "level",
+        level(from.remove("level").or_else(|| from.remove("defaultLevel"))),
+      );
+      rename(
+        &mut from,
+        &mut configuration,
+        &["rank", "defaultRank"],
+        "rank",
+        Value::is_number,
+      );
+    }
+    _ => {}
+  }
+  if let Some(level) = level(from.remove("defaultLevel")) {
+    configuration.insert("level".into(), level);
+  }
+  if !configuration.is_empty() {
+    rule.insert("defaultConfiguration".into(), configuration.into());
+  }
+  insert(
+    &mut rule,
+    "properties",
+    from.remove("properties").and_then(properties),
+  );
+  Value::Object(rule)
+}
+
+fn result(value: Value, context: &Context) -> Value {
+  let mut from = object(value);
+  let mut result = Map::new();
+
+  // rules were keyed, and the key is not necessarily the id
+  let rule_id = from
+    .remove("ruleKey")
+    .or_else(|| from.remove("ruleId"))
+    .and_then(|id| id.as_str().map(String::from))
+    .map(|id| context.rule_ids.get(&id).cloned().unwrap_or(id));
+  if let Some(rule_id) = rule_id {
+    insert(
+      &mut result,
+      "ruleIndex",
+      context.rule_indices.get(&rule_id).map(|i| json!(i)),
+    );
+    result.insert("ruleId".into(), rule_id.into());
+  }
+
+  match from.remove("level").as_ref().and_then(Value::as_str) {
+    Some(level @ "none")
+    | Some(level @ "note")
+    | Some(level @ "warning")
+    | Some(level @ "error") => {
+      result.insert("level".into(), level.into());
+    }
+    // levels which became kinds
+    Some(kind @ "pass")
+    | Some(kind @ "notApplicable")
+    | Some(kind @ "open") => {
+      result.insert("kind".into(), kind.into());
+      result.insert("level".into(), "none".into());
+    }
+    _ => {}
+  }
+  if let Some(kind) = from.remove("kind").filter(|kind| {
+    matches!(
+      kind.as_str(),
+      Some("notApplicable")
+        | Some("pass")
+        | Some("fail")
+        | Some("review")
+        | Some("open")
+        | Some("informational")
+    )
+  }) {
+    result.insert("kind".into(), kind);
+  }
+
+  let mut message = object(
+    from
+      .remove("message")
+      .and_then(self::message)
+      .unwrap_or(Value::Null),
+  );
+  if let Some(Value::Object(mut formatted)) =
+    from.remove("formattedRuleMessage")
+  {
+    rename(&mut formatted, &mut message, &["formatId"], "id", is_string);
+    copy(&mut formatted, &mut message, &["arguments"], is_strings);
+  }
+  rename(&mut from, &mut message, &["ruleMessageId"], "id", is_string);
+  if message.is_empty() {
+    message.insert("text".into(), "".into());
+  }
+  result.insert("message".into(), message.into());
+
+  let mut analysis_target = from.remove("analysisTarget");
+  let locations: Vec<Value> = array(from.remove("locations"))
+    .into_iter()
+    .filter_map(|value| {
+      let mut value = object(value);
+      // 1.0.0 locations carry the analysis target and the result file
+      if value.contains_key("resultFile") {
+        if let Some(target) = value.remove("analysisTarget") {
+          analysis_target.get_or_insert(target);
+        }
+      }
+      location(Value::Object(value), context)
+    })
+    .collect();
+  if !locations.is_empty() {
+    result.insert("locations".into(), locations.into());
+  }
+  insert(
+    &mut result,
+    "analysisTarget",
+    analysis_target.and_then(|target| {
+      let mut target = object(target);
+      match target.remove("fileLocation") {
+        Some(location) => artifact_location(location),
+        None => artifact_location(Value::Object(target)),
+      }
+    }),
+  );
+  let related: Vec<Value> = array(from.remove("relatedLocations"))
+    .into_iter()
+    .filter_map(|value| location(value, context))
+    .collect();
+  if !related.is_empty() {
+    result.insert("relatedLocations".into(), related.into());
+  }
+  let code_flows: Vec<Value> = array(from.remove("codeFlows"))
+    .into_iter()
+    .map(|value| code_flow(value, context))
+    .collect();
+  if !code_flows.is_empty() {
+    result.insert("codeFlows".into(), code_flows.into());
+  }
+  let stacks: Vec<Value> = array(from.remove("stacks"))
+    .into_iter()
+    .filter_map(|value| stack(value, context))
+    .collect();
+  if !stacks.is_empty() {
+    result.insert("stacks".into(), stacks.into());
+  }
+  let fixes: Vec<Value> = array(from.remove("fixes"))
+    .into_iter()
+    .filter_map(fix)
+    .collect();
+  if !fixes.is_empty() {
+    result.insert("fixes".into(), fixes.into());
+  }
+
+  let suppressions: Vec<Value> = array(from.remove("suppressionStates"))
+    .into_iter()
+    .filter_map(|state| match state.as_str()? {
+      "suppressedInSource" => Some(json!({ "kind": "inSource" })),
+      "suppressedExternally" => Some(json!({ "kind": "external" })),
+      _ => None,
+    })
+    .collect();
+  if !suppressions.is_empty() {
+    result.insert("suppressions".into(), suppressions.into());
+  }
+  insert(
+    &mut result,
+    "baselineState",
+    from
+      .remove("baselineState")
+      .and_then(|state| match state.as_str()? {
+        "existing" => Some("unchanged".into()),
+        state @ ("new" | "unchanged" | "updated" | "absent") => {
+          Some(state.into())
+        }
+        _ => None,
+      }),
+  );
+
+  copy(
+    &mut from,
+    &mut result,
+    &["fingerprints", "partialFingerprints"],
+    is_string_map,
+  );
+  rename(
+    &mut from,
+    &mut result,
+    &["guid", "instanceGuid"],
+    "guid",
+    is_string,
+  );
+  copy(
+    &mut from,
+    &mut result,
+    &["correlationGuid", "hostedViewerUri"],
+    is_string,
+  );
+  copy(&mut from, &mut result, &["occurrenceCount"], is_integer);
+  copy(&mut from, &mut result, &["rank"], Value::is_number);
+  copy(&mut from, &mut result, &["workItemUris"], is_strings);
+  let mut properties = from
+    .remove("properties")
+    .and_then(properties)
+    .map(object)
+    .unwrap_or_default();
+  // 1.0.0 results have their own tags
+  if let Some(tags) = from.remove("tags").filter(is_strings) {
+    properties.insert("tags".into(), tags);
+  }
+  if !properties.is_empty() {
+    result.insert("properties".into(), properties.into());
+  }
+  Value::Object(result)
+}
+
+fn upgrade_run(mut from: Map<String, Value>) -> Value {
+  let mut run = Map::new();
+  let mut context = Context::default();
+
+  // 2.1.0 drafts already have a driver, older versions describe the tool
+  // itself
+  let mut tool = object(from.remove("tool").unwrap_or(Value::Null));
+  let mut driver_from = match tool.remove("driver") {
+    Some(driver) => object(driver),
+    None => tool,
+  };
+  let mut driver = Map::new();
+  copy(
+    &mut driver_from,
+    &mut driver,
+    &[
+      "name",
+      "fullName",
+      "version",
+      "semanticVersion",
+      "dottedQuadFileVersion",
+      "downloadUri",
+      "informationUri",
+      "organization",
+      "product",
+      "language",
+      "guid",
+    ],
+    is_string,
+  );
+  if !driver.contains_key("name") {
+    let name = driver.get("fullName").cloned().unwrap_or_else(|| "".into());
+    driver.insert("name".into(), name);
+  }
+
+  // 1.0.0 rules are keyed in the run, 2.0.0 rules in the run's resources
+  let mut resources = object(from.remove("resources").unwrap_or(Value::Null));
+  let rules = driver_from
+    .remove("rules")
+    .or_else(|| resources.remove("rules"))
+    .or_else(|| from.remove("rules"));
+  let rules: Vec<Value> = match rules {
+    Some(Value::Object(rules)) => rules
+      .into_iter()
+      .map(|(key, value)| {
+        let rule = rule(&key, value);
+        if let Some(id) = rule.get("id").and_then(Value::as_str) {
+          context.rule_ids.insert(key, id.into());
+        }
+        rule
+      })
+      .collect(),
+    rules => array(rules)
+      .into_iter()
+      .map(|value| rule("", value))
+      .collect(),
+  };
+  rules.iter().enumerate().for_each(|(index, rule)| {
+    if let Some(id) = rule.get("id").and_then(Value::as_str) {
+      context.rule_indices.entry(id.into()).or_insert(index);
+    }
+  });
+  if !rules.is_empty() {
+    driver.insert("rules".into(), rules.into());
+  }
+  let global_message_strings: Map<String, Value> = object(
+    driver_from
+      .remove("globalMessageStrings")
+      .or_else(|| resources.remove("messageStrings"))
+      .unwrap_or(Value::Null),
+  )
+  .into_iter()
+  .filter_map(|(id, message)| Some((id, multiformat_message(message)?)))
+  .collect();
+  if !global_message_strings.is_empty() {
+    driver.insert("globalMessageStrings".into(), global_message_strings.into());
+  }
+  run.insert("tool".into(), json!({ "driver": driver }));
+
+  // artifacts, keyed by uri before 2.1.0
+  let files = from.remove("artifacts").or_else(|| from.remove("files"));
+  let keyed: Vec<(Option<String>, Map<String, Value>)> = match files {
+    Some(Value::Object(files)) => files
+      .into_iter()
+      .map(|(uri, file)| (Some(uri), object(file)))
+      .collect(),
+    files => array(files)
+      .into_iter()
+      .map(|file| (None, object(file)))
+      .collect(),
+  };
+  keyed.iter().enumerate().for_each(|(index, (uri, _))| {
+    if let Some(uri) = uri {
+      context.artifact_indices.insert(uri.clone(), index);
+    }
+  });
+  let artifacts: Vec<Value> = keyed
+    .into_iter()
+    .map(|(uri, mut from)| {
+      let mut artifact = Map::new();
+      let location = from
+        .remove("location")
+        .or_else(|| from.remove("fileLocation"))
+        .and_then(artifact_location)
+        .or_else(|| uri.map(|uri| json!({ "uri": uri })));
+      insert(&mut artifact, "location", location);
+      let parent = from
+        .remove("parentKey")
+        .and_then(|key| context.artifact_indices.get(key.as_str()?).copied())
+        .map(|index| json!(index))
+        .or_else(|| from.remove("parentIndex").filter(is_integer));
+      insert(&mut artifact, "parentIndex", parent);
+      copy(
+        &mut from,
+        &mut artifact,
+        &[
+          "mimeType",
+          "encoding",
+          "sourceLanguage",
+          "lastModifiedTimeUtc",
+        ],
+        is_string,
+      );
+      copy(&mut from, &mut artifact, &["length", "offset"], is_integer);
+      copy(&mut from, &mut artifact, &["roles"], is_strings);
+      let contents = match from.remove("contents") {
+        // 1.0.0 contents are base64 encoded
+        Some(Value::String(binary)) => Some(json!({ "binary": binary })),
+        Some(Value::Object(mut from)) => {
+          let mut contents = Map::new();
+          copy(&mut from, &mut contents, &["text", "binary"], is_string);
+          Some(Value::Object(contents))
+        }
+        _ => None,
+      };
+      insert(&mut artifact, "contents", contents);
+      let hashes = match from.remove("hashes") {
+        // 1.0.0 hashes are a list of algorithm and value pairs
+        Some(Value::Array(hashes)) => Some(
+          hashes
+            .into_iter()
+            .filter_map(|hash| {
+              let mut hash = object(hash);
+              let algorithm = hash.remove("algorithm")?.as_str()?.to_string();
+              Some((algorithm, hash.remove("value").filter(is_string)?))
+            })
+            .collect::<Map<String, Value>>()
+            .into(),
+        ),
+        hashes => hashes.filter(is_string_map),
+      };
+      insert(&mut artifact, "hashes", hashes);
+      insert(
+        &mut artifact,
+        "properties",
+        from.remove("properties").and_then(properties),
+      );
+      Value::Object(artifact)
+    })
+    .collect();
+  if !artifacts.is_empty() {
+    run.insert("artifacts".into(), artifacts.into());
+  }
+
+  // logical locations, keyed by their fully qualified name before 2.1.0
+  let keyed: Vec<(Option<String>, Map<String, Value>)> =
+    match from.remove("logicalLocations") {
+      Some(Value::Object(locations)) => locations
+        .into_iter()
+        .map(|(name, location)| (Some(name), object(location)))
+        .collect(),
+      locations => array(locations)
+        .into_iter()
+        .map(|location| (None, object(location)))
+        .collect(),
+    };
+  keyed
+    .iter()
+    .enumerate()
+    .for_each(|(index, (name, location))| {
+      let name = name.clone().or_else(|| {
+        location
+          .get("fullyQualifiedName")
+          .and_then(Value::as_str)
+          .map(String::from)
+      });
+      if let Some(name) = name {
+        context.logical_location_indices.insert(name, index);
+      }
+    });
+  let logical_locations: Vec<Value> = keyed
+    .into_iter()
+    .map(|(name, mut from)| {
+      let mut location = Map::new();
+      copy(
+        &mut from,
+        &mut location,
+        &["name", "decoratedName", "kind"],
+        is_string,
+      );
+      rename(
+        &mut from,
+        &mut location,
+        &["fullyQualifiedName"],
+        "fullyQualifiedName",
+        is_string,
+      );
+      if let Some(name) = name {
+        location
+          .entry("fullyQualifiedName")
+          .or_insert_with(|| name.into());
+      }
+      let parent = from
+        .remove("parentKey")
+        .and_then(|key| {
+          context.logical_location_indices.get(key.as_str()?).copied()
+        })
+        .map(|index| json!(index))
+        .or_else(|| from.remove("parentIndex").filter(is_integer));
+      insert(&mut location, "parentIndex", parent);
+      Value::Object(location)
+    })
+    .collect();
+  if !logical_locations.is_empty() {
+    run.insert("logicalLocations".into(), logical_locations.into());
+  }
+
+  // 1.0.0 runs have a single invocation, and their notifications
+  let mut invocations: Vec<Value> = match from.remove("invocation") {
+    Some(invocation) => vec![invocation],
+    None => array(from.remove("invocations")),
+  };
+  [
+    ("toolNotifications", "toolExecutionNotifications"),
+    (
+      "configurationNotifications",
+      "toolConfigurationNotifications",
+    ),
+  ]
+  .iter()
+  .for_each(|(old, key)| {
+    if let Some(notifications) = from.remove(*old) {
+      if invocations.is_empty() {
+        invocations.push(json!({}));
+      }
+      if let Some(invocation) = invocations[0].as_object_mut() {
+        invocation.entry(*key).or_insert(notifications);
+      }
+    }
+  });
+  let invocations: Vec<Value> = invocations
+    .into_iter()
+    .map(|value| invocation(value, &context))
+    .collect();
+  if !invocations.is_empty() {
+    run.insert("invocations".into(), invocations.into());
+  }
+
+  let results: Vec<Value> = array(from.remove("results"))
+    .into_iter()
+    .map(|value| result(value, &context))
+    .collect();
+  run.insert("results".into(), results.into());
+
+  let original_uri_base_ids: Map<String, Value> =
+    object(from.remove("originalUriBaseIds").unwrap_or(Value::Null))
+      .into_iter()
+      .filter_map(|(id, location)| Some((id, artifact_location(location)?)))
+      .collect();
+  if !original_uri_base_ids.is_empty() {
+    run.insert("originalUriBaseIds".into(), original_uri_base_ids.into());
+  }
+
+  let mut automation_details = Map::new();
+  match from.remove("id") {
+    Some(Value::Object(mut id)) => {
+      rename(
+        &mut id,
+        &mut automation_details,
+        &["instanceId"],
+        "id",
+        is_string,
+      );
+      rename(
+        &mut id,
+        &mut automation_details,
+        &["instanceGuid"],
+        "guid",
+        is_string,
+      );
+      copy(
+        &mut id,
+        &mut automation_details,
+        &["correlationGuid"],
+        is_string,
+      );
+    }
+    Some(Value::String(id)) => {
+      automation_details.insert("id".into(), id.into());
+    }
+    _ => {}
+  }
+  if let Some(Value::Object(mut details)) = from.remove("automationDetails") {
+    copy(
+      &mut details,
+      &mut automation_details,
+      &["id", "guid", "correlationGuid"],
+      is_string,
+    );
+  }
+  rename(
+    &mut from,
+    &mut automation_details,
+    &["automationId", "automationLogicalId"],
+    "id",
+    is_string,
+  );
+  if !automation_details.is_empty() {
+    run.insert("automationDetails".into(), automation_details.into());
+  }
+  rename(
+    &mut from,
+    &mut run,
+    &["baselineGuid", "baselineInstanceGuid", "baselineId"],
+    "baselineGuid",
+    is_string,
+  );
+  rename(
+    &mut from,
+    &mut run,
+    &["defaultEncoding", "defaultFileEncoding"],
+    "defaultEncoding",
+    is_string,
+  );
+  copy(
+    &mut from,
+    &mut run,
+    &["defaultSourceLanguage", "language"],
+    is_string,
+  );
+  if let Some(kind) = from.remove("columnKind").filter(|kind| {
+    matches!(
+      kind.as_str(),
+      Some("utf16CodeUnits") | Some("unicodeCodePoints")
+    )
+  }) {
+    run.insert("columnKind".into(), kind);
+  }
+  match from.remove("redactionToken") {
+    Some(Value::String(token)) => {
+      run.insert("redactionTokens".into(), json!([token]));
+    }
+    _ => copy(&mut from, &mut run, &["redactionTokens"], is_strings),
+  }
+  insert(
+    &mut run,
+    "properties",
+    from.remove("properties").and_then(properties),
+  );
+  Value::Object(run)
+}
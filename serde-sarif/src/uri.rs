@@ -0,0 +1,257 @@
+//! Resolution of artifact locations to absolute URIs and paths.
+//!
+//! The `uri` of a SARIF `artifactLocation` may be relative, in which case its
+//! `uriBaseId` names the base it is relative to. Base ids are defined in the
+//! run's `originalUriBaseIds`, where they may be relative to other base ids in
+//! turn, or left for the consumer of the log to define, ex. `SRCROOT` for the
+//! root of a checkout which is in a different place on every machine.
+//!
+//! [UriResolver] follows these chains as described by §3.14.14 of the SARIF
+//! specification: base URIs are treated as directories even if they lack the
+//! trailing slash, percent encoded URIs are decoded when converted to paths,
+//! and Windows style drive paths (`C:\src\main.c`) are accepted as well as
+//! `file://` URIs. Base ids supplied by the caller take precedence over those
+//! defined in the log.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::sarif::{ArtifactLocationBuilder, Run};
+//! use serde_sarif::uri::{parse_uri_base, UriResolver};
+//!
+//! let run: Run = serde_json::from_value(serde_json::json!({
+//!   "tool": { "driver": { "name": "clippy" } },
+//!   "originalUriBaseIds": {
+//!     "SRCROOT": {},
+//!     "LIB": { "uri": "lib", "uriBaseId": "SRCROOT" }
+//!   }
+//! }))
+//! .unwrap();
+//! let location = ArtifactLocationBuilder::default()
+//!   .uri("my%20file.rs")
+//!   .uri_base_id("LIB")
+//!   .build()
+//!   .unwrap();
+//!
+//! let (id, base) = parse_uri_base("SRCROOT=file:///src").unwrap();
+//! let resolver = UriResolver::new().uri_base(id, base);
+//! let url = resolver.resolve(&run, &location).unwrap();
+//! assert_eq!(url.as_str(), "file:///src/lib/my%20file.rs");
+//! # #[cfg(unix)]
+//! assert_eq!(
+//!   resolver.resolve_path(&run, &location).unwrap(),
+//!   std::path::PathBuf::from("/src/lib/my file.rs")
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use thiserror::Error;
+pub use url::Url;
+
+use crate::sarif;
+
+/// An error resolving an artifact location.
+#[derive(Error, Debug)]
+pub enum UriError {
+  #[error("the artifact location has no uri")]
+  MissingUri,
+  #[error("uriBaseId {0} is not defined")]
+  UndefinedUriBaseId(String),
+  #[error("uriBaseId {0} is defined in terms of itself")]
+  CyclicUriBaseId(String),
+  #[error("the relative uri {0} has no base to be resolved against")]
+  RelativeUri(String),
+  #[error("invalid uri {uri}: {source}")]
+  InvalidUri {
+    uri: String,
+    source: url::ParseError,
+  },
+  #[error("{0} is not a file uri")]
+  NotAFile(Url),
+  #[error("invalid uri base {0}, expected ID=URI")]
+  InvalidUriBase(String),
+}
+
+/// Resolves artifact locations to absolute URIs.
+#[derive(Clone, Debug, Default)]
+pub struct UriResolver {
+  uri_bases: BTreeMap<String, Url>,
+  default_base: Option<Url>,
+}
+
+impl UriResolver {
+  /// Creates a resolver which only knows the base ids defined by the logs
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// Defines (or overrides) a base id
+  ///
+  /// # Arguments
+  ///
+  /// * `id` - The base id, ex. `SRCROOT`
+  /// * `base` - The absolute URI it stands for
+  pub fn uri_base<S: Into<String>>(mut self, id: S, base: Url) -> Self {
+    self.uri_bases.insert(id.into(), directory(base));
+    self
+  }
+
+  /// Sets the base relative URIs without a base id are resolved against,
+  /// usually the directory the tool was run in
+  ///
+  /// # Arguments
+  ///
+  /// * `base` - The absolute base URI
+  pub fn default_base(mut self, base: Url) -> Self {
+    self.default_base = Some(directory(base));
+    self
+  }
+
+  /// Resolves an artifact location of `run` to an absolute URI
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run whose `originalUriBaseIds` the location may refer to
+  /// * `location` - The artifact location to resolve
+  pub fn resolve(
+    &self,
+    run: &sarif::Run,
+    location: &sarif::ArtifactLocation,
+  ) -> Result<Url, UriError> {
+    self.resolve_location(run, location, &mut vec![])
+  }
+
+  /// Resolves an artifact location of `run` to a path on the local file
+  /// system
+  ///
+  /// # Arguments
+  ///
+  /// * `run` - The run whose `originalUriBaseIds` the location may refer to
+  /// * `location` - The artifact location to resolve
+  pub fn resolve_path(
+    &self,
+    run: &sarif::Run,
+    location: &sarif::ArtifactLocation,
+  ) -> Result<PathBuf, UriError> {
+    let url = self.resolve(run, location)?;
+    url.to_file_path().map_err(|_| UriError::NotAFile(url))
+  }
+
+  fn resolve_location(
+    &self,
+    run: &sarif::Run,
+    location: &sarif::ArtifactLocation,
+    seen: &mut Vec<String>,
+  ) -> Result<Url, UriError> {
+    let uri = location.uri.as_deref().ok_or(UriError::MissingUri)?;
+    // the uriBaseId of an absolute uri is ignored
+    if let Some(url) = absolute(uri)? {
+      return Ok(url);
+    }
+    let base = match location.uri_base_id.as_ref() {
+      Some(id) => self.base(run, id, seen)?,
+      None => match self.default_base.clone() {
+        Some(base) => base,
+        None if uri.starts_with('/') => file_root(),
+        None => return Err(UriError::RelativeUri(uri.into())),
+      },
+    };
+    base
+      .join(&uri.replace('\\', "/"))
+      .map_err(|source| UriError::InvalidUri {
+        uri: uri.into(),
+        source,
+      })
+  }
+
+  fn base(
+    &self,
+    run: &sarif::Run,
+    id: &str,
+    seen: &mut Vec<String>,
+  ) -> Result<Url, UriError> {
+    if let Some(base) = self.uri_bases.get(id) {
+      return Ok(base.clone());
+    }
+    if seen.iter().any(|seen| seen == id) {
+      return Err(UriError::CyclicUriBaseId(id.into()));
+    }
+    // base ids without a uri are left for the consumer to define
+    let location = run
+      .original_uri_base_ids
+      .as_ref()
+      .and_then(|ids| ids.get(id))
+      .filter(|location| location.uri.is_some())
+      .ok_or_else(|| UriError::UndefinedUriBaseId(id.into()))?;
+    seen.push(id.into());
+    let base = self.resolve_location(run, location, seen);
+    seen.pop();
+    base.map(directory)
+  }
+}
+
+fn file_root() -> Url {
+  Url::parse("file:///").expect("file:/// is a valid url")
+}
+
+// Base URIs SHALL end with a slash, but some producers omit it, which would
+// make joining drop their last segment
+fn directory(mut url: Url) -> Url {
+  if !url.path().ends_with('/') {
+    let path = format!("{}/", url.path());
+    url.set_path(&path);
+  }
+  url
+}
+
+fn is_drive(uri: &str) -> bool {
+  let bytes = uri.as_bytes();
+  bytes.len() >= 3
+    && bytes[0].is_ascii_alphabetic()
+    && bytes[1] == b':'
+    && (bytes[2] == b'/' || bytes[2] == b'\\')
+}
+
+// Parses uris which are absolute, including Windows drive paths, which would
+// otherwise parse as a uri with a single letter scheme
+fn absolute(uri: &str) -> Result<Option<Url>, UriError> {
+  let parsed = if is_drive(uri) {
+    Url::parse(&format!("file:///{}", uri.replace('\\', "/")))
+  } else {
+    match Url::parse(uri) {
+      Err(url::ParseError::RelativeUrlWithoutBase) => return Ok(None),
+      parsed => parsed,
+    }
+  };
+  parsed.map(Some).map_err(|source| UriError::InvalidUri {
+    uri: uri.into(),
+    source,
+  })
+}
+
+/// Parses a base id definition of the form `ID=URI`, ex. `SRCROOT=/src`, as
+/// accepted on the command line. The URI may also be a path, which is made
+/// absolute relative to the current directory.
+///
+/// # Arguments
+///
+/// * `definition` - The base id definition
+pub fn parse_uri_base(definition: &str) -> Result<(String, Url), UriError> {
+  let (id, uri) = definition
+    .split_once('=')
+    .filter(|(id, uri)| !id.is_empty() && !uri.is_empty())
+    .ok_or_else(|| UriError::InvalidUriBase(definition.into()))?;
+  let url = match absolute(uri) {
+    Ok(Some(url)) if url.scheme().len() > 1 => url,
+    _ => {
+      let path = std::env::current_dir()
+        .map(|dir| dir.join(uri))
+        .map_err(|_| UriError::InvalidUriBase(definition.into()))?;
+      Url::from_directory_path(path)
+        .map_err(|_| UriError::InvalidUriBase(definition.into()))?
+    }
+  };
+  Ok((id.into(), directory(url)))
+}
@@ -0,0 +1,475 @@
+//! Validation of SARIF documents against the bundled SARIF 2.1.0 JSON schema.
+//!
+//! The validator understands the subset of JSON schema (draft-07) keywords
+//! used by the SARIF schema, which is enough to catch documents that would
+//! be rejected by consumers such as Github code scanning.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::validate::validate_schema;
+//!
+//! let value = serde_json::json!({ "version": "2.1.0", "runs": [{}] });
+//! let violations = validate_schema(&value);
+//!
+//! assert_eq!(violations.len(), 1);
+//! assert_eq!(violations[0].pointer, "/runs/0");
+//! ```
+//!
+//! Requirements of the specification which the schema cannot express are
+//! checked by the [rules] module, and [report] checks a log against both.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::sarif;
+
+pub mod rules;
+
+pub use rules::{validate_rules, Rule, RuleViolation, RULES};
+
+static SCHEMA: OnceLock<Value> = OnceLock::new();
+
+fn schema() -> &'static Value {
+  SCHEMA.get_or_init(|| {
+    serde_json::from_str(include_str!("../schema.json"))
+      .expect("bundled schema.json is valid JSON")
+  })
+}
+
+/// A single place where a document does not conform to the SARIF schema.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SchemaViolation {
+  /// A JSON pointer (RFC 6901) to the offending value.
+  pub pointer: String,
+  /// A human readable description of the violation.
+  pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let pointer = if self.pointer.is_empty() {
+      "/"
+    } else {
+      &self.pointer
+    };
+    write!(f, "{}: {}", pointer, self.message)
+  }
+}
+
+/// Returns every violation of the SARIF schema found in `value`
+///
+/// An empty list means that the document is valid.
+///
+/// # Arguments
+///
+/// * `value` - A JSON document, typically an entire SARIF log
+pub fn validate_schema(value: &Value) -> Vec<SchemaViolation> {
+  let mut validator = Validator::default();
+  validator.validate(schema(), value, &mut String::new());
+  validator.violations
+}
+
+/// An error reading a log to validate or writing its report.
+#[derive(Error, Debug)]
+pub enum ValidateError {
+  #[error(transparent)]
+  Io(#[from] std::io::Error),
+  #[error(transparent)]
+  Json(#[from] serde_json::Error),
+}
+
+/// Validates a log against the schema and, if it deserializes, the semantic
+/// [RULES], writing each violation on a line of its own. Returns whether the
+/// log is valid, i.e. has no schema violations and no error level rule
+/// violations.
+///
+/// The raw JSON is validated rather than a deserialized [sarif::Sarif] so
+/// that properties unknown to the schema are reported too.
+///
+/// # Arguments
+///
+/// * `reader` - A reader of the log
+/// * `writer` - The writer the violations are written to, ex. stderr
+pub fn report<R: Read, W: Write>(
+  reader: R,
+  mut writer: W,
+) -> Result<bool, ValidateError> {
+  let value: Value = serde_json::from_reader(reader)?;
+  let violations = validate_schema(&value);
+  for violation in &violations {
+    writeln!(writer, "error[schema]: {}", violation)?;
+  }
+  let mut valid = violations.is_empty();
+  if let Ok(sarif) = serde_json::from_value::<sarif::Sarif>(value) {
+    for violation in validate_rules(&sarif) {
+      valid &= violation.rule.level != sarif::ResultLevel::Error;
+      writeln!(writer, "{}", violation)?;
+    }
+  }
+  Ok(valid)
+}
+
+impl sarif::Sarif {
+  /// Returns every violation of the SARIF schema found in this log
+  ///
+  /// Only the fields modelled by [sarif::Sarif] take part in the validation,
+  /// use [validate_schema] to validate a raw JSON document.
+  pub fn validate(&self) -> Vec<SchemaViolation> {
+    match serde_json::to_value(self) {
+      Ok(value) => validate_schema(&value),
+      Err(e) => vec![SchemaViolation {
+        pointer: String::new(),
+        message: e.to_string(),
+      }],
+    }
+  }
+}
+
+#[derive(Default)]
+struct Validator {
+  violations: Vec<SchemaViolation>,
+  patterns: HashMap<String, Option<Regex>>,
+}
+
+impl Validator {
+  fn report(&mut self, pointer: &str, message: String) {
+    self.violations.push(SchemaViolation {
+      pointer: pointer.into(),
+      message,
+    });
+  }
+
+  // Returns true if value is valid under schema, without recording violations
+  fn is_valid(&mut self, schema: &Value, value: &Value) -> bool {
+    let mut validator = Validator {
+      violations: vec![],
+      patterns: std::mem::take(&mut self.patterns),
+    };
+    validator.validate(schema, value, &mut String::new());
+    self.patterns = validator.patterns;
+    validator.violations.is_empty()
+  }
+
+  fn validate(&mut self, schema: &Value, value: &Value, pointer: &mut String) {
+    let schema = match schema {
+      Value::Object(schema) => schema,
+      Value::Bool(false) => {
+        self.report(pointer, "no value is allowed here".into());
+        return;
+      }
+      _ => return,
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+      match resolve_ref(reference) {
+        Some(schema) => self.validate(schema, value, pointer),
+        None => {
+          self.report(pointer, format!("unresolvable reference {}", reference))
+        }
+      }
+      return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+      let types: Vec<&str> = match expected {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+      };
+      if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+        self.report(
+          pointer,
+          format!(
+            "expected {}, found {}",
+            types.join(" or "),
+            type_name(value)
+          ),
+        );
+        return;
+      }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+      if !allowed.contains(value) {
+        self.report(
+          pointer,
+          format!("{} is not one of {}", value, Value::Array(allowed.clone())),
+        );
+      }
+    }
+
+    self.validate_combinators(schema, value, pointer);
+
+    match value {
+      Value::Object(object) => self.validate_object(schema, object, pointer),
+      Value::Array(array) => self.validate_array(schema, array, pointer),
+      Value::String(string) => self.validate_string(schema, string, pointer),
+      Value::Number(number) => {
+        if let Some(number) = number.as_f64() {
+          self.validate_number(schema, number, pointer)
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn validate_combinators(
+    &mut self,
+    schema: &Map<String, Value>,
+    value: &Value,
+    pointer: &str,
+  ) {
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+      schemas.iter().for_each(|schema| {
+        self.validate(schema, value, &mut pointer.to_string())
+      });
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+      if !schemas.iter().any(|schema| self.is_valid(schema, value)) {
+        self.report(
+          pointer,
+          format!("expected one of {}, found none", describe(schemas)),
+        );
+      }
+    }
+
+    if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+      let matches = schemas
+        .iter()
+        .filter(|schema| self.is_valid(schema, value))
+        .count();
+      if matches != 1 {
+        self.report(
+          pointer,
+          format!(
+            "expected exactly one of {}, found {}",
+            describe(schemas),
+            matches
+          ),
+        );
+      }
+    }
+  }
+
+  fn validate_object(
+    &mut self,
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    pointer: &mut String,
+  ) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+      required
+        .iter()
+        .filter_map(Value::as_str)
+        .filter(|name| !object.contains_key(*name))
+        .for_each(|name| {
+          self
+            .report(pointer, format!("missing required property \"{}\"", name))
+        });
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    object.iter().for_each(|(name, property)| {
+      let len = pointer.len();
+      push_pointer(pointer, name);
+      match properties.and_then(|properties| properties.get(name)) {
+        Some(property_schema) => {
+          self.validate(property_schema, property, pointer)
+        }
+        None => match schema.get("additionalProperties") {
+          Some(Value::Bool(false)) => {
+            pointer.truncate(len);
+            self.report(
+              pointer,
+              format!("additional property \"{}\" is not allowed", name),
+            );
+          }
+          Some(additional) => self.validate(additional, property, pointer),
+          None => {}
+        },
+      }
+      pointer.truncate(len);
+    });
+  }
+
+  fn validate_array(
+    &mut self,
+    schema: &Map<String, Value>,
+    array: &[Value],
+    pointer: &mut String,
+  ) {
+    if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
+      if (array.len() as u64) < min_items {
+        self.report(
+          pointer,
+          format!(
+            "expected at least {} items, found {}",
+            min_items,
+            array.len()
+          ),
+        );
+      }
+    }
+
+    if let Some(Value::Bool(true)) = schema.get("uniqueItems") {
+      if let Some((i, _)) = array
+        .iter()
+        .enumerate()
+        .find(|(i, item)| array[..*i].contains(item))
+      {
+        self.report(
+          pointer,
+          format!("items are not unique, item {} is a duplicate", i),
+        );
+      }
+    }
+
+    if let Some(items) = schema.get("items") {
+      array.iter().enumerate().for_each(|(i, item)| {
+        let len = pointer.len();
+        push_pointer(pointer, &i.to_string());
+        self.validate(items, item, pointer);
+        pointer.truncate(len);
+      });
+    }
+  }
+
+  fn validate_string(
+    &mut self,
+    schema: &Map<String, Value>,
+    string: &str,
+    pointer: &str,
+  ) {
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+      let regex = self
+        .patterns
+        .entry(pattern.into())
+        .or_insert_with(|| Regex::new(pattern).ok());
+      if let Some(regex) = regex {
+        if !regex.is_match(string) {
+          self.report(
+            pointer,
+            format!("\"{}\" does not match pattern \"{}\"", string, pattern),
+          );
+        }
+      }
+    }
+
+    if let Some(format) = schema.get("format").and_then(Value::as_str) {
+      if !has_format(string, format) {
+        self
+          .report(pointer, format!("\"{}\" is not a valid {}", string, format));
+      }
+    }
+  }
+
+  fn validate_number(
+    &mut self,
+    schema: &Map<String, Value>,
+    number: f64,
+    pointer: &str,
+  ) {
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+      if number < minimum {
+        self.report(
+          pointer,
+          format!("{} is less than the minimum of {}", number, minimum),
+        );
+      }
+    }
+
+    if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+      if number > maximum {
+        self.report(
+          pointer,
+          format!("{} is greater than the maximum of {}", number, maximum),
+        );
+      }
+    }
+  }
+}
+
+fn resolve_ref(reference: &str) -> Option<&'static Value> {
+  reference
+    .strip_prefix('#')
+    .and_then(|pointer| schema().pointer(pointer))
+}
+
+fn push_pointer(pointer: &mut String, token: &str) {
+  pointer.push('/');
+  pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+  match expected {
+    "object" => value.is_object(),
+    "array" => value.is_array(),
+    "string" => value.is_string(),
+    "boolean" => value.is_boolean(),
+    "null" => value.is_null(),
+    "number" => value.is_number(),
+    "integer" => {
+      value.is_i64()
+        || value.is_u64()
+        || value.as_f64().is_some_and(|f| f.fract() == 0.0)
+    }
+    _ => true,
+  }
+}
+
+fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "boolean",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+fn has_format(string: &str, format: &str) -> bool {
+  static URI: OnceLock<Regex> = OnceLock::new();
+  static DATE_TIME: OnceLock<Regex> = OnceLock::new();
+  match format {
+    "uri" => URI
+      .get_or_init(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.\-]*:\S*$").unwrap())
+      .is_match(string),
+    "uri-reference" => !string.chars().any(char::is_whitespace),
+    "date-time" => DATE_TIME
+      .get_or_init(|| {
+        Regex::new(
+          r"^\d{4}-\d{2}-\d{2}[Tt]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+\-]\d{2}:\d{2})$",
+        )
+        .unwrap()
+      })
+      .is_match(string),
+    _ => true,
+  }
+}
+
+// Summarizes the schemas of an anyOf / oneOf, which in the SARIF schema are
+// always lists of required properties
+fn describe(schemas: &[Value]) -> String {
+  let alternatives: Vec<String> = schemas
+    .iter()
+    .map(|schema| match schema.get("required") {
+      Some(Value::Array(required)) => required
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<_>>()
+        .join(" and "),
+      _ => schema.to_string(),
+    })
+    .collect();
+  alternatives.join(" or ")
+}
@@ -0,0 +1,655 @@
+//! Semantic validation of SARIF logs.
+//!
+//! Many of the requirements in the SARIF specification cannot be expressed
+//! in the JSON schema, for example that a `ruleIndex` refers to an existing
+//! rule. Logs violating them deserialize just fine but are silently
+//! misinterpreted by consumers. Each requirement is checked by a [Rule],
+//! which has a stable id and a level describing how severe a violation is.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::sarif::Sarif;
+//!
+//! let sarif: Sarif = serde_json::from_value(serde_json::json!({
+//!   "version": "2.1.0",
+//!   "runs": [{
+//!     "tool": { "driver": { "name": "tool" } },
+//!     "results": [{ "message": { "text": "message" }, "ruleIndex": 0 }]
+//!   }]
+//! })).unwrap();
+//!
+//! let violations = sarif.validate_rules();
+//! assert_eq!(violations[0].rule.id, "rule-index-out-of-range");
+//! assert_eq!(violations[0].pointer, "/runs/0/results/0/ruleIndex");
+//! ```
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::sarif;
+
+type Check = fn(&sarif::Run, &mut Vec<(String, String)>);
+
+/// A single semantic requirement of the SARIF specification.
+pub struct Rule {
+  /// A stable identifier for the rule.
+  pub id: &'static str,
+  /// How severe a violation of the rule is.
+  pub level: sarif::ResultLevel,
+  /// A description of what the rule checks.
+  pub description: &'static str,
+  check: Check,
+}
+
+impl fmt::Debug for Rule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Rule")
+      .field("id", &self.id)
+      .field("level", &self.level)
+      .field("description", &self.description)
+      .finish()
+  }
+}
+
+/// A place where a log violates a [Rule].
+#[derive(Debug)]
+pub struct RuleViolation {
+  /// The rule which is violated.
+  pub rule: &'static Rule,
+  /// A JSON pointer (RFC 6901) to the offending value.
+  pub pointer: String,
+  /// A human readable description of the violation.
+  pub message: String,
+}
+
+impl fmt::Display for RuleViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}[{}]: {}: {}",
+      self.rule.level, self.rule.id, self.pointer, self.message
+    )
+  }
+}
+
+/// All rules checked by [validate_rules].
+pub static RULES: &[Rule] = &[
+  Rule {
+    id: "rule-index-out-of-range",
+    level: sarif::ResultLevel::Error,
+    description: "A result's ruleIndex or rule.index SHALL refer to a rule of the tool component which defines it.",
+    check: check_rule_index,
+  },
+  Rule {
+    id: "rule-id-mismatch",
+    level: sarif::ResultLevel::Error,
+    description: "A result's ruleId, rule.id and the id of the rule it indexes SHALL agree.",
+    check: check_rule_id,
+  },
+  Rule {
+    id: "duplicate-rule-id",
+    level: sarif::ResultLevel::Error,
+    description: "The rules of a tool component SHALL have distinct ids.",
+    check: check_duplicate_rule_id,
+  },
+  Rule {
+    id: "undefined-uri-base-id",
+    level: sarif::ResultLevel::Warning,
+    description: "A uriBaseId SHOULD be defined in the run's originalUriBaseIds.",
+    check: check_uri_base_id,
+  },
+  Rule {
+    id: "region-end-before-start",
+    level: sarif::ResultLevel::Error,
+    description: "A region's end SHALL NOT come before its start.",
+    check: check_region,
+  },
+  Rule {
+    id: "artifact-index-out-of-range",
+    level: sarif::ResultLevel::Error,
+    description: "An artifactLocation's index SHALL refer to an element of the run's artifacts.",
+    check: check_artifact_index,
+  },
+  Rule {
+    id: "thread-flow-location-index-out-of-range",
+    level: sarif::ResultLevel::Error,
+    description: "A threadFlowLocation's index SHALL refer to an element of the run's threadFlowLocations.",
+    check: check_thread_flow_location_index,
+  },
+  Rule {
+    id: "invocation-index-out-of-range",
+    level: sarif::ResultLevel::Error,
+    description: "A result's provenance.invocationIndex SHALL refer to an element of the run's invocations.",
+    check: check_invocation_index,
+  },
+  Rule {
+    id: "undefined-message-id",
+    level: sarif::ResultLevel::Error,
+    description: "A message without text SHALL have an id defined in the rule's messageStrings or the tool component's globalMessageStrings.",
+    check: check_message_id,
+  },
+];
+
+/// Returns every violation of the [RULES] found in `sarif`
+///
+/// # Arguments
+///
+/// * `sarif` - The SARIF log to validate
+pub fn validate_rules(sarif: &sarif::Sarif) -> Vec<RuleViolation> {
+  let mut violations = vec![];
+  sarif.runs.iter().enumerate().for_each(|(i, run)| {
+    RULES.iter().for_each(|rule| {
+      let mut findings = vec![];
+      (rule.check)(run, &mut findings);
+      violations.extend(findings.into_iter().map(|(pointer, message)| {
+        RuleViolation {
+          rule,
+          pointer: format!("/runs/{}{}", i, pointer),
+          message,
+        }
+      }))
+    })
+  });
+  violations
+}
+
+impl sarif::Sarif {
+  /// Returns every violation of the semantic validation [RULES] found in
+  /// this log
+  pub fn validate_rules(&self) -> Vec<RuleViolation> {
+    validate_rules(self)
+  }
+}
+
+// Returns the element at index, treating negative indices (-1 is used by
+// the spec to mean "not set") as absent
+fn get<T>(items: Option<&Vec<T>>, index: i64) -> Option<&T> {
+  usize::try_from(index)
+    .ok()
+    .and_then(|index| items.and_then(|items| items.get(index)))
+}
+
+fn is_set(index: Option<i64>) -> Option<i64> {
+  index.filter(|index| *index >= 0)
+}
+
+// Returns the tool component whose rules are referenced by the result, as
+// well as a JSON pointer to it relative to the run
+fn rule_component<'a>(
+  run: &'a sarif::Run,
+  result: &sarif::Result,
+) -> Option<(&'a sarif::ToolComponent, String)> {
+  let component = run.resolve_tool_component(
+    result
+      .rule
+      .as_ref()
+      .and_then(|rule| rule.tool_component.as_ref()),
+  )?;
+  if std::ptr::eq(component, &run.tool.driver) {
+    return Some((component, "/tool/driver".into()));
+  }
+  run
+    .tool
+    .extensions
+    .iter()
+    .flatten()
+    .position(|extension| std::ptr::eq(extension, component))
+    .map(|i| (component, format!("/tool/extensions/{}", i)))
+}
+
+fn rule_indices(result: &sarif::Result) -> Vec<(&'static str, i64)> {
+  let mut indices = vec![];
+  if let Some(index) = is_set(result.rule_index) {
+    indices.push(("/ruleIndex", index));
+  }
+  if let Some(index) = is_set(result.rule.as_ref().and_then(|r| r.index)) {
+    indices.push(("/rule/index", index));
+  }
+  indices
+}
+
+fn results(run: &sarif::Run) -> impl Iterator<Item = (String, &sarif::Result)> {
+  run
+    .results
+    .iter()
+    .flatten()
+    .enumerate()
+    .map(|(i, result)| (format!("/results/{}", i), result))
+}
+
+// Returns every location of a result together with its JSON pointer
+fn result_locations<'a>(
+  pointer: &str,
+  result: &'a sarif::Result,
+) -> Vec<(String, &'a sarif::Location)> {
+  let mut locations = vec![];
+  result
+    .locations
+    .iter()
+    .flatten()
+    .enumerate()
+    .for_each(|(i, l)| {
+      locations.push((format!("{}/locations/{}", pointer, i), l))
+    });
+  result
+    .related_locations
+    .iter()
+    .flatten()
+    .enumerate()
+    .for_each(|(i, l)| {
+      locations.push((format!("{}/relatedLocations/{}", pointer, i), l))
+    });
+  thread_flow_locations(pointer, result).into_iter().for_each(
+    |(pointer, thread_flow_location)| {
+      if let Some(location) = thread_flow_location.location.as_ref() {
+        locations.push((format!("{}/location", pointer), location))
+      }
+    },
+  );
+  locations
+}
+
+fn thread_flow_locations<'a>(
+  pointer: &str,
+  result: &'a sarif::Result,
+) -> Vec<(String, &'a sarif::ThreadFlowLocation)> {
+  let mut locations = vec![];
+  result
+    .code_flows
+    .iter()
+    .flatten()
+    .enumerate()
+    .for_each(|(i, code_flow)| {
+      code_flow
+        .thread_flows
+        .iter()
+        .enumerate()
+        .for_each(|(j, thread_flow)| {
+          thread_flow.locations.iter().enumerate().for_each(|(k, l)| {
+            locations.push((
+              format!(
+                "{}/codeFlows/{}/threadFlows/{}/locations/{}",
+                pointer, i, j, k
+              ),
+              l,
+            ))
+          })
+        })
+    });
+  locations
+}
+
+// Returns every artifact location of a run together with its JSON pointer
+fn artifact_locations(
+  run: &sarif::Run,
+) -> Vec<(String, &sarif::ArtifactLocation)> {
+  let mut artifact_locations = vec![];
+  results(run).for_each(|(pointer, result)| {
+    result_locations(&pointer, result).into_iter().for_each(
+      |(pointer, location)| {
+        if let Some(artifact_location) = location
+          .physical_location
+          .as_ref()
+          .and_then(|p| p.artifact_location.as_ref())
+        {
+          artifact_locations.push((
+            format!("{}/physicalLocation/artifactLocation", pointer),
+            artifact_location,
+          ))
+        }
+      },
+    );
+    if let Some(analysis_target) = result.analysis_target.as_ref() {
+      artifact_locations
+        .push((format!("{}/analysisTarget", pointer), analysis_target));
+    }
+    result
+      .fixes
+      .iter()
+      .flatten()
+      .enumerate()
+      .for_each(|(i, fix)| {
+        fix
+          .artifact_changes
+          .iter()
+          .enumerate()
+          .for_each(|(j, change)| {
+            artifact_locations.push((
+              format!(
+                "{}/fixes/{}/artifactChanges/{}/artifactLocation",
+                pointer, i, j
+              ),
+              &change.artifact_location,
+            ))
+          })
+      });
+  });
+  run
+    .artifacts
+    .iter()
+    .flatten()
+    .enumerate()
+    .for_each(|(i, artifact)| {
+      if let Some(location) = artifact.location.as_ref() {
+        artifact_locations
+          .push((format!("/artifacts/{}/location", i), location))
+      }
+    });
+  run
+    .original_uri_base_ids
+    .iter()
+    .flatten()
+    .for_each(|(key, location)| {
+      artifact_locations.push((
+        format!(
+          "/originalUriBaseIds/{}",
+          key.replace('~', "~0").replace('/', "~1")
+        ),
+        location,
+      ))
+    });
+  artifact_locations
+}
+
+// Returns every region of a run together with its JSON pointer
+fn regions(run: &sarif::Run) -> Vec<(String, &sarif::Region)> {
+  let mut regions = vec![];
+  results(run).for_each(|(pointer, result)| {
+    result_locations(&pointer, result).into_iter().for_each(
+      |(pointer, location)| {
+        if let Some(physical_location) = location.physical_location.as_ref() {
+          if let Some(region) = physical_location.region.as_ref() {
+            regions
+              .push((format!("{}/physicalLocation/region", pointer), region))
+          }
+          if let Some(region) = physical_location.context_region.as_ref() {
+            regions.push((
+              format!("{}/physicalLocation/contextRegion", pointer),
+              region,
+            ))
+          }
+        }
+      },
+    );
+    result
+      .fixes
+      .iter()
+      .flatten()
+      .enumerate()
+      .for_each(|(i, fix)| {
+        fix
+          .artifact_changes
+          .iter()
+          .enumerate()
+          .for_each(|(j, change)| {
+            change.replacements.iter().enumerate().for_each(
+              |(k, replacement)| {
+                regions.push((
+                  format!(
+              "{}/fixes/{}/artifactChanges/{}/replacements/{}/deletedRegion",
+              pointer, i, j, k
+            ),
+                  &replacement.deleted_region,
+                ))
+              },
+            )
+          })
+      });
+  });
+  regions
+}
+
+// Checks whether a (possibly hierarchical) rule id, ex. CA2101/md5, is
+// consistent with the id of a rule descriptor, ex. CA2101
+fn rule_id_matches(rule_id: &str, descriptor_id: &str) -> bool {
+  rule_id == descriptor_id
+    || rule_id
+      .strip_prefix(descriptor_id)
+      .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn check_rule_index(run: &sarif::Run, findings: &mut Vec<(String, String)>) {
+  results(run).for_each(|(pointer, result)| {
+    let component = rule_component(run, result);
+    rule_indices(result)
+      .into_iter()
+      .for_each(|(field, index)| match component.as_ref() {
+        Some((component, component_pointer)) => {
+          let count = component.rules.as_ref().map_or(0, |r| r.len());
+          if get(component.rules.as_ref(), index).is_none() {
+            findings.push((
+              format!("{}{}", pointer, field),
+              format!(
+                "rule index {} is out of range, {}/rules has {} rules",
+                index, component_pointer, count
+              ),
+            ))
+          }
+        }
+        None => findings.push((
+          format!("{}/rule/toolComponent", pointer),
+          "the referenced tool component does not exist".into(),
+        )),
+      })
+  })
+}
+
+fn check_rule_id(run: &sarif::Run, findings: &mut Vec<(String, String)>) {
+  results(run).for_each(|(pointer, result)| {
+    let reference_id = result.rule.as_ref().and_then(|r| r.id.as_ref());
+    if let (Some(rule_id), Some(reference_id)) =
+      (result.rule_id.as_ref(), reference_id)
+    {
+      if rule_id != reference_id {
+        findings.push((
+          format!("{}/rule/id", pointer),
+          format!(
+            "rule.id \"{}\" does not match ruleId \"{}\"",
+            reference_id, rule_id
+          ),
+        ))
+      }
+    }
+
+    if let Some((component, _)) = rule_component(run, result) {
+      let rule_id = result.rule_id.as_ref().or(reference_id);
+      rule_indices(result).into_iter().for_each(|(field, index)| {
+        if let (Some(rule_id), Some(rule)) =
+          (rule_id, get(component.rules.as_ref(), index))
+        {
+          if !rule_id_matches(rule_id, &rule.id) {
+            findings.push((
+              format!("{}{}", pointer, field),
+              format!(
+                "ruleId \"{}\" does not match the id \"{}\" of rule {}",
+                rule_id, rule.id, index
+              ),
+            ))
+          }
+        }
+      })
+    }
+  })
+}
+
+fn check_duplicate_rule_id(
+  run: &sarif::Run,
+  findings: &mut Vec<(String, String)>,
+) {
+  let components =
+    std::iter::once(("/tool/driver".to_string(), &run.tool.driver)).chain(
+      run
+        .tool
+        .extensions
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, c)| (format!("/tool/extensions/{}", i), c)),
+    );
+  components.for_each(|(pointer, component)| {
+    let mut seen = HashSet::new();
+    component
+      .rules
+      .iter()
+      .flatten()
+      .enumerate()
+      .for_each(|(i, rule)| {
+        if !seen.insert(&rule.id) {
+          findings.push((
+            format!("{}/rules/{}/id", pointer, i),
+            format!("rule id \"{}\" is defined more than once", rule.id),
+          ))
+        }
+      })
+  })
+}
+
+fn check_uri_base_id(run: &sarif::Run, findings: &mut Vec<(String, String)>) {
+  artifact_locations(run).into_iter().for_each(
+    |(pointer, artifact_location)| {
+      if let Some(uri_base_id) = artifact_location.uri_base_id.as_ref() {
+        let defined = run
+          .original_uri_base_ids
+          .as_ref()
+          .is_some_and(|ids| ids.contains_key(uri_base_id));
+        if !defined {
+          findings.push((
+            format!("{}/uriBaseId", pointer),
+            format!(
+              "uriBaseId \"{}\" is not defined in originalUriBaseIds",
+              uri_base_id
+            ),
+          ))
+        }
+      }
+    },
+  )
+}
+
+fn check_region(run: &sarif::Run, findings: &mut Vec<(String, String)>) {
+  regions(run).into_iter().for_each(|(pointer, region)| {
+    if let (Some(start_line), Some(end_line)) =
+      (region.start_line, region.end_line)
+    {
+      if end_line < start_line {
+        findings.push((
+          format!("{}/endLine", pointer),
+          format!("endLine {} is before startLine {}", end_line, start_line),
+        ));
+        return;
+      }
+    }
+    if let (Some(start_line), Some(start_column), Some(end_column)) =
+      (region.start_line, region.start_column, region.end_column)
+    {
+      // endLine defaults to startLine
+      if region.end_line.unwrap_or(start_line) == start_line
+        && end_column < start_column
+      {
+        findings.push((
+          format!("{}/endColumn", pointer),
+          format!(
+            "endColumn {} is before startColumn {} on the same line",
+            end_column, start_column
+          ),
+        ))
+      }
+    }
+  })
+}
+
+fn check_artifact_index(
+  run: &sarif::Run,
+  findings: &mut Vec<(String, String)>,
+) {
+  artifact_locations(run).into_iter().for_each(
+    |(pointer, artifact_location)| {
+      if let Some(index) = is_set(artifact_location.index) {
+        if get(run.artifacts.as_ref(), index).is_none() {
+          findings.push((
+            format!("{}/index", pointer),
+            format!(
+              "artifact index {} is out of range, the run has {} artifacts",
+              index,
+              run.artifacts.as_ref().map_or(0, |a| a.len())
+            ),
+          ))
+        }
+      }
+    },
+  )
+}
+
+fn check_thread_flow_location_index(
+  run: &sarif::Run,
+  findings: &mut Vec<(String, String)>,
+) {
+  results(run).for_each(|(pointer, result)| {
+    thread_flow_locations(&pointer, result).into_iter().for_each(
+      |(pointer, thread_flow_location)| {
+        if let Some(index) = is_set(thread_flow_location.index) {
+          if get(run.thread_flow_locations.as_ref(), index).is_none() {
+            findings.push((
+              format!("{}/index", pointer),
+              format!(
+                "thread flow location index {} is out of range, the run has {} threadFlowLocations",
+                index,
+                run.thread_flow_locations.as_ref().map_or(0, |t| t.len())
+              ),
+            ))
+          }
+        }
+      },
+    )
+  })
+}
+
+fn check_invocation_index(
+  run: &sarif::Run,
+  findings: &mut Vec<(String, String)>,
+) {
+  results(run).for_each(|(pointer, result)| {
+    if let Some(index) =
+      is_set(result.provenance.as_ref().and_then(|p| p.invocation_index))
+    {
+      if get(run.invocations.as_ref(), index).is_none() {
+        findings.push((
+          format!("{}/provenance/invocationIndex", pointer),
+          format!(
+            "invocation index {} is out of range, the run has {} invocations",
+            index,
+            run.invocations.as_ref().map_or(0, |i| i.len())
+          ),
+        ))
+      }
+    }
+  })
+}
+
+fn check_message_id(run: &sarif::Run, findings: &mut Vec<(String, String)>) {
+  results(run).for_each(|(pointer, result)| {
+    if let (None, Some(id)) =
+      (result.message.text.as_ref(), result.message.id.as_ref())
+    {
+      let in_rule = run
+        .resolve_rule(result)
+        .and_then(|rule| rule.message_strings.as_ref())
+        .is_some_and(|strings| strings.contains_key(id));
+      let in_component =
+        rule_component(run, result).is_some_and(|(component, _)| {
+          component
+            .global_message_strings
+            .as_ref()
+            .is_some_and(|strings| strings.contains_key(id))
+        });
+      if !in_rule && !in_component {
+        findings.push((
+          format!("{}/message/id", pointer),
+          format!("message id \"{}\" is not defined", id),
+        ))
+      }
+    }
+  })
+}
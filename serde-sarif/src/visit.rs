@@ -0,0 +1,61 @@
+//! Visitors over the SARIF object graph.
+//!
+//! [Visit] and [VisitMut] have a method per SARIF type, ex.
+//! [Visit::visit_result] or [VisitMut::visit_region_mut], which by default
+//! walk the objects within it, so that a visitor only overrides the methods of
+//! the types it is interested in. An overriding method calls the matching
+//! `walk_` function to keep visiting the objects within, or leaves it out to
+//! skip them.
+//!
+//! Both traits, and the walk functions, are generated from the SARIF schema
+//! at build time alongside the [sarif](crate::sarif) types.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use serde_sarif::sarif::{ArtifactLocation, Sarif};
+//! use serde_sarif::visit::{walk_artifact_location_mut, VisitMut};
+//!
+//! // makes absolute paths relative to the checkout
+//! struct StripPrefix<'a>(&'a str);
+//!
+//! impl VisitMut for StripPrefix<'_> {
+//!   fn visit_artifact_location_mut(&mut self, location: &mut ArtifactLocation) {
+//!     if let Some(uri) = location.uri.as_mut() {
+//!       if let Some(relative) = uri.strip_prefix(self.0) {
+//!         *uri = relative.into();
+//!       }
+//!     }
+//!     walk_artifact_location_mut(self, location)
+//!   }
+//! }
+//!
+//! let mut sarif: Sarif = serde_json::from_value(serde_json::json!({
+//!   "version": "2.1.0",
+//!   "runs": [{
+//!     "tool": { "driver": { "name": "clippy" } },
+//!     "results": [{
+//!       "message": { "text": "unused variable" },
+//!       "locations": [{
+//!         "physicalLocation": {
+//!           "artifactLocation": { "uri": "/checkout/src/main.rs" }
+//!         }
+//!       }]
+//!     }]
+//!   }]
+//! }))
+//! .unwrap();
+//!
+//! StripPrefix("/checkout/").visit_sarif_mut(&mut sarif);
+//! let results = sarif.runs[0].results.as_ref().unwrap();
+//! let locations = results[0].locations.as_ref().unwrap();
+//! let location = locations[0].physical_location.as_ref().unwrap();
+//! assert_eq!(
+//!   location.artifact_location.as_ref().unwrap().uri.as_deref(),
+//!   Some("src/main.rs")
+//! );
+//! ```
+
+use crate::sarif;
+
+include!(concat!(env!("OUT_DIR"), "/visit.rs"));
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use serde_sarif::baseline::compare;
+use serde_sarif::sarif;
+use serde_sarif::sarif::ResultBaselineState;
+
+fn result(
+  rule_id: &str,
+  uri: &str,
+  line: i64,
+  message: &str,
+) -> serde_json::Value {
+  serde_json::json!({
+    "ruleId": rule_id,
+    "message": { "text": message },
+    "locations": [{
+      "physicalLocation": {
+        "artifactLocation": { "uri": uri },
+        "region": { "startLine": line }
+      }
+    }]
+  })
+}
+
+fn log(results: Vec<serde_json::Value>) -> Result<sarif::Sarif> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": { "name": "tool", "rules": [{ "id": "a" }, { "id": "b" }] }
+      },
+      "results": results
+    }]
+  }))?)
+}
+
+#[test]
+// Test that every result is annotated with its baseline state
+fn test_compare() -> Result<()> {
+  let baseline = log(vec![
+    result("a", "lib.rs", 10, "unused variable `x`"),
+    result("a", "lib.rs", 20, "unused variable `y` in function"),
+    result("b", "main.rs", 5, "fixed in the meantime"),
+    {
+      let mut result = result("b", "old.rs", 1, "renamed file");
+      result["partialFingerprints"] = serde_json::json!({ "hash": "1234" });
+      result
+    },
+  ])?;
+  let mut current = log(vec![
+    result("a", "lib.rs", 14, "unused variable `x`"),
+    result("a", "lib.rs", 24, "unused variable `y` in closure"),
+    result("a", "lib.rs", 30, "unused variable `z`"),
+    {
+      let mut result = result("b", "new.rs", 1, "renamed file");
+      result["partialFingerprints"] = serde_json::json!({ "hash": "1234" });
+      result
+    },
+  ])?;
+
+  compare(&mut current, &baseline);
+
+  let results = current.runs[0].results.as_ref().unwrap();
+  assert_eq!(
+    results
+      .iter()
+      .map(|result| result.baseline_state.clone())
+      .collect::<Vec<_>>(),
+    vec![
+      Some(ResultBaselineState::Unchanged),
+      Some(ResultBaselineState::Updated),
+      Some(ResultBaselineState::New),
+      Some(ResultBaselineState::Updated),
+      Some(ResultBaselineState::Absent),
+    ]
+  );
+  assert_eq!(
+    results[4].message.text.as_deref(),
+    Some("fixed in the meantime")
+  );
+
+  Ok(())
+}
+
+#[test]
+// Test that an absent result copied from the baseline no longer refers to the
+// baseline run by index, and brings along the uriBaseIds and taxa it needs
+fn test_absent_result_is_detached() -> Result<()> {
+  let artifact = |index: i64| {
+    serde_json::json!({
+      "physicalLocation": { "artifactLocation": { "index": index } }
+    })
+  };
+  let baseline: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "tool", "rules": [{ "id": "a" }] } },
+      "originalUriBaseIds": {
+        "SRCROOT": { "uri": "src/", "uriBaseId": "ROOT" },
+        "ROOT": { "uri": "file:///repo/" }
+      },
+      "artifacts": [
+        { "location": { "uri": "unrelated.rs" } },
+        { "location": { "uri": "lib.rs", "uriBaseId": "SRCROOT" } }
+      ],
+      "logicalLocations": [
+        { "name": "other" },
+        { "name": "parse", "fullyQualifiedName": "lib::parse" }
+      ],
+      "threadFlowLocations": [
+        { "importance": "unimportant" },
+        { "location": artifact(1), "importance": "essential" }
+      ],
+      "taxonomies": [{ "name": "CWE", "taxa": [{ "id": "CWE-20" }] }],
+      "results": [{
+        "ruleIndex": 0,
+        "message": { "text": "gone" },
+        "provenance": { "invocationIndex": 0 },
+        "analysisTarget": { "index": 1 },
+        "locations": [{
+          "physicalLocation": { "artifactLocation": { "index": 1 } },
+          "logicalLocations": [{ "index": 1 }]
+        }],
+        "codeFlows": [{ "threadFlows": [{ "locations": [{ "index": 1 }] }] }],
+        "stacks": [{ "frames": [{ "location": artifact(1) }] }],
+        "fixes": [{ "artifactChanges": [{
+          "artifactLocation": { "index": 1 },
+          "replacements": [{ "deletedRegion": { "startLine": 1 } }]
+        }] }],
+        "taxa": [{ "index": 0, "toolComponent": { "index": 0 } }]
+      }]
+    }]
+  }))?;
+  let mut current: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "tool" } },
+      "artifacts": [{ "location": { "uri": "main.rs" } }],
+      "logicalLocations": [{ "name": "main" }],
+      "threadFlowLocations": [{ "importance": "unimportant" }],
+      "results": []
+    }]
+  }))?;
+
+  compare(&mut current, &baseline);
+
+  let run = serde_json::to_value(&current.runs[0])?;
+  let lib = serde_json::json!({ "uri": "lib.rs", "uriBaseId": "SRCROOT" });
+  let result = &run["results"][0];
+  assert_eq!(result["baselineState"], "absent");
+  assert_eq!(result["provenance"], serde_json::json!({}));
+  assert_eq!(result["analysisTarget"], lib);
+  let location = &result["locations"][0];
+  assert_eq!(location["physicalLocation"]["artifactLocation"], lib);
+  assert_eq!(
+    location["logicalLocations"][0],
+    serde_json::json!({ "name": "parse", "fullyQualifiedName": "lib::parse" })
+  );
+  assert_eq!(
+    result["codeFlows"][0]["threadFlows"][0]["locations"][0],
+    serde_json::json!({
+      "location": { "physicalLocation": { "artifactLocation": lib } },
+      "importance": "essential"
+    })
+  );
+  assert_eq!(
+    result["stacks"][0]["frames"][0]["location"]["physicalLocation"]
+      ["artifactLocation"],
+    lib
+  );
+  assert_eq!(
+    result["fixes"][0]["artifactChanges"][0]["artifactLocation"],
+    lib
+  );
+  assert_eq!(
+    result["taxa"][0],
+    serde_json::json!({ "id": "CWE-20", "toolComponent": { "name": "CWE" } })
+  );
+  // the base ids the result is relative to, and its taxonomy, are copied
+  let ids: Vec<_> = run["originalUriBaseIds"]
+    .as_object()
+    .unwrap()
+    .keys()
+    .collect();
+  assert_eq!(ids, vec!["ROOT", "SRCROOT"]);
+  assert_eq!(run["taxonomies"][0]["name"], "CWE");
+  assert_eq!(run["artifacts"].as_array().map(Vec::len), Some(1));
+  Ok(())
+}
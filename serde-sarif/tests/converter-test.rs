@@ -0,0 +1,127 @@
+use anyhow::Result;
+use serde_sarif::converters::{
+  ConvertOptions, Converter, ConverterError, Items, Registry,
+};
+use serde_sarif::fingerprint::{PRIMARY_LOCATION_LINE_HASH, RULE_SNIPPET_HASH};
+use serde_sarif::sarif;
+use serde_sarif::taxonomy::{cwe, TaxonomyMapping};
+use std::convert::TryInto;
+use std::io::BufRead;
+
+// `file:line: rule: message`
+struct Lines;
+
+impl Converter for Lines {
+  type Item = (String, i64, String, String);
+
+  fn name(&self) -> &str {
+    "lines"
+  }
+
+  fn parse<'r, R: BufRead + 'r>(
+    &self,
+    reader: R,
+  ) -> Result<Items<'r, Self::Item>, ConverterError> {
+    Ok(Box::new(reader.lines().map(|line| {
+      let line = line?;
+      let parts: Vec<&str> = line.splitn(4, ':').collect();
+      Ok((
+        parts[0].to_string(),
+        parts[1].parse().unwrap_or(1),
+        parts[2].trim().to_string(),
+        parts[3].trim().to_string(),
+      ))
+    })))
+  }
+
+  fn rule_id(&self, item: &Self::Item) -> Option<String> {
+    Some(item.2.clone())
+  }
+
+  fn results(
+    &self,
+    item: &Self::Item,
+  ) -> Result<Vec<sarif::Result>, ConverterError> {
+    Ok(vec![sarif::ResultBuilder::default()
+      .message::<sarif::Message>((&item.3).try_into()?)
+      .locations(vec![sarif::LocationBuilder::default()
+        .physical_location(
+          sarif::PhysicalLocationBuilder::default()
+            .artifact_location(
+              sarif::ArtifactLocationBuilder::default()
+                .uri(&item.0)
+                .build()?,
+            )
+            .region(sarif::RegionBuilder::default().start_line(item.1).build()?)
+            .build()?,
+        )
+        .build()?])
+      .build()?])
+  }
+}
+
+fn convert(
+  name: &str,
+  input: &str,
+  options: &ConvertOptions,
+) -> Result<sarif::Sarif> {
+  let mut output = vec![];
+  Registry::new().register(Lines).convert(
+    name,
+    &mut input.as_bytes(),
+    &mut output,
+    options,
+  )?;
+  Ok(serde_json::from_slice(&output)?)
+}
+
+#[test]
+fn test_convert() -> Result<()> {
+  let input = "a.sh:1: X1: first\nb.sh:2: X2: second\na.sh:3: X1: third\n";
+  let sarif = convert("lines", input, &ConvertOptions::new())?;
+  let run = &sarif.runs[0];
+  assert_eq!(run.tool.driver.name, "lines");
+  let rules = run.tool.driver.rules.as_ref().unwrap();
+  assert_eq!(rules.len(), 2);
+  let results = run.results.as_ref().unwrap();
+  let indices: Vec<_> = results.iter().map(|r| r.rule_index).collect();
+  assert_eq!(indices, vec![Some(0), Some(1), Some(0)]);
+  assert_eq!(results[2].rule_id.as_deref(), Some("X1"));
+  Ok(())
+}
+
+#[test]
+fn test_convert_with_taxonomies() -> Result<()> {
+  let mapping = TaxonomyMapping::new(vec![cwe()]).map("X1", "CWE-88")?;
+  let options = ConvertOptions::new().pretty(false).taxonomies(mapping);
+  let sarif = convert("lines", "a.sh:1: X1: first\n", &options)?;
+  let run = &sarif.runs[0];
+  assert_eq!(run.taxonomies.as_ref().map(|t| t.len()), Some(1));
+  let rule = &run.tool.driver.rules.as_ref().unwrap()[0];
+  assert_eq!(
+    rule.relationships.as_ref().unwrap()[0].target.id.as_deref(),
+    Some("CWE-88")
+  );
+  Ok(())
+}
+
+#[test]
+fn test_convert_with_fingerprints() -> Result<()> {
+  let dir = tempfile::tempdir()?;
+  std::fs::write(dir.path().join("a.sh"), "echo $1\necho $2\n")?;
+  let options = ConvertOptions::new().fingerprints(dir.path());
+  let sarif = convert("lines", "a.sh:2: X1: first\nb.sh:1: X1: b\n", &options)?;
+  let results = sarif.runs[0].results.as_ref().unwrap();
+  let fingerprints = results[0].partial_fingerprints.as_ref().unwrap();
+  assert!(fingerprints.contains_key(PRIMARY_LOCATION_LINE_HASH));
+  assert!(fingerprints.contains_key(RULE_SNIPPET_HASH));
+  // b.sh does not exist
+  assert_eq!(results[1].partial_fingerprints, None);
+  Ok(())
+}
+
+#[test]
+fn test_unknown_converter() {
+  let result = convert("pylint", "", &ConvertOptions::new());
+  assert_eq!(result.unwrap_err().to_string(), "no converter for pylint");
+}
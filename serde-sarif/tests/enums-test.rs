@@ -0,0 +1,52 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+
+#[test]
+// Test that schema enums deserialize into their typed variants
+fn test_typed_enums() -> Result<()> {
+  let result: sarif::Result = serde_json::from_str(
+    r#"{
+      "message": { "text": "message" },
+      "kind": "fail",
+      "level": "error",
+      "baselineState": "new"
+    }"#,
+  )?;
+
+  assert_eq!(result.kind, Some(sarif::ResultKind::Fail));
+  assert_eq!(result.level, Some(sarif::ResultLevel::Error));
+  assert_eq!(result.baseline_state, Some(sarif::ResultBaselineState::New));
+
+  Ok(())
+}
+
+#[test]
+// Test that values outside of the schema enums round-trip unchanged
+fn test_unknown_enum_values_round_trip() -> Result<()> {
+  let json = r#"{"level":"fatal","message":{"text":"message"}}"#;
+  let result: sarif::Result = serde_json::from_str(json)?;
+
+  assert_eq!(
+    result.level,
+    Some(sarif::ResultLevel::Unknown("fatal".into()))
+  );
+  assert_eq!(serde_json::to_string(&result)?, json);
+
+  Ok(())
+}
+
+#[test]
+// Test that builders take the typed enums directly
+fn test_builder_enums() -> Result<()> {
+  let result = sarif::ResultBuilder::default()
+    .message(sarif::MessageBuilder::default().text("message").build()?)
+    .level(sarif::ResultLevel::Warning)
+    .build()?;
+
+  assert_eq!(
+    serde_json::to_value(&result)?,
+    serde_json::json!({ "level": "warning", "message": { "text": "message" } })
+  );
+
+  Ok(())
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use serde_sarif::external::{
+  resolve, split, Category, ExternalPropertiesError,
+};
+use serde_sarif::sarif;
+use serde_sarif::validate::validate_schema;
+
+fn log() -> Result<sarif::Sarif> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "clippy", "rules": [{ "id": "a" }] } },
+      "automationDetails": { "guid": "2d2e5d8a-0f4c-4c1e-9b61-4b5e4a0c1e11" },
+      "artifacts": [{ "location": { "uri": "main.rs" } }],
+      "properties": { "tags": ["lint"], "owner": "ci", "attempt": 2 },
+      "results": [
+        { "message": { "text": "first" }, "ruleIndex": 0 },
+        { "message": { "text": "second" }, "ruleIndex": 0 }
+      ]
+    }]
+  }))?)
+}
+
+#[test]
+// Test that a run split into external property files resolves to itself
+fn test_split_and_resolve() -> Result<()> {
+  let dir = tempfile::tempdir()?;
+  let original = log()?;
+  let mut sarif = original.clone();
+  let categories = [
+    Category::Driver,
+    Category::Artifacts,
+    Category::ExternalizedProperties,
+    Category::Results,
+  ];
+  let external = split(&mut sarif.runs[0], &categories, |category| {
+    Some(
+      sarif::ArtifactLocationBuilder::default()
+        .uri(format!("{:?}.sarif-external-properties", category))
+        .build()
+        .unwrap(),
+    )
+  });
+  assert_eq!(external.len(), 4);
+  assert_eq!(sarif.runs[0].results, None);
+  assert_eq!(sarif.runs[0].properties, None);
+  assert_eq!(sarif.runs[0].tool.driver.rules, None);
+  let references = sarif.runs[0].external_property_file_references.as_ref();
+  let results = references.and_then(|r| r.results.as_ref()).unwrap();
+  assert_eq!(results[0].item_count, Some(2));
+
+  categories
+    .iter()
+    .zip(&external)
+    .try_for_each(|(category, external)| {
+      let path = dir
+        .path()
+        .join(format!("{:?}.sarif-external-properties", category));
+      std::fs::write(path, serde_json::to_string(external)?)
+        .map_err(anyhow::Error::from)
+    })?;
+  resolve(&mut sarif, dir.path())?;
+  assert_eq!(sarif, original);
+  Ok(())
+}
+
+#[test]
+// Test that inline external properties with another guid are rejected
+fn test_resolve_guid_mismatch() -> Result<()> {
+  let mut sarif = log()?;
+  let external = split(&mut sarif.runs[0], &[Category::Results], |_| None);
+  let mut modified = external[0].clone();
+  modified.run_guid = Some("00000000-0000-0000-0000-000000000000".into());
+  sarif.inline_external_properties = Some(vec![modified]);
+  assert!(matches!(
+    resolve(&mut sarif, "."),
+    Err(ExternalPropertiesError::RunGuidMismatch { .. })
+  ));
+  assert!(sarif.runs[0].external_property_file_references.is_some());
+  Ok(())
+}
+
+#[test]
+// Test that the guids of split runs are valid under the schema, whether the
+// external properties are inline or referenced
+fn test_split_guid_is_valid() -> Result<()> {
+  let mut sarif = log()?;
+  let external = split(&mut sarif.runs[0], &[Category::Results], |_| None);
+  sarif.inline_external_properties = Some(external);
+  assert_eq!(validate_schema(&serde_json::to_value(&sarif)?), vec![]);
+
+  let mut sarif = log()?;
+  split(&mut sarif.runs[0], &[Category::Results], |_| {
+    sarif::ArtifactLocationBuilder::default()
+      .uri("results.sarif-external-properties")
+      .build()
+      .ok()
+  });
+  assert_eq!(validate_schema(&serde_json::to_value(&sarif)?), vec![]);
+  Ok(())
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde_sarif::fingerprint::{
+  add_fingerprints, line_hashes, PRIMARY_LOCATION_LINE_HASH, RULE_SNIPPET_HASH,
+};
+use serde_sarif::sarif;
+
+#[test]
+// Test that line hashes match those computed by the Github upload-sarif action
+fn test_line_hashes() {
+  assert_eq!(
+    line_hashes("a\r\nb\r\n\r\nb\n\n"),
+    vec![
+      "37b592d49955b41e:1",
+      "e2658b13689f2acf:1",
+      "79c1ecac854f2c03:1",
+      "a0e70ee94550ca05:1",
+      "3c5bef55b72b93c9:1",
+      "c129715d7a2bc9a3:1",
+    ]
+  );
+}
+
+fn fingerprints(
+  contents: &str,
+  line: i64,
+) -> Result<std::collections::BTreeMap<String, String>> {
+  let dir = tempfile::tempdir()?;
+  std::fs::write(dir.path().join("main.rs"), contents)?;
+  let mut sarif: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "clippy", "rules": [{ "id": "a" }] } },
+      "results": [{
+        "message": { "text": "message" },
+        "ruleIndex": 0,
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "main.rs" },
+            "region": { "startLine": line }
+          }
+        }]
+      }]
+    }]
+  }))?;
+  add_fingerprints(&mut sarif, dir.path());
+  let result = &sarif.runs[0].results.as_ref().unwrap()[0];
+  Ok(result.partial_fingerprints.clone().unwrap_or_default())
+}
+
+#[test]
+// Test that fingerprints do not change when the result moves
+fn test_add_fingerprints() -> Result<()> {
+  let before = fingerprints("fn main() {\n  let x = 1;\n}\n", 2)?;
+  let after =
+    fingerprints("\n// comment\nfn main() {\n    let x = 1;\n}\n", 4)?;
+  assert!(before.contains_key(PRIMARY_LOCATION_LINE_HASH));
+  assert!(before.contains_key(RULE_SNIPPET_HASH));
+  assert_eq!(before, after);
+
+  let changed = fingerprints("fn main() {\n  let y = 1;\n}\n", 2)?;
+  assert_ne!(before[RULE_SNIPPET_HASH], changed[RULE_SNIPPET_HASH]);
+  Ok(())
+}
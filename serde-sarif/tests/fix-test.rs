@@ -0,0 +1,176 @@
+use anyhow::Result;
+use serde_sarif::fix::{apply, unified_diff, Edit, FixError, Fixer};
+use serde_sarif::sarif;
+use serde_sarif::uri::{UriResolver, Url};
+
+fn edit(range: std::ops::Range<usize>, text: &str) -> Edit {
+  Edit {
+    range,
+    text: text.to_string(),
+  }
+}
+
+#[test]
+fn test_hunks() -> Result<()> {
+  let contents: String = (1..=10).map(|i| format!("l{}\n", i)).collect();
+  let line = |n: usize| contents.find(&format!("l{}\n", n)).unwrap();
+  // replace line 2 and delete line 9
+  let edits = vec![
+    edit(line(9)..line(10), ""),
+    edit(line(2)..line(2) + 2, "L2"),
+  ];
+  assert_eq!(
+    unified_diff("lines.txt", &contents, &edits, 1)?,
+    "--- a/lines.txt\n+++ b/lines.txt\n\
+     @@ -1,3 +1,3 @@\n l1\n-l2\n+L2\n l3\n\
+     @@ -8,3 +8,2 @@\n l8\n-l9\n l10\n"
+  );
+  assert_eq!(apply(&contents, &edits)?.lines().count(), 9);
+  Ok(())
+}
+
+#[test]
+fn test_no_newline_at_end_of_file() -> Result<()> {
+  let edits = vec![edit(3..3, "\nc")];
+  assert_eq!(
+    unified_diff("abc.txt", "a\nb", &edits, 3)?,
+    "--- a/abc.txt\n+++ b/abc.txt\n@@ -1,2 +1,3 @@\n a\n\
+     -b\n\\ No newline at end of file\n+b\n+c\n\\ No newline at end of file\n"
+  );
+  // edits which change nothing have no diff
+  assert_eq!(unified_diff("abc.txt", "a\nb", &[edit(0..1, "a")], 3)?, "");
+  Ok(())
+}
+
+#[test]
+fn test_overlap() -> Result<()> {
+  let edits = vec![edit(0..4, "x"), edit(2..6, "y")];
+  assert!(matches!(
+    apply("abcdefgh", &edits),
+    Err(FixError::Overlap(2))
+  ));
+  // insertions at the same position do not overlap, and are made in order
+  let edits = vec![edit(1..1, "x"), edit(1..1, "y")];
+  assert_eq!(apply("ab", &edits)?, "axyb");
+  Ok(())
+}
+
+// a result with one fix for each of `fixes`, which are the replaced column
+// ranges of line 1 and the inserted text
+fn fixed_result(fixes: &[(i64, i64, &str)]) -> Result<sarif::Result> {
+  let fixes: Vec<_> = fixes
+    .iter()
+    .map(|(start, end, text)| {
+      serde_json::json!({ "artifactChanges": [{
+        "artifactLocation": { "uri": "main.sh", "uriBaseId": "SRCROOT" },
+        "replacements": [{
+          "deletedRegion": {
+            "startLine": 1, "startColumn": start, "endColumn": end
+          },
+          "insertedContent": { "text": text }
+        }]
+      }]})
+    })
+    .collect();
+  Ok(serde_json::from_value(serde_json::json!({
+    "message": { "text": "quote" },
+    "fixes": fixes
+  }))?)
+}
+
+#[test]
+fn test_fixer() -> Result<()> {
+  let dir = tempfile::tempdir()?;
+  let path = dir.path().join("main.sh");
+  std::fs::write(&path, "echo $1 $2\n")?;
+  let base = Url::from_directory_path(dir.path()).unwrap();
+  let run: sarif::Run = serde_json::from_value(serde_json::json!({
+    "tool": { "driver": { "name": "shellcheck" } },
+    "originalUriBaseIds": {
+      "SRCROOT": { "uri": base.as_str() }
+    }
+  }))?;
+  let resolver = UriResolver::new();
+  let mut fixer = Fixer::new(&resolver);
+  assert!(fixer.add(&run, &fixed_result(&[(6, 8, "\"$1\"")])?)?);
+  // a duplicate fix is not made twice
+  assert!(fixer.add(&run, &fixed_result(&[(6, 8, "\"$1\"")])?)?);
+  // the first fix which does not overlap is taken
+  let result = fixed_result(&[(1, 8, "printf"), (9, 11, "\"$2\"")])?;
+  assert!(fixer.add(&run, &result)?);
+  let result = fixed_result(&[(1, 5, "printf")])?;
+  assert!(fixer.add(&run, &result)?);
+  assert!(matches!(
+    fixer.add(&run, &fixed_result(&[(4, 7, "")])?),
+    Err(FixError::Overlap(_))
+  ));
+  assert!(!fixer.add(&run, &fixed_result(&[])?)?);
+  assert_eq!(fixer.fixes(), 4);
+
+  let diffs = fixer.diffs(3)?;
+  assert_eq!(
+    diffs,
+    vec![(
+      path.as_path(),
+      "--- a/main.sh\n+++ b/main.sh\n@@ -1,1 +1,1 @@\n-echo $1 $2\n\
+       +printf \"$1\" \"$2\"\n"
+        .to_string()
+    )]
+  );
+  fixer.write()?;
+  assert_eq!(std::fs::read_to_string(&path)?, "printf \"$1\" \"$2\"\n");
+  Ok(())
+}
+
+#[test]
+// Test that an insertion at the edge of an edit of another fix conflicts with
+// it, while the edits of one fix may touch
+fn test_fixer_touching() -> Result<()> {
+  let dir = tempfile::tempdir()?;
+  std::fs::write(dir.path().join("main.sh"), "echo $1\n")?;
+  let base = Url::from_directory_path(dir.path()).unwrap();
+  let run: sarif::Run = serde_json::from_value(serde_json::json!({
+    "tool": { "driver": { "name": "shellcheck" } },
+    "originalUriBaseIds": {
+      "SRCROOT": { "uri": base.as_str() }
+    }
+  }))?;
+  let replacement = |start: i64, end: i64, text: &str| {
+    serde_json::json!({
+      "deletedRegion": {
+        "startLine": 1, "startColumn": start, "endColumn": end
+      },
+      "insertedContent": { "text": text }
+    })
+  };
+  let quote: sarif::Result = serde_json::from_value(serde_json::json!({
+    "message": { "text": "quote" },
+    "fixes": [{ "artifactChanges": [{
+      "artifactLocation": { "uri": "main.sh", "uriBaseId": "SRCROOT" },
+      "replacements": [
+        replacement(6, 6, "\""),
+        replacement(6, 8, "$1"),
+        replacement(8, 8, "\"")
+      ]
+    }]}]
+  }))?;
+  let resolver = UriResolver::new();
+  let mut fixer = Fixer::new(&resolver);
+  assert!(fixer.add(&run, &quote)?);
+  for (start, end, text) in &[(6, 8, "X"), (6, 6, "X"), (8, 8, "X")] {
+    assert!(matches!(
+      fixer.add(&run, &fixed_result(&[(*start, *end, text)])?),
+      Err(FixError::Overlap(_))
+    ));
+  }
+  // an insertion at the edge of a fix which only inserts conflicts too
+  let mut fixer = Fixer::new(&resolver);
+  assert!(fixer.add(&run, &fixed_result(&[(6, 6, "\"")])?)?);
+  assert!(fixer.add(&run, &fixed_result(&[(8, 8, "\"")])?)?);
+  assert!(matches!(
+    fixer.add(&run, &fixed_result(&[(6, 8, "X")])?),
+    Err(FixError::Overlap(5))
+  ));
+  assert_eq!(fixer.diffs(0)?[0].1.lines().last(), Some("+echo \"$1\""));
+  Ok(())
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use serde_sarif::flow::steps;
+use serde_sarif::sarif::{self, ThreadFlowLocationImportance};
+
+#[test]
+fn test_steps() -> Result<()> {
+  let run: sarif::Run = serde_json::from_value(serde_json::json!({
+    "tool": { "driver": { "name": "taint" } },
+    "threadFlowLocations": [{
+      "location": { "message": { "text": "shared" } },
+      "kinds": ["call"],
+      "importance": "unimportant"
+    }]
+  }))?;
+  let thread_flow: sarif::ThreadFlow =
+    serde_json::from_value(serde_json::json!({
+      "locations": [
+        { "index": 0, "importance": "essential" },
+        { "index": 5, "nestingLevel": 2 },
+        { "importance": "whatever" }
+      ]
+    }))?;
+  let steps = steps(&run, &thread_flow);
+  assert_eq!(steps.len(), 3);
+  // local properties take precedence over the shared ones
+  assert_eq!(steps[0].importance, ThreadFlowLocationImportance::Essential);
+  assert_eq!(steps[0].kinds, ["call"]);
+  assert!(steps[0].location.is_some());
+  // an index outside of the run's thread flow locations is ignored
+  assert_eq!(steps[1].location, None);
+  assert_eq!(steps[1].nesting_level, 2);
+  assert!(steps[2]
+    .importance
+    .is_at_least(&ThreadFlowLocationImportance::Important));
+  assert!(!steps[2]
+    .importance
+    .is_at_least(&ThreadFlowLocationImportance::Essential));
+  Ok(())
+}
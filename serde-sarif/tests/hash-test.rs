@@ -0,0 +1,35 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde_sarif::sarif;
+
+#[test]
+fn test_dedupe_results() -> Result<()> {
+  let result = |rank: f64| -> Result<sarif::Result> {
+    Ok(serde_json::from_value(serde_json::json!({
+      "message": { "text": "unused variable" },
+      "ruleId": "unused",
+      "kind": "fail",
+      "rank": rank
+    }))?)
+  };
+  let results: HashSet<sarif::Result> =
+    vec![result(0.0)?, result(-0.0)?, result(50.0)?, result(50.0)?]
+      .into_iter()
+      .collect();
+  assert_eq!(results.len(), 2);
+  Ok(())
+}
+
+#[test]
+fn test_struct_update() {
+  let region = sarif::Region {
+    start_line: Some(1),
+    ..Default::default()
+  };
+  let other = region.clone();
+  assert_eq!(region, other);
+  let regions: HashSet<sarif::Region> =
+    vec![region, other].into_iter().collect();
+  assert_eq!(regions.len(), 1);
+}
@@ -0,0 +1,156 @@
+use anyhow::Result;
+use serde_sarif::localize::{load_translations, Localizer};
+use serde_sarif::sarif;
+use serde_sarif::uri::{UriResolver, Url};
+
+fn run() -> Result<sarif::Run> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "tool": { "driver": {
+      "name": "tool",
+      "globalMessageStrings": { "done": { "text": "analysis done" } },
+      "rules": [{
+        "id": "UNUSED",
+        "guid": "8d1b5f6c-5e3a-4c9e-9b1a-1f2e3d4c5b6a",
+        "shortDescription": { "text": "Unused variable" },
+        "fullDescription": { "text": "A variable is never read." },
+        "messageStrings": { "default": { "text": "{0} is unused" } }
+      }],
+      "notifications": [{
+        "id": "DONE",
+        "messageStrings": { "default": { "text": "{0} files analyzed" } }
+      }]
+    } },
+    "translations": [{
+      "name": "tool",
+      "language": "de",
+      "associatedComponent": { "name": "tool" },
+      "rules": [{
+        "id": "UNUSED",
+        "messageStrings": { "default": { "text": "{0} ist unbenutzt" } }
+      }]
+    }, {
+      "name": "tool",
+      "language": "de-DE",
+      "associatedComponent": { "name": "tool" },
+      "globalMessageStrings": { "done": { "text": "Analyse fertig" } },
+      "rules": [{
+        "id": "renamed",
+        "guid": "8d1b5f6c-5e3a-4c9e-9b1a-1f2e3d4c5b6a",
+        "shortDescription": { "text": "Unbenutzte Variable" },
+        "messageStrings": { "default": { "text": "{0} wird nicht verwendet" } }
+      }],
+      "notifications": [{
+        "id": "DONE",
+        "messageStrings": { "default": { "text": "{0} Dateien analysiert" } }
+      }]
+    }]
+  }))?)
+}
+
+fn sarif_result(message: serde_json::Value) -> Result<sarif::Result> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "ruleId": "UNUSED",
+    "message": message
+  }))?)
+}
+
+#[test]
+fn test_message() -> Result<()> {
+  let run = run()?;
+  let result = sarif_result(serde_json::json!({
+    "id": "default", "arguments": ["x"]
+  }))?;
+  let message = |language| {
+    Localizer::new(&run, language)
+      .message(&result.message, &result)
+      .map(|message| message.to_string())
+  };
+  // the exact language is preferred, rules are matched by guid
+  assert_eq!(message("de-DE").as_deref(), Some("x wird nicht verwendet"));
+  assert_eq!(message("de-AT").as_deref(), Some("x ist unbenutzt"));
+  assert_eq!(message("en-US").as_deref(), Some("x is unused"));
+  assert_eq!(message("ja").as_deref(), Some("x is unused"));
+
+  let global = sarif_result(serde_json::json!({ "id": "done" }))?;
+  assert_eq!(
+    Localizer::new(&run, "de-DE")
+      .message(&global.message, &global)
+      .map(|message| message.to_string())
+      .as_deref(),
+    Some("Analyse fertig")
+  );
+  Ok(())
+}
+
+#[test]
+fn test_descriptions() -> Result<()> {
+  let run = run()?;
+  let result = sarif_result(serde_json::json!({ "text": "x is unused" }))?;
+  let localizer = Localizer::new(&run, "de-DE");
+  assert_eq!(
+    localizer.short_description(&result),
+    Some("Unbenutzte Variable")
+  );
+  // untranslated properties fall back to the rule
+  assert_eq!(
+    localizer.full_description(&result),
+    Some("A variable is never read.")
+  );
+  Ok(())
+}
+
+#[test]
+fn test_notification() -> Result<()> {
+  let run = run()?;
+  let notification: sarif::Notification =
+    serde_json::from_value(serde_json::json!({
+      "descriptor": { "id": "DONE" },
+      "message": { "id": "default", "arguments": ["3"] }
+    }))?;
+  let message = |language| {
+    Localizer::new(&run, language)
+      .notification(&notification)
+      .map(|message| message.to_string())
+  };
+  assert_eq!(message("de-DE").as_deref(), Some("3 Dateien analysiert"));
+  assert_eq!(message("en").as_deref(), Some("3 files analyzed"));
+  Ok(())
+}
+
+#[test]
+fn test_load_translations() -> Result<()> {
+  let dir = tempfile::tempdir()?;
+  std::fs::write(
+    dir.path().join("ja.sarif-external-properties"),
+    serde_json::to_string(&serde_json::json!({
+      "translations": [{
+        "name": "tool",
+        "language": "ja-JP",
+        "associatedComponent": { "name": "tool" },
+        "rules": [{
+          "id": "UNUSED",
+          "messageStrings": { "default": { "text": "{0} は未使用です" } }
+        }]
+      }]
+    }))?,
+  )?;
+  let mut run = run()?;
+  run.artifacts = Some(vec![serde_json::from_value(serde_json::json!({
+    "location": { "uri": "ja.sarif-external-properties" },
+    "roles": ["translation"]
+  }))?]);
+  let resolver = UriResolver::new()
+    .default_base(Url::from_directory_path(dir.path()).unwrap());
+  let translations = load_translations(&run, &resolver)?;
+  assert_eq!(translations.len(), 1);
+
+  let result = sarif_result(serde_json::json!({
+    "id": "default", "arguments": ["x"]
+  }))?;
+  let message = Localizer::new(&run, "ja-JP")
+    .translations(&translations)
+    .message(&result.message, &result)
+    .map(|message| message.to_string());
+  assert_eq!(message.as_deref(), Some("x は未使用です"));
+  Ok(())
+}
@@ -0,0 +1,301 @@
+use anyhow::Result;
+use serde_sarif::merge::{merge, MergeStrategy};
+use serde_sarif::sarif;
+
+fn log(value: serde_json::Value) -> Result<sarif::Sarif> {
+  Ok(serde_json::from_value(value)?)
+}
+
+#[test]
+// Test that coalescing runs of the same tool keeps every index consistent
+fn test_merge_coalesce_by_tool() -> Result<()> {
+  let first = log(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "clippy", "rules": [{ "id": "a" }] } },
+      "originalUriBaseIds": { "SRCROOT": { "uri": "file:///first/" } },
+      "artifacts": [{ "location": { "uri": "lib.rs", "uriBaseId": "SRCROOT" } }],
+      "results": [{ "message": { "text": "first" }, "ruleIndex": 0 }]
+    }]
+  }))?;
+  let second = log(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": { "name": "clippy", "rules": [{ "id": "b" }, { "id": "a" }] }
+      },
+      "originalUriBaseIds": { "SRCROOT": { "uri": "file:///second/" } },
+      "artifacts": [{ "location": { "uri": "main.rs", "uriBaseId": "SRCROOT" } }],
+      "invocations": [{ "executionSuccessful": true }],
+      "results": [{
+        "message": { "text": "second" },
+        "ruleIndex": 1,
+        "provenance": { "invocationIndex": 0 },
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "index": 0, "uriBaseId": "SRCROOT" }
+          }
+        }]
+      }]
+    }, {
+      "tool": { "driver": { "name": "shellcheck" } },
+      "results": []
+    }]
+  }))?;
+
+  let merged = merge(vec![first, second], MergeStrategy::CoalesceByTool)?;
+
+  assert_eq!(
+    serde_json::to_value(&merged)?["runs"],
+    serde_json::json!([{
+      "tool": {
+        "driver": { "name": "clippy", "rules": [{ "id": "a" }, { "id": "b" }] }
+      },
+      "originalUriBaseIds": {
+        "SRCROOT": { "uri": "file:///first/" },
+        "SRCROOT_2": { "uri": "file:///second/" }
+      },
+      "artifacts": [
+        { "location": { "uri": "lib.rs", "uriBaseId": "SRCROOT" } },
+        { "location": { "uri": "main.rs", "uriBaseId": "SRCROOT_2" } }
+      ],
+      "invocations": [{ "executionSuccessful": true }],
+      "results": [
+        { "message": { "text": "first" }, "ruleIndex": 0 },
+        {
+          "message": { "text": "second" },
+          "ruleIndex": 0,
+          "provenance": { "invocationIndex": 0 },
+          "locations": [{
+            "physicalLocation": {
+              "artifactLocation": { "index": 1, "uriBaseId": "SRCROOT_2" }
+            }
+          }]
+        }
+      ]
+    }, {
+      "tool": { "driver": { "name": "shellcheck" } },
+      "results": []
+    }])
+  );
+  assert!(merged.validate_rules().is_empty());
+
+  Ok(())
+}
+
+#[test]
+// Test that the logical locations, taxonomies and web requests of coalesced
+// runs are appended, and the indices into them shifted
+fn test_merge_coalesce_appends_arrays() -> Result<()> {
+  let run = |name: &str, taxonomy: &str| {
+    serde_json::json!({
+      "tool": {
+        "driver": {
+          "name": "zap",
+          "rules": [{
+            "id": name,
+            "relationships": [{
+              "target": { "id": "1", "toolComponent": { "index": 0 } }
+            }]
+          }]
+        }
+      },
+      "taxonomies": [{ "name": taxonomy, "taxa": [{ "id": "1" }] }],
+      "logicalLocations": [
+        { "name": "module" },
+        { "name": name, "parentIndex": 0 }
+      ],
+      "webRequests": [{ "index": 0, "target": name }],
+      "results": [{
+        "message": { "text": name },
+        "ruleIndex": 0,
+        "taxa": [{ "id": "1", "toolComponent": { "index": 0 } }],
+        "webRequest": { "index": 0 },
+        "locations": [{ "logicalLocations": [{ "index": 1 }] }]
+      }]
+    })
+  };
+  let first = log(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [run("fnA", "CWE")]
+  }))?;
+  let second = log(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [run("fnB", "OWASP")]
+  }))?;
+
+  let merged = merge(vec![first, second], MergeStrategy::CoalesceByTool)?;
+
+  let run = serde_json::to_value(&merged.runs[0])?;
+  assert_eq!(
+    run["logicalLocations"],
+    serde_json::json!([
+      { "name": "module" },
+      { "name": "fnA", "parentIndex": 0 },
+      { "name": "module" },
+      { "name": "fnB", "parentIndex": 2 }
+    ])
+  );
+  assert_eq!(run["taxonomies"][1]["name"], "OWASP");
+  assert_eq!(
+    run["tool"]["driver"]["rules"][1]["relationships"][0]["target"]
+      ["toolComponent"]["index"],
+    1
+  );
+  assert_eq!(
+    run["webRequests"][1],
+    serde_json::json!({ "index": 1, "target": "fnB" })
+  );
+  let result = &run["results"][1];
+  assert_eq!(result["ruleIndex"], 1);
+  assert_eq!(result["taxa"][0]["toolComponent"]["index"], 1);
+  assert_eq!(result["webRequest"]["index"], 1);
+  assert_eq!(result["locations"][0]["logicalLocations"][0]["index"], 3);
+
+  Ok(())
+}
+
+#[test]
+// Test that the rule configuration overrides and notifications of the
+// invocations of coalesced runs refer to the merged rules and artifacts
+fn test_merge_coalesce_invocations() -> Result<()> {
+  let run = |rules: serde_json::Value, rule: i64| {
+    serde_json::json!({
+      "tool": { "driver": { "name": "clippy", "rules": rules } },
+      "artifacts": [{ "location": { "uri": "src/main.rs" } }],
+      "invocations": [{
+        "executionSuccessful": true,
+        "ruleConfigurationOverrides": [{
+          "descriptor": { "index": rule },
+          "configuration": { "level": "error" }
+        }],
+        "toolExecutionNotifications": [{
+          "message": { "text": "crashed" },
+          "associatedRule": { "index": rule },
+          "locations": [{
+            "physicalLocation": { "artifactLocation": { "index": 0 } }
+          }]
+        }]
+      }],
+      "results": [{
+        "message": { "text": "result" },
+        "ruleIndex": rule,
+        "provenance": { "invocationIndex": 0 }
+      }]
+    })
+  };
+  let first = log(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [run(serde_json::json!([{ "id": "a" }]), 0)]
+  }))?;
+  let second = log(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [run(serde_json::json!([{ "id": "b" }, { "id": "c" }]), 1)]
+  }))?;
+
+  let merged = merge(vec![first, second], MergeStrategy::CoalesceByTool)?;
+
+  let run = &merged.runs[0];
+  let invocation = &run.invocations.as_ref().unwrap()[1];
+  let notification =
+    &invocation.tool_execution_notifications.as_ref().unwrap()[0];
+  let rule = run.resolve_associated_rule(notification).unwrap();
+  assert_eq!(rule.id, "c");
+  let location = notification.locations.as_ref().unwrap()[0]
+    .physical_location
+    .as_ref()
+    .and_then(|location| location.artifact_location.as_ref())
+    .unwrap();
+  assert_eq!(location.index, Some(1));
+  let rules = run.tool.driver.rules.as_ref().unwrap();
+  let overridden = |invocation: &sarif::Invocation| {
+    let descriptor =
+      &invocation.rule_configuration_overrides.as_ref().unwrap()[0].descriptor;
+    run
+      .resolve_descriptor(descriptor, false)
+      .unwrap()
+      .id
+      .clone()
+  };
+  let invocations = run.invocations.as_ref().unwrap();
+  assert_eq!(rules.len(), 3);
+  assert_eq!(overridden(&invocations[0]), "a");
+  assert_eq!(overridden(&invocations[1]), "c");
+  Ok(())
+}
+
+#[test]
+// Test that runs which count columns in different units are not coalesced
+fn test_merge_coalesce_column_kind() -> Result<()> {
+  let run = |column_kind: &str| {
+    log(serde_json::json!({
+      "version": "2.1.0",
+      "runs": [{
+        "tool": { "driver": { "name": "clippy" } },
+        "columnKind": column_kind
+      }]
+    }))
+  };
+  let merged = merge(
+    vec![run("utf16CodeUnits")?, run("unicodeCodePoints")?],
+    MergeStrategy::CoalesceByTool,
+  );
+  assert_eq!(
+    merged.unwrap_err().to_string(),
+    "cannot coalesce the runs of clippy, which count columns in different \
+     units"
+  );
+  Ok(())
+}
+
+#[test]
+// Test that the property bags of the logs are merged, and that the locations
+// of the nodes of coalesced graphs are remapped
+fn test_merge_properties_and_graphs() -> Result<()> {
+  let run = |uri: &str| {
+    serde_json::json!({
+      "tool": { "driver": { "name": "clippy" } },
+      "artifacts": [{ "location": { "uri": uri } }],
+      "graphs": [{
+        "nodes": [{
+          "id": "root",
+          "location": {
+            "physicalLocation": { "artifactLocation": { "index": 0 } }
+          },
+          "children": [{
+            "id": "child",
+            "location": {
+              "physicalLocation": { "artifactLocation": { "index": 0 } }
+            }
+          }]
+        }]
+      }]
+    })
+  };
+  let first = log(serde_json::json!({
+    "version": "2.1.0",
+    "properties": { "tags": ["a"], "owner": "first" },
+    "runs": [run("src/a.rs")]
+  }))?;
+  let second = log(serde_json::json!({
+    "version": "2.1.0",
+    "properties": { "tags": ["a", "b"], "owner": "second", "team": "lint" },
+    "runs": [run("src/b.rs")]
+  }))?;
+
+  let merged = merge(vec![first, second], MergeStrategy::CoalesceByTool)?;
+
+  assert_eq!(
+    serde_json::to_value(&merged.properties)?,
+    serde_json::json!({ "tags": ["a", "b"], "owner": "first", "team": "lint" })
+  );
+  let run = serde_json::to_value(&merged.runs[0])?;
+  let node = &run["graphs"][1]["nodes"][0];
+  let index = |node: &serde_json::Value| {
+    node["location"]["physicalLocation"]["artifactLocation"]["index"].clone()
+  };
+  assert_eq!(index(node), 1);
+  assert_eq!(index(&node["children"][0]), 1);
+  assert_eq!(index(&run["graphs"][0]["nodes"][0]), 0);
+  Ok(())
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use serde_sarif::message::{LinkTarget, Span};
+use serde_sarif::sarif;
+
+fn run() -> Result<sarif::Run> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "tool": { "driver": {
+      "name": "tool",
+      "globalMessageStrings": {
+        "global": { "text": "global {0}" }
+      },
+      "rules": [{
+        "id": "RULE",
+        "messageStrings": {
+          "default": { "text": "{{{0}}} is {1} but {2}" }
+        }
+      }]
+    } }
+  }))?)
+}
+
+fn result(message: serde_json::Value) -> Result<sarif::Result> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "ruleId": "RULE",
+    "message": message,
+    "relatedLocations": [{ "id": 0 }]
+  }))?)
+}
+
+fn format(message: serde_json::Value) -> Result<String> {
+  let run = run()?;
+  let result = result(message)?;
+  Ok(result.message.format(&run, &result).unwrap().to_string())
+}
+
+#[test]
+fn test_arguments() -> Result<()> {
+  // placeholders without an argument are kept
+  assert_eq!(
+    format(serde_json::json!({ "id": "default", "arguments": ["x", "1"] }))?,
+    "{x} is 1 but {2}"
+  );
+  assert_eq!(
+    format(serde_json::json!({ "id": "global", "arguments": ["message"] }))?,
+    "global message"
+  );
+  assert_eq!(
+    format(serde_json::json!({ "text": "\\[not a link\\] {{0}}" }))?,
+    "[not a link] {0}"
+  );
+  Ok(())
+}
+
+#[test]
+fn test_links() -> Result<()> {
+  let run = run()?;
+  let result = result(serde_json::json!({
+    "text": "see [{0}](0), [the docs](https://example.com) and [nothing](7)",
+    "arguments": ["here"]
+  }))?;
+  let message = result.message.format(&run, &result).unwrap();
+  let related = &result.related_locations.as_ref().unwrap()[0];
+  assert_eq!(
+    message.spans,
+    vec![
+      Span::Text("see ".into()),
+      Span::Link {
+        text: "here".into(),
+        target: LinkTarget::Location(related),
+      },
+      Span::Text(", ".into()),
+      Span::Link {
+        text: "the docs".into(),
+        target: LinkTarget::Uri("https://example.com".into()),
+      },
+      // links to undefined locations are left as text
+      Span::Text(" and nothing".into()),
+    ]
+  );
+  Ok(())
+}
@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde_sarif::properties::{
+  GithubRuleProperties, Precision, ProblemSeverity, PropertyError,
+};
+use serde_sarif::sarif;
+
+fn bag(value: serde_json::Value) -> Result<sarif::PropertyBag> {
+  Ok(serde_json::from_value(value)?)
+}
+
+#[test]
+fn test_round_trip() -> Result<()> {
+  let json = serde_json::json!({
+    "tags": ["maintainability"],
+    "precision": "very-high",
+    "nested": { "count": 3 }
+  });
+  let properties = bag(json.clone())?;
+  assert_eq!(properties.tags(), ["maintainability"]);
+  assert_eq!(properties.get::<i64>("missing")?, None);
+  assert_eq!(
+    properties.get::<Precision>("precision")?,
+    Some(Precision::VeryHigh)
+  );
+  assert_eq!(serde_json::to_value(&properties)?, json);
+  Ok(())
+}
+
+#[test]
+fn test_set() -> Result<()> {
+  let mut properties = sarif::PropertyBag::default();
+  properties.set("tags", vec!["security"])?;
+  properties.add_tag("security");
+  properties.set("problem.severity", ProblemSeverity::Warning)?;
+  assert_eq!(properties.tags(), ["security"]);
+  assert_eq!(
+    serde_json::to_value(&properties)?,
+    serde_json::json!({ "tags": ["security"], "problem.severity": "warning" })
+  );
+  assert!(matches!(
+    properties.get::<i64>("problem.severity"),
+    Err(PropertyError::Invalid { key, .. }) if key == "problem.severity"
+  ));
+  // tags are removed like any other property
+  assert_eq!(
+    properties.remove("tags"),
+    Some(serde_json::json!(["security"]))
+  );
+  assert_eq!(properties.remove("tags"), None);
+  assert_eq!(
+    properties.remove("problem.severity"),
+    Some(serde_json::json!("warning"))
+  );
+  assert_eq!(serde_json::to_value(&properties)?, serde_json::json!({}));
+  Ok(())
+}
+
+#[test]
+fn test_github_extension() -> Result<()> {
+  let mut properties = bag(serde_json::json!({
+    "security-severity": 9.1, "kind": "path-problem"
+  }))?;
+  let github: GithubRuleProperties = properties.extension()?;
+  assert_eq!(github.security_severity, Some(9.1));
+  assert_eq!(github.precision, None);
+  properties.set_extension(&GithubRuleProperties {
+    security_severity: Some(7.0),
+    ..github
+  })?;
+  assert_eq!(
+    serde_json::to_value(&properties)?,
+    serde_json::json!({ "security-severity": "7", "kind": "path-problem" })
+  );
+  // the value is written as is, so it reads back unchanged
+  let github = GithubRuleProperties {
+    security_severity: Some(9.25),
+    ..Default::default()
+  };
+  properties.set_extension(&github)?;
+  assert_eq!(
+    properties.get::<String>("security-severity")?.unwrap(),
+    "9.25"
+  );
+  let github: GithubRuleProperties = properties.extension()?;
+  assert_eq!(github.security_severity, Some(9.25));
+  Ok(())
+}
+
+#[test]
+fn test_hash() -> Result<()> {
+  let results: HashSet<sarif::Result> = vec![
+    serde_json::json!({ "message": { "text": "a" }, "properties": { "n": 1 } }),
+    serde_json::json!({ "message": { "text": "a" }, "properties": { "n": 1 } }),
+    serde_json::json!({ "message": { "text": "a" }, "properties": { "n": 2 } }),
+  ]
+  .into_iter()
+  .map(serde_json::from_value)
+  .collect::<Result<_, _>>()?;
+  assert_eq!(results.len(), 2);
+  Ok(())
+}
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use serde_sarif::query::{is_suppressed, resolve_level, Query};
+use serde_sarif::sarif;
+
+fn sarif() -> Result<sarif::Sarif> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": {
+        "name": "clippy",
+        "rules": [
+          { "id": "clippy::unused", "properties": { "tags": ["style"] } },
+          { "id": "clippy::absurd", "defaultConfiguration": { "level": "error" } },
+          { "id": "clippy::overridden" }
+        ]
+      } },
+      "invocations": [{
+        "executionSuccessful": true,
+        "ruleConfigurationOverrides": [{
+          "descriptor": { "id": "clippy::overridden" },
+          "configuration": { "level": "note" }
+        }]
+      }],
+      "artifacts": [
+        { "location": { "uri": "src" } },
+        { "location": { "uri": "src/main.rs" }, "parentIndex": 0 },
+        { "location": { "uri": "tests/test.rs" } }
+      ],
+      "results": [{
+        "ruleIndex": 0,
+        "message": { "text": "unused variable" },
+        "locations": [{
+          "physicalLocation": { "artifactLocation": { "index": 2 } }
+        }]
+      }, {
+        "ruleIndex": 1,
+        "message": { "text": "comparison is always true" },
+        "baselineState": "new",
+        "locations": [{
+          "physicalLocation": { "artifactLocation": { "index": 1 } }
+        }]
+      }, {
+        "ruleId": "clippy::overridden",
+        "message": { "text": "overridden" },
+        "provenance": { "invocationIndex": 0 },
+        "suppressions": [{ "kind": "inSource" }]
+      }, {
+        "kind": "pass",
+        "level": "error",
+        "message": { "text": "passed" }
+      }]
+    }, {
+      "tool": { "driver": { "name": "shellcheck" } },
+      "results": [{
+        "ruleId": "SC2086",
+        "level": "warning",
+        "message": { "text": "double quote to prevent globbing" }
+      }]
+    }]
+  }))?)
+}
+
+fn messages(query: &Query, sarif: &sarif::Sarif) -> Vec<String> {
+  query
+    .results(sarif)
+    .filter_map(|(_, result)| result.message.text.clone())
+    .collect()
+}
+
+#[test]
+fn test_resolve_level() -> Result<()> {
+  let sarif = sarif()?;
+  let levels: Vec<_> = Query::new()
+    .results(&sarif)
+    .map(|(run, result)| resolve_level(run, result))
+    .collect();
+  assert_eq!(
+    levels,
+    vec![
+      sarif::ResultLevel::Warning,
+      sarif::ResultLevel::Error,
+      sarif::ResultLevel::Note,
+      sarif::ResultLevel::None,
+      sarif::ResultLevel::Warning,
+    ]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_filters() -> Result<()> {
+  let sarif = sarif()?;
+  let error = Query::new().level(sarif::ResultLevel::Error);
+  assert_eq!(messages(&error, &sarif), vec!["comparison is always true"]);
+  let rule = Query::new().rule("clippy::*").rule("SC*");
+  assert_eq!(messages(&rule, &sarif).len(), 4);
+  let path = Query::new().path("src/**");
+  assert_eq!(messages(&path, &sarif), vec!["comparison is always true"]);
+  let tool = Query::new().tool("shellcheck");
+  assert_eq!(messages(&tool, &sarif).len(), 1);
+  let kind = Query::new().kind(sarif::ResultKind::Pass);
+  assert_eq!(messages(&kind, &sarif), vec!["passed"]);
+  let state = Query::new().baseline_state(sarif::ResultBaselineState::New);
+  assert_eq!(messages(&state, &sarif), vec!["comparison is always true"]);
+  let suppressed = Query::new().suppressed(true);
+  assert_eq!(messages(&suppressed, &sarif), vec!["overridden"]);
+  let tag = Query::new().tag("style");
+  assert_eq!(messages(&tag, &sarif), vec!["unused variable"]);
+  // filters are combined
+  let none = Query::new().tool("shellcheck").rule("clippy::*");
+  assert!(messages(&none, &sarif).is_empty());
+  Ok(())
+}
+
+#[test]
+fn test_is_suppressed() -> Result<()> {
+  let result = |suppressions: serde_json::Value| -> Result<sarif::Result> {
+    Ok(serde_json::from_value(serde_json::json!({
+      "message": { "text": "suppressed?" },
+      "suppressions": suppressions
+    }))?)
+  };
+  assert!(!is_suppressed(&result(serde_json::json!([]))?));
+  assert!(is_suppressed(&result(serde_json::json!([
+    { "kind": "external", "status": "accepted" }
+  ]))?));
+  assert!(!is_suppressed(&result(serde_json::json!([
+    { "kind": "external" },
+    { "kind": "external", "status": "underReview" }
+  ]))?));
+  Ok(())
+}
+
+#[test]
+fn test_prune() -> Result<()> {
+  let sarif = sarif()?;
+  let pruned = Query::new().level(sarif::ResultLevel::Error).prune(&sarif);
+  let run = &pruned.runs[0];
+  let rules = run.tool.driver.rules.as_ref().unwrap();
+  // the overridden rule is kept for the invocation's override
+  let ids: Vec<_> = rules.iter().map(|rule| &rule.id).collect();
+  assert_eq!(ids, vec!["clippy::absurd", "clippy::overridden"]);
+  let results = run.results.as_ref().unwrap();
+  assert_eq!(results.len(), 1);
+  assert_eq!(results[0].rule_index, Some(0));
+  // the parent of the remaining artifact is kept too
+  let artifacts = run.artifacts.as_ref().unwrap();
+  assert_eq!(artifacts.len(), 2);
+  assert_eq!(artifacts[1].parent_index, Some(0));
+  let location = results[0].locations.as_ref().unwrap()[0]
+    .physical_location
+    .as_ref()
+    .and_then(|location| location.artifact_location.as_ref())
+    .unwrap();
+  assert_eq!(location.index, Some(1));
+  assert!(pruned.runs[1].results.as_ref().unwrap().is_empty());
+  Ok(())
+}
+
+// Returns a log whose invocation overrides the level of rule "b" by index,
+// and whose notification is associated with rule "c"
+fn overridden() -> Result<sarif::Sarif> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": {
+        "name": "tool",
+        "rules": [{ "id": "a" }, { "id": "b" }, { "id": "c" }]
+      } },
+      "invocations": [{
+        "executionSuccessful": true,
+        "ruleConfigurationOverrides": [{
+          "descriptor": { "index": 1 },
+          "configuration": { "level": "error" }
+        }],
+        "toolExecutionNotifications": [{
+          "message": { "text": "notified" },
+          "associatedRule": { "index": 2 }
+        }]
+      }],
+      "results": [
+        { "ruleId": "a", "message": { "text": "a" } },
+        {
+          "ruleId": "b",
+          "message": { "text": "b" },
+          "provenance": { "invocationIndex": 0 }
+        }
+      ]
+    }]
+  }))?)
+}
+
+#[test]
+// Test that rule configuration overrides are matched by index as well as id
+fn test_resolve_level_override_index() -> Result<()> {
+  let sarif = overridden()?;
+  let levels: Vec<_> = Query::new()
+    .results(&sarif)
+    .map(|(run, result)| resolve_level(run, result))
+    .collect();
+  assert_eq!(
+    levels,
+    vec![sarif::ResultLevel::Warning, sarif::ResultLevel::Error]
+  );
+  Ok(())
+}
+
+#[test]
+// Test that pruning keeps the rules that overrides and notifications refer
+// to, and updates their indices
+fn test_prune_overrides() -> Result<()> {
+  let sarif = overridden()?;
+  let pruned = Query::new().rule("b").prune(&sarif);
+  let run = &pruned.runs[0];
+  let ids: Vec<_> = run
+    .tool
+    .driver
+    .rules
+    .iter()
+    .flatten()
+    .map(|r| &r.id)
+    .collect();
+  assert_eq!(ids, vec!["b", "c"]);
+  let invocation = &run.invocations.as_ref().unwrap()[0];
+  let overrides = invocation.rule_configuration_overrides.as_ref().unwrap();
+  assert_eq!(overrides[0].descriptor.index, Some(0));
+  let notifications = invocation.tool_execution_notifications.as_ref().unwrap();
+  assert_eq!(
+    notifications[0].associated_rule.as_ref().unwrap().index,
+    Some(1)
+  );
+  assert_eq!(
+    resolve_level(run, &run.results.as_ref().unwrap()[0]),
+    sarif::ResultLevel::Error
+  );
+  Ok(())
+}
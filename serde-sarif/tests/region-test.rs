@@ -0,0 +1,94 @@
+use anyhow::Result;
+use serde_sarif::region::{ColumnUnit, SourceText};
+use serde_sarif::sarif;
+
+// 'é' is two bytes, '😀' is four bytes and two UTF-16 code units
+const CONTENTS: &str = "aé😀b\r\nsecond\n";
+
+fn region(value: serde_json::Value) -> Result<sarif::Region> {
+  Ok(serde_json::from_value(value)?)
+}
+
+#[test]
+fn test_column_unit() -> Result<()> {
+  let run: sarif::Run = serde_json::from_value(serde_json::json!({
+    "tool": { "driver": { "name": "shellcheck" } },
+  }))?;
+  assert_eq!(ColumnUnit::of(&run), ColumnUnit::Utf16CodeUnits);
+  let run: sarif::Run = serde_json::from_value(serde_json::json!({
+    "tool": { "driver": { "name": "shellcheck" } },
+    "columnKind": "unicodeCodePoints"
+  }))?;
+  assert_eq!(ColumnUnit::of(&run), ColumnUnit::UnicodeCodePoints);
+  Ok(())
+}
+
+#[test]
+fn test_convert_column() {
+  let text = SourceText::new(CONTENTS);
+  let convert = |column, to| {
+    text
+      .convert_column(1, column, ColumnUnit::Bytes, to)
+      .unwrap()
+  };
+  assert_eq!(convert(8, ColumnUnit::Utf16CodeUnits), 5);
+  assert_eq!(convert(8, ColumnUnit::UnicodeCodePoints), 4);
+  assert_eq!(convert(4, ColumnUnit::Utf16CodeUnits), 3);
+  assert_eq!(text.position(10, ColumnUnit::Utf16CodeUnits), (2, 1));
+  // an index which owns its contents converts the same
+  let owned = SourceText::owned(CONTENTS.to_string());
+  assert_eq!(
+    owned.convert_column(1, 8, ColumnUnit::Bytes, ColumnUnit::Utf16CodeUnits),
+    Some(5)
+  );
+}
+
+#[test]
+fn test_byte_range() -> Result<()> {
+  let text = SourceText::new(CONTENTS);
+  let utf16 = ColumnUnit::Utf16CodeUnits;
+  let code_points = ColumnUnit::UnicodeCodePoints;
+
+  // the end of the line excludes the carriage return
+  let emoji = region(serde_json::json!({ "startLine": 1, "startColumn": 3 }))?;
+  assert_eq!(text.byte_range(&emoji, utf16), Some(3..8));
+  let emoji = region(serde_json::json!({
+    "startLine": 1, "startColumn": 3, "endColumn": 4
+  }))?;
+  assert_eq!(text.byte_range(&emoji, code_points), Some(3..7));
+  let emoji = region(serde_json::json!({ "charOffset": 2, "charLength": 2 }))?;
+  assert_eq!(text.byte_range(&emoji, utf16), Some(3..7));
+  let emoji = region(serde_json::json!({ "charOffset": 2, "charLength": 1 }))?;
+  assert_eq!(text.byte_range(&emoji, code_points), Some(3..7));
+  // byte offsets take precedence
+  let emoji = region(serde_json::json!({
+    "startLine": 2, "byteOffset": 3, "byteLength": 4
+  }))?;
+  assert_eq!(text.byte_range(&emoji, utf16), Some(3..7));
+  assert_eq!(
+    text.byte_range(&region(serde_json::json!({}))?, utf16),
+    None
+  );
+  Ok(())
+}
+
+#[test]
+fn test_normalize() -> Result<()> {
+  let text = SourceText::new(CONTENTS);
+  let second = region(serde_json::json!({ "startLine": 2 }))?;
+  let second = text.normalize(&second, ColumnUnit::Utf16CodeUnits).unwrap();
+  assert_eq!(
+    second,
+    region(serde_json::json!({
+      "startLine": 2,
+      "startColumn": 1,
+      "endLine": 2,
+      "endColumn": 7,
+      "charOffset": 7,
+      "charLength": 6,
+      "byteOffset": 10,
+      "byteLength": 6
+    }))?
+  );
+  Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+
+fn run() -> Result<sarif::Run> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "tool": {
+      "driver": {
+        "name": "CodeQL",
+        "rules": [{ "id": "CA2101" }],
+        "notifications": [{ "id": "query-failed" }]
+      },
+      "extensions": [{
+        "name": "codeql/javascript-queries",
+        "guid": "5d9a4fcd-3f4e-4e4b-a1c4-9d7a5e7f8a11",
+        "rules": [
+          { "id": "js/sql-injection" },
+          { "id": "js/xss", "guid": "b6c1b8a4-3b0a-4a43-9b2e-8f1f0d6f0c1e" }
+        ]
+      }]
+    },
+    "results": [
+      {
+        "message": { "text": "by index" },
+        "rule": { "index": 1, "toolComponent": { "index": 0 } }
+      },
+      {
+        "message": { "text": "by guid" },
+        "rule": {
+          "guid": "b6c1b8a4-3b0a-4a43-9b2e-8f1f0d6f0c1e",
+          "toolComponent": { "guid": "5d9a4fcd-3f4e-4e4b-a1c4-9d7a5e7f8a11" }
+        }
+      },
+      {
+        "message": { "text": "by id" },
+        "ruleId": "js/sql-injection",
+        "rule": { "toolComponent": { "name": "codeql/javascript-queries" } }
+      },
+      { "message": { "text": "hierarchical" }, "ruleId": "CA2101/md5" },
+      {
+        "message": { "text": "unset index" },
+        "ruleId": "CA2101",
+        "ruleIndex": -1
+      },
+      { "message": { "text": "unknown" }, "ruleId": "CA9999" }
+    ]
+  }))?)
+}
+
+#[test]
+fn test_resolve_rule() -> Result<()> {
+  let run = run()?;
+  let ids: Vec<Option<&str>> = run
+    .results
+    .iter()
+    .flatten()
+    .map(|result| run.resolve_rule(result).map(|rule| rule.id.as_str()))
+    .collect();
+  assert_eq!(
+    ids,
+    vec![
+      Some("js/xss"),
+      Some("js/xss"),
+      Some("js/sql-injection"),
+      Some("CA2101"),
+      Some("CA2101"),
+      None
+    ]
+  );
+  Ok(())
+}
+
+#[test]
+fn test_resolve_notification() -> Result<()> {
+  let run = run()?;
+  let notification: sarif::Notification =
+    serde_json::from_value(serde_json::json!({
+      "message": { "text": "query failed" },
+      "descriptor": { "id": "query-failed" },
+      "associatedRule": { "index": 0, "toolComponent": { "index": 0 } }
+    }))?;
+  assert_eq!(
+    run
+      .resolve_notification(&notification)
+      .map(|d| d.id.as_str()),
+    Some("query-failed")
+  );
+  assert_eq!(
+    run
+      .resolve_associated_rule(&notification)
+      .map(|d| d.id.as_str()),
+    Some("js/sql-injection")
+  );
+  Ok(())
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+use serde_sarif::stream::{ResultReader, SarifStreamWriter};
+use std::convert::{TryFrom, TryInto};
+use std::io::Cursor;
+
+#[test]
+// Test that reading yields the same results and runs as deserializing the
+// whole log, including runs whose metadata follows their results
+fn test_result_reader() -> Result<()> {
+  let log = r#"{
+    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+    "version": "2.1.0",
+    "runs": [{
+      "results": [{
+        "message": { "text": "first" },
+        "ruleIndex": 0,
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "src/lib.rs" },
+            "region": { "startLine": 10, "startColumn": -1 }
+          }
+        }]
+      }, {
+        "message": { "text": "second" },
+        "rank": 12.5e-1
+      }],
+      "properties": { "count": 2, "nested": [1, { "a": [] }] },
+      "tool": { "driver": { "name": "first", "rules": [{ "id": "rule" }] } }
+    }, {
+      "tool": { "driver": { "name": "second" } },
+      "results": []
+    }, {
+      "tool": { "driver": { "name": "third" } },
+      "results": [{ "message": { "text": "third" } }]
+    }]
+  }"#;
+
+  let sarif: sarif::Sarif = serde_json::from_str(log)?;
+  let reader = ResultReader::new(Cursor::new(log))?;
+
+  assert_eq!(reader.runs().len(), 3);
+  assert_eq!(reader.runs()[1].tool.driver.name, "second");
+  assert_eq!(reader.runs()[1].results, None);
+
+  let streamed = reader.collect::<Result<Vec<_>, _>>()?;
+  let expected: Vec<_> = sarif
+    .runs
+    .iter()
+    .enumerate()
+    .flat_map(|(i, run)| {
+      run
+        .results
+        .iter()
+        .flatten()
+        .map(move |result| (i, result.clone()))
+    })
+    .collect();
+
+  assert_eq!(
+    streamed
+      .iter()
+      .map(|r| (r.run_index, r.result.clone()))
+      .collect::<Vec<_>>(),
+    expected
+  );
+  assert_eq!(streamed[0].run.tool.driver.name, "first");
+  assert_eq!(streamed[2].run.tool.driver.name, "third");
+
+  Ok(())
+}
+
+#[test]
+// Test that malformed logs are reported as errors
+fn test_result_reader_error() -> Result<()> {
+  let log = r#"{ "runs": [{ "tool": { "driver": { "name": "tool" } },
+    "results": [{ "message": { "text": "message" } } { }] }] }"#;
+
+  assert!(ResultReader::new(Cursor::new(log)).is_err());
+
+  Ok(())
+}
+
+#[test]
+// Test that the stream writer produces the same log as serializing it whole
+fn test_sarif_stream_writer() -> Result<()> {
+  let tool: sarif::Tool = sarif::ToolComponentBuilder::default()
+    .name("tool")
+    .build()?
+    .try_into()?;
+  let rule = sarif::ReportingDescriptorBuilder::default()
+    .id("rule")
+    .build()?;
+  let result = sarif::ResultBuilder::default()
+    .message(sarif::Message::try_from("message")?)
+    .rule_id("rule")
+    .rule_index(0)
+    .build()?;
+
+  let mut writer = SarifStreamWriter::pretty(vec![])?;
+  let mut run = writer.begin_run(
+    sarif::RunBuilder::default()
+      .tool(tool.clone())
+      .column_kind(sarif::ResultColumnKind::UnicodeCodePoints)
+      .build()?,
+  )?;
+  assert_eq!(run.write_rule(rule.clone()), 0);
+  run.write_result(&result)?;
+  run.write_result(&result)?;
+  run.end()?;
+  writer
+    .begin_run(sarif::RunBuilder::default().tool(tool.clone()).build()?)?
+    .end()?;
+  let log = String::from_utf8(writer.finish()?)?;
+
+  let mut driver = tool.driver.clone();
+  driver.rules = Some(vec![rule]);
+  let expected = sarif::SarifBuilder::default()
+    .schema(sarif::SCHEMA_URL)
+    .version(sarif::Version::V2_1_0)
+    .runs(vec![
+      sarif::RunBuilder::default()
+        .tool(sarif::Tool::try_from(driver)?)
+        .column_kind(sarif::ResultColumnKind::UnicodeCodePoints)
+        .results(vec![result.clone(), result])
+        .build()?,
+      sarif::RunBuilder::default()
+        .tool(tool)
+        .results(vec![])
+        .build()?,
+    ])
+    .build()?;
+
+  assert_eq!(serde_json::from_str::<sarif::Sarif>(&log)?, expected);
+  assert_eq!(
+    serde_json::from_str::<serde_json::Value>(&log)?,
+    serde_json::to_value(&expected)?
+  );
+
+  Ok(())
+}
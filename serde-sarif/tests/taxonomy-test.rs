@@ -0,0 +1,173 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+use serde_sarif::taxonomy::{
+  cwe, owasp_top_ten, resolve_taxon, taxa, TaxonomyError, TaxonomyMapping,
+};
+
+fn run() -> Result<sarif::Run> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "tool": { "driver": {
+      "name": "hadolint",
+      "rules": [{ "id": "DL3002" }, { "id": "DL3008" }]
+    } },
+    "results": [
+      { "ruleIndex": 0, "message": { "text": "last user should not be root" } },
+      {
+        "ruleIndex": 1,
+        "message": { "text": "pin versions" },
+        "taxa": [{ "id": "A06:2021", "toolComponent": { "index": 1 } }]
+      }
+    ]
+  }))?)
+}
+
+#[test]
+fn test_bundled_taxonomies() {
+  let cwe = cwe();
+  assert_eq!(cwe.name, "CWE");
+  assert!(cwe.taxa.iter().flatten().any(|taxon| taxon.id == "CWE-79"));
+  assert_eq!(owasp_top_ten().taxa.map(|taxa| taxa.len()), Some(10));
+}
+
+#[test]
+fn test_mapping() -> Result<()> {
+  let mapping = TaxonomyMapping::from_reader(
+    r#"{ "DL3002": ["CWE-250", "A05:2021"], "DL3008": ["CWE-1357"] }"#
+      .as_bytes(),
+    vec![cwe(), owasp_top_ten()],
+  )?;
+  let mut run = run()?;
+  mapping.apply(&mut run);
+  // applying the mapping twice adds the taxonomies once
+  mapping.apply(&mut run);
+  let names: Vec<_> = run
+    .taxonomies
+    .iter()
+    .flatten()
+    .map(|taxonomy| taxonomy.name.as_str())
+    .collect();
+  assert_eq!(names, vec!["CWE", "OWASP Top 10"]);
+  // nor are the relationships of the rules duplicated
+  let relationships: Vec<_> = run
+    .tool
+    .driver
+    .rules
+    .iter()
+    .flatten()
+    .map(|rule| rule.relationships.as_ref().map_or(0, Vec::len))
+    .collect();
+  assert_eq!(relationships, vec![2, 1]);
+  assert_eq!(
+    run
+      .tool
+      .driver
+      .supported_taxonomies
+      .as_ref()
+      .map(|supported| supported.len()),
+    Some(2)
+  );
+
+  let ids = |result: &sarif::Result| -> Vec<String> {
+    taxa(&run, result)
+      .into_iter()
+      .map(|(_, taxon)| taxon.id.clone())
+      .collect()
+  };
+  let results = run.results.as_ref().unwrap();
+  assert_eq!(ids(&results[0]), vec!["CWE-250", "A05:2021"]);
+  // the taxa of the result come first
+  assert_eq!(ids(&results[1]), vec!["A06:2021", "CWE-1357"]);
+  Ok(())
+}
+
+#[test]
+fn test_resolve_taxon() -> Result<()> {
+  let mut run = run()?;
+  run.taxonomies = Some(vec![cwe()]);
+  let reference: sarif::ReportingDescriptorReference =
+    serde_json::from_value(serde_json::json!({
+      "id": "CWE-22", "toolComponent": { "name": "CWE" }
+    }))?;
+  let (taxonomy, taxon) = resolve_taxon(&run, &reference).unwrap();
+  assert_eq!(taxonomy.name, "CWE");
+  assert_eq!(
+    taxon.help_uri.as_deref(),
+    Some("https://cwe.mitre.org/data/definitions/22.html")
+  );
+  Ok(())
+}
+
+#[test]
+fn test_unknown_taxon() {
+  let mapping = TaxonomyMapping::new(vec![cwe()]).map("DL3002", "A05:2021");
+  assert!(matches!(
+    mapping,
+    Err(TaxonomyError::UnknownTaxon { rule, taxon })
+      if rule == "DL3002" && taxon == "A05:2021"
+  ));
+}
+
+#[test]
+// Test that weaknesses missing from the bundled CWE snapshot are added with
+// their helpUri, while malformed ids are still rejected
+fn test_unbundled_weakness() -> Result<()> {
+  assert!(!cwe()
+    .taxa
+    .iter()
+    .flatten()
+    .any(|taxon| taxon.id == "CWE-611"));
+  let mapping = TaxonomyMapping::new(vec![cwe(), owasp_top_ten()])
+    .map("DL3002", "CWE-611")?
+    .map("DL3008", "CWE-611")?;
+  let mut run = run()?;
+  mapping.apply(&mut run);
+  let results = run.results.as_ref().unwrap();
+  let (taxonomy, taxon) = taxa(&run, &results[0])[0];
+  assert_eq!(taxonomy.name, "CWE");
+  assert_eq!(taxon.id, "CWE-611");
+  assert_eq!(
+    taxon.help_uri.as_deref(),
+    Some("https://cwe.mitre.org/data/definitions/611.html")
+  );
+  assert!(TaxonomyMapping::new(vec![cwe()])
+    .map("DL3002", "CWE-06")
+    .is_err());
+  assert!(TaxonomyMapping::new(vec![cwe()])
+    .map("DL3002", "CWE-x")
+    .is_err());
+  assert!(TaxonomyMapping::new(vec![owasp_top_ten()])
+    .map("DL3002", "CWE-611")
+    .is_err());
+  Ok(())
+}
+
+#[test]
+// Test that the taxa mapped to are added to a taxonomy of the same name which
+// the run has already, so that every relationship resolves
+fn test_mapping_existing_taxonomy() -> Result<()> {
+  let mut run = run()?;
+  run.taxonomies = Some(vec![serde_json::from_value(serde_json::json!({
+    "name": "CWE",
+    "guid": "00000000-0000-4000-8000-000000000000",
+    "taxa": [{ "id": "CWE-79" }]
+  }))?]);
+  TaxonomyMapping::new(vec![cwe()])
+    .map("DL3002", "CWE-250")?
+    .map("DL3008", "CWE-611")?
+    .apply(&mut run);
+  let taxonomies = run.taxonomies.as_ref().unwrap();
+  assert_eq!(taxonomies.len(), 1);
+  let ids: Vec<_> = taxonomies[0]
+    .taxa
+    .iter()
+    .flatten()
+    .map(|taxon| taxon.id.as_str())
+    .collect();
+  assert_eq!(ids, vec!["CWE-79", "CWE-250", "CWE-611"]);
+  for rule in run.tool.driver.rules.iter().flatten() {
+    let relationships = rule.relationships.as_deref().unwrap_or_default();
+    assert_eq!(relationships.len(), 1);
+    assert!(resolve_taxon(&run, &relationships[0].target).is_some());
+  }
+  Ok(())
+}
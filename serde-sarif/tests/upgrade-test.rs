@@ -0,0 +1,150 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+use serde_sarif::upgrade::{detect, upgrade, SourceVersion};
+
+#[test]
+fn test_detect() {
+  assert_eq!(detect("1.0.0"), Some(SourceVersion::V1_0_0));
+  assert_eq!(
+    detect("2.0.0-csd.2.beta.2019-01-24"),
+    Some(SourceVersion::V2_0_0)
+  );
+  assert_eq!(detect("2.1.0"), Some(SourceVersion::V2_1_0));
+  assert_eq!(detect("3.0.0"), None);
+}
+
+#[test]
+// Test that a SARIF 1.0.0 log with keyed rules, files and logical locations
+// is upgraded
+fn test_upgrade_v1() -> Result<()> {
+  let sarif = upgrade(serde_json::json!({
+    "version": "1.0.0",
+    "runs": [{
+      "tool": { "name": "legacy", "version": "1.2" },
+      "invocation": { "commandLine": "legacy main.c", "fileName": "legacy" },
+      "files": {
+        "file:///src/main.c": {
+          "mimeType": "text/x-c",
+          "hashes": [{ "algorithm": "sha-256", "value": "abc" }]
+        }
+      },
+      "logicalLocations": { "main": { "name": "main", "kind": "function" } },
+      "rules": {
+        "C2001-1": {
+          "id": "C2001",
+          "shortDescription": "A rule",
+          "messageFormats": { "Default": "{0} is wrong" },
+          "configuration": "enabled",
+          "defaultLevel": "warning"
+        }
+      },
+      "results": [{
+        "ruleKey": "C2001-1",
+        "level": "pass",
+        "formattedRuleMessage": { "formatId": "Default", "arguments": ["x"] },
+        "suppressionStates": ["suppressedInSource"],
+        "baselineState": "existing",
+        "locations": [{
+          "resultFile": {
+            "uri": "file:///src/main.c",
+            "region": { "startLine": 3, "offset": 10, "length": 2 }
+          },
+          "fullyQualifiedLogicalName": "main"
+        }],
+        "codeFlows": [{
+          "locations": [{
+            "step": 1,
+            "physicalLocation": { "uri": "file:///src/main.c" },
+            "message": "here"
+          }]
+        }]
+      }]
+    }]
+  }))?;
+
+  assert_eq!(sarif.version, sarif::Version::V2_1_0);
+  let run = &sarif.runs[0];
+  assert_eq!(run.tool.driver.name, "legacy");
+  let rule = &run.tool.driver.rules.as_ref().unwrap()[0];
+  assert_eq!(rule.id, "C2001");
+  assert_eq!(
+    rule.message_strings.as_ref().unwrap()["Default"].text,
+    "{0} is wrong"
+  );
+  let configuration = rule.default_configuration.as_ref().unwrap();
+  assert_eq!(
+    configuration.level,
+    Some(sarif::ReportingConfigurationLevel::Warning)
+  );
+  assert!(run.invocations.as_ref().unwrap()[0].execution_successful);
+  let artifact = &run.artifacts.as_ref().unwrap()[0];
+  assert_eq!(
+    artifact.location.as_ref().unwrap().uri.as_deref(),
+    Some("file:///src/main.c")
+  );
+
+  let result = &run.results.as_ref().unwrap()[0];
+  assert_eq!(result.rule_id.as_deref(), Some("C2001"));
+  assert_eq!(result.rule_index, Some(0));
+  assert_eq!(result.kind, Some(sarif::ResultKind::Pass));
+  assert_eq!(result.message.id.as_deref(), Some("Default"));
+  assert_eq!(
+    result.baseline_state,
+    Some(sarif::ResultBaselineState::Unchanged)
+  );
+  assert_eq!(result.suppressions.as_ref().map(Vec::len), Some(1));
+  let location = &result.locations.as_ref().unwrap()[0];
+  let physical = location.physical_location.as_ref().unwrap();
+  let region = physical.region.as_ref().unwrap();
+  assert_eq!((region.start_line, region.char_offset), (Some(3), Some(10)));
+  let logical = &location.logical_locations.as_ref().unwrap()[0];
+  assert_eq!(logical.index, Some(0));
+  let thread_flow = &result.code_flows.as_ref().unwrap()[0].thread_flows[0];
+  assert_eq!(thread_flow.locations[0].execution_order, Some(1));
+  Ok(())
+}
+
+#[test]
+// Test that a SARIF 2.0.0 draft log with file locations and rules in its
+// resources is upgraded
+fn test_upgrade_v2_draft() -> Result<()> {
+  let sarif = upgrade(serde_json::json!({
+    "version": "2.0.0-csd.2.beta.2018-10-10",
+    "runs": [{
+      "tool": { "name": "draft" },
+      "resources": {
+        "rules": {
+          "R1": { "id": "R1", "fullDescription": { "text": "A rule" } }
+        }
+      },
+      "originalUriBaseIds": { "SRCROOT": "file:///src/" },
+      "invocations": [{ "toolNotifications": [{ "message": { "text": "hi" } }] }],
+      "results": [{
+        "ruleId": "R1",
+        "message": { "messageId": "default", "arguments": ["a"] },
+        "locations": [{
+          "physicalLocation": {
+            "fileLocation": { "uri": "main.rs", "uriBaseId": "SRCROOT" },
+            "region": { "startLine": 1, "snippet": { "text": "fn main" } }
+          }
+        }]
+      }]
+    }]
+  }))?;
+
+  let run = &sarif.runs[0];
+  let base = &run.original_uri_base_ids.as_ref().unwrap()["SRCROOT"];
+  assert_eq!(base.uri.as_deref(), Some("file:///src/"));
+  let invocation = &run.invocations.as_ref().unwrap()[0];
+  assert!(invocation.tool_execution_notifications.is_some());
+  let result = &run.results.as_ref().unwrap()[0];
+  assert_eq!(result.rule_index, Some(0));
+  assert_eq!(result.message.id.as_deref(), Some("default"));
+  let physical = result.locations.as_ref().unwrap()[0]
+    .physical_location
+    .as_ref()
+    .unwrap();
+  let artifact = physical.artifact_location.as_ref().unwrap();
+  assert_eq!(artifact.uri_base_id.as_deref(), Some("SRCROOT"));
+  Ok(())
+}
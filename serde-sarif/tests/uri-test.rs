@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+use serde_sarif::uri::{UriError, UriResolver, Url};
+
+fn run() -> Result<sarif::Run> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "tool": { "driver": { "name": "clippy" } },
+    "originalUriBaseIds": {
+      "ROOT": { "uri": "file:///root" },
+      "SRC": { "uri": "src", "uriBaseId": "ROOT" },
+      "A": { "uri": "a/", "uriBaseId": "B" },
+      "B": { "uri": "b/", "uriBaseId": "A" },
+      "SRCROOT": {}
+    }
+  }))?)
+}
+
+fn location(uri: &str, uri_base_id: Option<&str>) -> sarif::ArtifactLocation {
+  let mut builder = sarif::ArtifactLocationBuilder::default();
+  builder.uri(uri);
+  if let Some(uri_base_id) = uri_base_id {
+    builder.uri_base_id(uri_base_id);
+  }
+  builder.build().unwrap()
+}
+
+#[test]
+fn test_resolve() -> Result<()> {
+  let run = run()?;
+  let resolver = UriResolver::new()
+    .default_base(Url::parse("file:///work/")?)
+    .uri_base("SRCROOT", Url::parse("file:///checkout")?);
+  let resolve = |uri, id| resolver.resolve(&run, &location(uri, id));
+
+  // base ids without a trailing slash are still directories
+  assert_eq!(
+    resolve("main.rs", Some("SRC"))?.as_str(),
+    "file:///root/src/main.rs"
+  );
+  assert_eq!(
+    resolve("lib.rs", Some("SRCROOT"))?.as_str(),
+    "file:///checkout/lib.rs"
+  );
+  assert_eq!(resolve("lib.rs", None)?.as_str(), "file:///work/lib.rs");
+  // absolute uris ignore their base id
+  assert_eq!(
+    resolve("file:///abs/lib.rs", Some("SRC"))?.as_str(),
+    "file:///abs/lib.rs"
+  );
+  assert_eq!(
+    resolve("C:\\src\\main.c", None)?.as_str(),
+    "file:///C:/src/main.c"
+  );
+  assert!(matches!(
+    resolve("lib.rs", Some("A")),
+    Err(UriError::CyclicUriBaseId(_))
+  ));
+  assert!(matches!(
+    resolve("lib.rs", Some("UNKNOWN")),
+    Err(UriError::UndefinedUriBaseId(_))
+  ));
+  Ok(())
+}
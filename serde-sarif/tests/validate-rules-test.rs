@@ -0,0 +1,137 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+
+#[test]
+// Test that semantically inconsistent logs are reported by rule id
+fn test_validate_rules() -> Result<()> {
+  let sarif: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": "tool",
+          "rules": [{ "id": "CA2101" }, { "id": "CA2101" }]
+        }
+      },
+      "results": [{
+        "message": { "text": "message" },
+        "ruleId": "CA2102",
+        "ruleIndex": 0,
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "src/lib.rs", "uriBaseId": "SRCROOT" },
+            "region": { "startLine": 4, "endLine": 2 }
+          }
+        }]
+      }, {
+        "message": { "text": "message" },
+        "ruleId": "CA2101/md5",
+        "ruleIndex": 2
+      }]
+    }]
+  }))?;
+
+  let violations: Vec<_> = sarif
+    .validate_rules()
+    .into_iter()
+    .map(|violation| (violation.rule.id, violation.pointer))
+    .collect();
+
+  assert_eq!(
+    violations,
+    vec![
+      ("rule-index-out-of-range", "/runs/0/results/1/ruleIndex".into()),
+      ("rule-id-mismatch", "/runs/0/results/0/ruleIndex".into()),
+      ("duplicate-rule-id", "/runs/0/tool/driver/rules/1/id".into()),
+      (
+        "undefined-uri-base-id",
+        "/runs/0/results/0/locations/0/physicalLocation/artifactLocation/uriBaseId"
+          .into()
+      ),
+      (
+        "region-end-before-start",
+        "/runs/0/results/0/locations/0/physicalLocation/region/endLine".into()
+      ),
+    ]
+  );
+
+  Ok(())
+}
+
+#[test]
+// Test that hierarchical rule ids match their parent rule
+fn test_validate_rules_valid() -> Result<()> {
+  let sarif: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "tool", "rules": [{ "id": "CA2101" }] } },
+      "originalUriBaseIds": { "SRCROOT": { "uri": "file:///src/" } },
+      "results": [{
+        "message": { "text": "message" },
+        "ruleId": "CA2101/md5",
+        "ruleIndex": 0,
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "lib.rs", "uriBaseId": "SRCROOT" },
+            "region": { "startLine": 2, "startColumn": 4, "endColumn": 8 }
+          }
+        }]
+      }]
+    }]
+  }))?;
+
+  assert!(sarif.validate_rules().is_empty());
+
+  Ok(())
+}
+
+#[test]
+// Test that message ids are looked up in the rule a result refers to by id
+fn test_validate_message_id() -> Result<()> {
+  let sarif: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": "tool",
+          "rules": [{
+            "id": "R1",
+            "messageStrings": { "default": { "text": "message" } }
+          }]
+        },
+        "extensions": [{
+          "name": "queries",
+          "rules": [{
+            "id": "R2",
+            "messageStrings": { "default": { "text": "message" } }
+          }]
+        }]
+      },
+      "results": [{
+        "message": { "id": "default" },
+        "ruleId": "R1"
+      }, {
+        "message": { "id": "default" },
+        "rule": { "id": "R2", "toolComponent": { "name": "queries" } }
+      }, {
+        "message": { "id": "missing" },
+        "ruleId": "R1"
+      }]
+    }]
+  }))?;
+
+  let violations: Vec<_> = sarif
+    .validate_rules()
+    .into_iter()
+    .map(|violation| (violation.rule.id, violation.pointer))
+    .collect();
+  assert_eq!(
+    violations,
+    vec![(
+      "undefined-message-id",
+      "/runs/0/results/2/message/id".into()
+    )]
+  );
+
+  Ok(())
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+use serde_sarif::validate::{validate_schema, SchemaViolation};
+
+// Returns the violations of a log with one run of `run`, as (pointer, message)
+fn violations(run: serde_json::Value) -> Vec<(String, String)> {
+  validate_schema(&serde_json::json!({ "version": "2.1.0", "runs": [run] }))
+    .into_iter()
+    .map(|SchemaViolation { pointer, message }| (pointer, message))
+    .collect()
+}
+
+fn tool() -> serde_json::Value {
+  serde_json::json!({ "driver": { "name": "tool" } })
+}
+
+#[test]
+// Test that a valid log has no violations
+fn test_valid() -> Result<()> {
+  let sarif: sarif::Sarif = serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": tool(),
+      "results": [{ "message": { "text": "message" }, "level": "error" }]
+    }]
+  }))?;
+  assert_eq!(sarif.validate(), vec![]);
+  Ok(())
+}
+
+#[test]
+// Test that missing required properties are reported where they are missing
+fn test_required() {
+  assert_eq!(
+    violations(serde_json::json!({ "tool": { "driver": {} } })),
+    vec![(
+      "/runs/0/tool/driver".to_string(),
+      "missing required property \"name\"".to_string()
+    )]
+  );
+  assert_eq!(
+    validate_schema(&serde_json::json!({ "runs": [] })),
+    vec![SchemaViolation {
+      pointer: "".into(),
+      message: "missing required property \"version\"".into()
+    }]
+  );
+}
+
+#[test]
+// Test that properties the schema does not define are rejected
+fn test_additional_properties() {
+  assert_eq!(
+    violations(serde_json::json!({ "tool": tool(), "unknown": 1 })),
+    vec![(
+      "/runs/0".to_string(),
+      "additional property \"unknown\" is not allowed".to_string()
+    )]
+  );
+}
+
+#[test]
+// Test that enum and pattern violations are reported
+fn test_enum_and_pattern() {
+  assert_eq!(
+    violations(serde_json::json!({
+      "tool": tool(),
+      "automationDetails": { "guid": "not-a-guid" },
+      "results": [{ "message": { "text": "message" }, "level": "fatal" }]
+    })),
+    vec![
+      (
+        "/runs/0/automationDetails/guid".to_string(),
+        "\"not-a-guid\" does not match pattern \
+         \"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[1-5][0-9a-fA-F]{3}-[89abAB][0-9a-fA-F]{3}-[0-9a-fA-F]{12}$\""
+          .to_string()
+      ),
+      (
+        "/runs/0/results/0/level".to_string(),
+        "\"fatal\" is not one of [\"none\",\"note\",\"warning\",\"error\"]"
+          .to_string()
+      ),
+    ]
+  );
+  // the version nibble and variant bits of a GUID are checked
+  assert_eq!(
+    violations(serde_json::json!({
+      "tool": tool(),
+      "automationDetails": { "guid": "330283d0-7dbf-b1b3-4a65-16dfbc2d179a" }
+    }))
+    .len(),
+    1
+  );
+}
+
+#[test]
+// Test that definitions referred to by $ref are validated, however deep
+fn test_ref() {
+  // runs/items -> run -> results/items -> result -> locations/items ->
+  // location -> physicalLocation -> region -> startLine
+  assert_eq!(
+    violations(serde_json::json!({
+      "tool": tool(),
+      "results": [{
+        "message": { "text": "message" },
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "a.rs" },
+            "region": { "startLine": 0 }
+          }
+        }]
+      }]
+    })),
+    vec![(
+      "/runs/0/results/0/locations/0/physicalLocation/region/startLine"
+        .to_string(),
+      "0 is less than the minimum of 1".to_string()
+    )]
+  );
+  assert_eq!(
+    violations(serde_json::json!({ "tool": { "driver": { "name": 1 } } })),
+    vec![(
+      "/runs/0/tool/driver/name".to_string(),
+      "expected string, found number".to_string()
+    )]
+  );
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use serde_sarif::sarif;
+use serde_sarif::visit::{walk_result_mut, Visit, VisitMut};
+
+fn sarif() -> Result<sarif::Sarif> {
+  Ok(serde_json::from_value(serde_json::json!({
+    "version": "2.1.0",
+    "runs": [{
+      "tool": { "driver": { "name": "clippy" } },
+      "originalUriBaseIds": { "SRCROOT": { "uri": "file:///src/" } },
+      "results": [{
+        "message": { "text": "secret in main.rs" },
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "main.rs", "uriBaseId": "SRCROOT" },
+            "region": { "startLine": 1 }
+          }
+        }],
+        "relatedLocations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "lib.rs", "uriBaseId": "SRCROOT" },
+            "region": { "startLine": 2 }
+          }
+        }]
+      }, {
+        "message": { "text": "secret in lib.rs" }
+      }]
+    }]
+  }))?)
+}
+
+#[derive(Default)]
+struct Counter {
+  artifact_locations: usize,
+  regions: usize,
+}
+
+impl Visit for Counter {
+  fn visit_artifact_location(&mut self, _: &sarif::ArtifactLocation) {
+    self.artifact_locations += 1;
+  }
+
+  fn visit_region(&mut self, _: &sarif::Region) {
+    self.regions += 1;
+  }
+}
+
+#[test]
+fn test_visit() -> Result<()> {
+  let mut counter = Counter::default();
+  counter.visit_sarif(&sarif()?);
+  // the uri base id counts too
+  assert_eq!(counter.artifact_locations, 3);
+  assert_eq!(counter.regions, 2);
+  Ok(())
+}
+
+// redacts result messages, without descending into the results
+struct Redact;
+
+impl VisitMut for Redact {
+  fn visit_message_mut(&mut self, message: &mut sarif::Message) {
+    message.text = Some("redacted".into());
+  }
+
+  fn visit_result_mut(&mut self, result: &mut sarif::Result) {
+    if result.locations.is_some() {
+      walk_result_mut(self, result)
+    }
+  }
+}
+
+#[test]
+fn test_visit_mut() -> Result<()> {
+  let mut sarif = sarif()?;
+  Redact.visit_sarif_mut(&mut sarif);
+  let results = sarif.runs[0].results.as_ref().unwrap();
+  assert_eq!(results[0].message.text.as_deref(), Some("redacted"));
+  assert_eq!(results[1].message.text.as_deref(), Some("secret in lib.rs"));
+  Ok(())
+}
@@ -38,6 +38,15 @@
 //! shellcheck -f json shellscript.sh | shellcheck-sarif
 //! ```
 //!
+//! Pass `--fingerprint` to add the `partialFingerprints` Github code scanning
+//! tracks alerts by, reading the scripts relative to the directory `shellcheck` was
+//! run in.
+//!
+//! Pass `--taxonomy-mapping` to categorize the rules by CWE or the OWASP Top
+//! 10, which are bundled. The mapping file is a JSON object from rule ids to
+//! arrays of taxon ids, ex. `{ "2086": ["CWE-88"] }`. CWE ids missing from the
+//! bundled snapshot are accepted too.
+//!
 //! If you are using Github Actions, SARIF is useful for integrating with
 //! Github Advanced Security (GHAS), which can show code alerts in the
 //! "Security" tab of your repository.
@@ -81,6 +90,9 @@
 
 use anyhow::Result;
 use clap::Parser;
+use serde_sarif::converters::shellcheck::ShellcheckConverter;
+use serde_sarif::converters::{convert, ConvertOptions};
+use serde_sarif::taxonomy::{cwe, owasp_top_ten, TaxonomyMapping};
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 
@@ -98,6 +110,14 @@ struct Args {
   /// output file; writes to stdout if none is given
   #[arg(short, long)]
   output: Option<std::path::PathBuf>,
+  /// add partialFingerprints to each result, reading the source files the
+  /// results refer to
+  #[arg(long)]
+  fingerprint: bool,
+  /// categorize the rules by the taxa of CWE or the OWASP Top 10 given in
+  /// this JSON file, which maps rule ids to arrays of taxon ids
+  #[arg(long, value_name = "FILE")]
+  taxonomy_mapping: Option<std::path::PathBuf>,
 }
 
 fn main() -> Result<()> {
@@ -115,5 +135,17 @@ fn main() -> Result<()> {
   };
   let writer = BufWriter::new(write);
 
-  serde_sarif::converters::shellcheck::parse_to_writer(reader, writer)
+  let options = match args.taxonomy_mapping {
+    Some(path) => ConvertOptions::new()
+      .taxonomies(TaxonomyMapping::load(path, vec![cwe(), owasp_top_ten()])?),
+    None => ConvertOptions::new(),
+  };
+  let options = if args.fingerprint {
+    options.fingerprints(".")
+  } else {
+    options
+  };
+
+  convert(&ShellcheckConverter, reader, writer, &options)?;
+  Ok(())
 }